@@ -0,0 +1,21 @@
+// Fill of the shape behind all other shapes. `STYLE` is defined by the background settings in
+// front of this code. The first color is the color of the shape, the second one is passed in the
+// params. Gradients span the local space from -1 to 1, checkerboard cells are one unit large.
+
+const LINEAR_GRADIENT = 1u;
+const RADIAL_GRADIENT = 2u;
+const CHECKERBOARD = 3u;
+
+fn fill(input: smud::FillInput) -> vec4<f32> {
+    let p = input.pos;
+    var t = 0.0;
+    if STYLE == LINEAR_GRADIENT {
+        t = clamp(p.x * 0.5 + 0.5, 0.0, 1.0);
+    } else if STYLE == RADIAL_GRADIENT {
+        t = clamp(length(p), 0.0, 1.0);
+    } else if STYLE == CHECKERBOARD {
+        let cell = floor(p);
+        t = abs(cell.x + cell.y) % 2.0;
+    }
+    return mix(input.color, input.params, t);
+}
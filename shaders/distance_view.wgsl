@@ -0,0 +1,35 @@
+// Fill that shows the raw distance of a shape. `SPACING` and `HEATMAP` are defined by the
+// distance view settings in front of this code.
+
+const INSIDE = vec3(0.25, 0.55, 0.9);
+const OUTSIDE = vec3(0.9, 0.6, 0.3);
+const EXACT = vec3(0.3, 0.8, 0.35);
+const UNDERESTIMATE = vec3(0.2, 0.35, 0.95);
+const OVERESTIMATE = vec3(0.95, 0.2, 0.2);
+
+fn fill(input: smud::FillInput) -> vec4<f32> {
+    let d = input.distance;
+    let width = fwidth(d);
+
+    // An exact distance changes by one per unit of distance, the size of a pixel in the local
+    // space of the shape converts the screen space derivatives
+    let pixel = vec2(length(dpdx(input.pos)), length(dpdy(input.pos)));
+    let gradient = length(vec2(dpdx(d), dpdy(d)) / max(pixel, vec2(1e-6)));
+
+    var rgb = select(INSIDE, OUTSIDE, d > 0.0);
+    if HEATMAP {
+        rgb = mix(EXACT, UNDERESTIMATE, clamp(1.0 - gradient, 0.0, 1.0));
+        rgb = mix(rgb, OVERESTIMATE, clamp(gradient - 1.0, 0.0, 1.0));
+    }
+
+    // Bands between the contour lines get darker with the distance
+    rgb *= 0.85 + 0.15 * cos(6.2831853 * d / SPACING);
+    rgb *= 1.0 - 0.4 * (1.0 - exp(-0.002 * abs(d)));
+
+    let contour = abs(fract(d / SPACING + 0.5) - 0.5) * SPACING;
+    rgb = mix(rgb, rgb * 0.4, 1.0 - smoothstep(0.0, width, contour));
+
+    let zero = 1.0 - smoothstep(0.0, 1.5 * width, abs(d));
+    rgb = mix(rgb, vec3(1.0), zero);
+    return vec4(rgb, 1.0);
+}
@@ -0,0 +1,28 @@
+#define_import_path smud_demo::globals
+
+// Uniforms that are updated by Bevy every frame. bevy_smud binds them in its view bind group
+// in the same way as Bevy's 2d pipelines do.
+#import bevy_sprite::mesh2d_view_bindings::{view, globals}
+
+// Seconds since startup, wraps to 0 after 1 hour
+fn time() -> f32 {
+    return globals.time;
+}
+
+// Seconds since the previous frame
+fn delta_time() -> f32 {
+    return globals.delta_time;
+}
+
+// Frames since startup
+fn frame_count() -> u32 {
+    return globals.frame_count;
+}
+
+// Size of the viewport in physical pixels
+fn viewport_size() -> vec2<f32> {
+    return view.viewport.zw;
+}
+
+// TODO: `cursor_position()` needs its own uniform, which requires bevy_smud to let the demo extend
+// the bind groups of its pipeline.
@@ -0,0 +1,366 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    camera::visibility::RenderLayers,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    window::PrimaryWindow,
+};
+use bevy_egui::egui;
+use bevy_smud::SmudShape;
+
+use crate::{
+    ShapeCamera, consts,
+    export::status_ui,
+    file::{PendingFile, open_file_ui},
+    shader::create_shader,
+    state::GlobalState,
+    util::convert_color,
+};
+
+/// How the area behind the shapes is filled, the gradients go from the background color to the
+/// second color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BackgroundStyle {
+    Solid,
+    LinearGradient,
+    RadialGradient,
+    /// Shows transparent parts of shapes, the cells stay the same size on screen.
+    Checkerboard,
+}
+
+impl BackgroundStyle {
+    pub const ALL: [BackgroundStyle; 4] = [
+        BackgroundStyle::Solid,
+        BackgroundStyle::LinearGradient,
+        BackgroundStyle::RadialGradient,
+        BackgroundStyle::Checkerboard,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            BackgroundStyle::Solid => "Solid",
+            BackgroundStyle::LinearGradient => "Linear",
+            BackgroundStyle::RadialGradient => "Radial",
+            BackgroundStyle::Checkerboard => "Checkerboard",
+        }
+    }
+}
+
+pub struct BackgroundSettings {
+    pub style: BackgroundStyle,
+    pub second_color: egui::Color32,
+    /// Direction of the linear gradient in radians.
+    pub angle: f32,
+    /// Size of the checkerboard cells in logical pixels.
+    pub cell_size: f32,
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            style: BackgroundStyle::Solid,
+            second_color: consts::DEFAULT_BACKGROUND_SECOND_COLOR,
+            angle: TAU / 4.0,
+            cell_size: consts::DEFAULT_CHECKERBOARD_CELL_SIZE,
+        }
+    }
+}
+
+/// The shape that covers the visible area with the background, it's only rendered by the
+/// [`ShapeCamera`] so exports keep their own background.
+#[derive(Component)]
+pub struct BackgroundShape {
+    linear_gradient: Handle<Shader>,
+    radial_gradient: Handle<Shader>,
+    checkerboard: Handle<Shader>,
+}
+
+/// An image loaded from disk that is shown behind or in front of the shapes for tracing them.
+#[derive(Resource)]
+pub struct ReferenceImage {
+    pub path: String,
+    file: Option<PendingFile>,
+    /// Name of the loaded image and its handle.
+    image: Option<(String, Handle<Image>)>,
+    status: Option<Result<String, String>>,
+    pub visible: bool,
+    pub in_front: bool,
+    /// Position of the center of the image in world space.
+    pub position: Vec2,
+    /// Size of an image pixel in world space.
+    pub scale: f32,
+    pub opacity: f32,
+}
+
+impl Default for ReferenceImage {
+    fn default() -> Self {
+        Self {
+            path: consts::DEFAULT_REFERENCE_IMAGE_PATH.to_owned(),
+            file: None,
+            image: None,
+            status: None,
+            visible: true,
+            in_front: false,
+            position: Vec2::ZERO,
+            scale: 1.0,
+            opacity: 0.5,
+        }
+    }
+}
+
+/// Marks the sprite that shows the [`ReferenceImage`].
+#[derive(Component)]
+pub struct ReferenceSprite;
+
+pub fn spawn_background(
+    commands: &mut Commands,
+    global_state: &mut GlobalState,
+    shaders: &mut Assets<Shader>,
+) {
+    let sdf = create_shader(
+        shaders,
+        global_state,
+        "#import smud\n\nfn sdf(input: smud::SdfInput) -> f32 {\n    return -1.0;\n}\n",
+    );
+    let mut fill = |style: BackgroundStyle| {
+        let code = format!(
+            "#import smud\n\nconst STYLE = {}u;\n\n{}",
+            style as u32,
+            include_str!("../shaders/background.wgsl"),
+        );
+        create_shader(shaders, global_state, &code)
+    };
+    let background = BackgroundShape {
+        linear_gradient: fill(BackgroundStyle::LinearGradient),
+        radial_gradient: fill(BackgroundStyle::RadialGradient),
+        checkerboard: fill(BackgroundStyle::Checkerboard),
+    };
+    let render_layers = RenderLayers::layer(consts::BACKGROUND_RENDER_LAYER);
+
+    commands.spawn((
+        SmudShape {
+            sdf,
+            fill: background.linear_gradient.clone(),
+            ..default()
+        },
+        background,
+        Transform::default(),
+        Visibility::Hidden,
+        render_layers.clone(),
+    ));
+    commands.spawn((
+        Sprite::default(),
+        ReferenceSprite,
+        Transform::default(),
+        Visibility::Hidden,
+        render_layers,
+    ));
+}
+
+/// Moves the background shape to the visible area next to the side panel and applies the style.
+/// Runs after the camera moved, so the edges of the background never become visible.
+pub fn update_background(
+    global_state: Res<GlobalState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Transform, &Projection), With<ShapeCamera>>,
+    background: Single<
+        (
+            &BackgroundShape,
+            &mut SmudShape,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        Without<ShapeCamera>,
+    >,
+) {
+    let (background, mut shape, mut transform, mut visibility) = background.into_inner();
+    let settings = &global_state.background;
+    let (camera_transform, projection) = *camera;
+    let Projection::Orthographic(projection) = projection else {
+        return;
+    };
+
+    let fill = match settings.style {
+        BackgroundStyle::Solid => {
+            visibility.set_if_neq(Visibility::Hidden);
+            return;
+        }
+        BackgroundStyle::LinearGradient => &background.linear_gradient,
+        BackgroundStyle::RadialGradient => &background.radial_gradient,
+        BackgroundStyle::Checkerboard => &background.checkerboard,
+    };
+    visibility.set_if_neq(Visibility::Inherited);
+    if shape.fill != *fill {
+        shape.fill = fill.clone();
+    }
+    shape.color = convert_color(global_state.background_color);
+    shape.params = convert_color(settings.second_color).to_linear().to_vec4();
+
+    let scale = projection.scale;
+    let visible_size =
+        (window.size() - Vec2::X * global_state.side_panel_width).max(Vec2::ONE) * scale;
+    let center =
+        camera_transform.translation.xy() + Vec2::X * global_state.side_panel_width / 2.0 * scale;
+    let half_diagonal = visible_size.length() / 2.0;
+
+    // Gradients span the visible area in every direction, checkerboard cells are one unit large
+    let (rotation, unit) = match settings.style {
+        BackgroundStyle::LinearGradient => (settings.angle, half_diagonal),
+        BackgroundStyle::Checkerboard => (0.0, settings.cell_size.max(1.0) * scale),
+        _ => (0.0, half_diagonal),
+    };
+    *transform = Transform::from_translation(center.extend(consts::BACKGROUND_Z))
+        .with_rotation(Quat::from_rotation_z(rotation))
+        .with_scale(Vec3::splat(unit));
+    shape.bounds = Rectangle::from_length((2.0 * half_diagonal / unit).ceil() + 2.0);
+}
+
+/// Loads chosen images and shows the reference image with its current settings.
+pub fn update_reference_image(
+    mut reference_image: ResMut<ReferenceImage>,
+    mut images: ResMut<Assets<Image>>,
+    sprite: Single<(&mut Sprite, &mut Transform, &mut Visibility), With<ReferenceSprite>>,
+) {
+    if let Some(file) = reference_image.file.as_ref().and_then(PendingFile::take) {
+        reference_image.file = None;
+        match file.and_then(|file| Ok((file.name, load_image(&file.bytes)?))) {
+            Ok((name, image)) => {
+                reference_image.image = Some((name, images.add(image)));
+                reference_image.status = None;
+            }
+            Err(error) => reference_image.status = Some(Err(error)),
+        }
+    }
+
+    let (mut sprite, mut transform, mut visibility) = sprite.into_inner();
+    let Some((_, image)) = reference_image
+        .image
+        .as_ref()
+        .filter(|_| reference_image.visible)
+    else {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+    visibility.set_if_neq(Visibility::Inherited);
+    if sprite.image != *image {
+        sprite.image = image.clone();
+    }
+    sprite.color = Color::WHITE.with_alpha(reference_image.opacity);
+    let z = if reference_image.in_front {
+        consts::REFERENCE_IMAGE_FRONT_Z
+    } else {
+        consts::REFERENCE_IMAGE_BACK_Z
+    };
+    *transform = Transform::from_translation(reference_image.position.extend(z))
+        .with_scale(Vec3::splat(reference_image.scale));
+}
+
+fn load_image(bytes: &[u8]) -> Result<Image, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|error| error.to_string())?
+        .into_rgba8();
+    Ok(Image::new(
+        Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        image.into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    ))
+}
+
+pub fn background_ui(
+    ui: &mut egui::Ui,
+    global_state: &mut GlobalState,
+    reference_image: &mut ReferenceImage,
+) {
+    let settings = &mut global_state.background;
+
+    ui.label("Background:");
+    ui.horizontal(|ui| {
+        for style in BackgroundStyle::ALL {
+            ui.selectable_value(&mut settings.style, style, style.name());
+        }
+    });
+    ui.end_row();
+
+    ui.label("Background color:");
+    ui.horizontal(|ui| {
+        if ui.button("⟲").clicked() {
+            global_state.background_color = consts::DEFAULT_BACKGROUND_COLOR;
+            settings.second_color = consts::DEFAULT_BACKGROUND_SECOND_COLOR;
+        };
+        ui.color_edit_button_srgba(&mut global_state.background_color);
+        if settings.style != BackgroundStyle::Solid {
+            ui.color_edit_button_srgba(&mut settings.second_color);
+        }
+        match settings.style {
+            BackgroundStyle::LinearGradient => {
+                ui.label("angle");
+                ui.drag_angle(&mut settings.angle);
+            }
+            BackgroundStyle::Checkerboard => {
+                ui.label("cell size");
+                ui.add(
+                    egui::DragValue::new(&mut settings.cell_size)
+                        .range(1.0..=f32::MAX)
+                        .speed(1.0),
+                );
+            }
+            _ => {}
+        }
+    });
+    ui.end_row();
+
+    ui.label("Reference image:");
+    ui.vertical(|ui| {
+        if let Some(file) = open_file_ui(ui, &mut reference_image.path, "image/*") {
+            reference_image.file = Some(file);
+        }
+        status_ui(ui, &reference_image.status);
+        if let Some((name, _)) = &reference_image.image {
+            ui.horizontal(|ui| {
+                ui.label(name.as_str());
+                if ui.small_button("🗑").on_hover_text("Remove image").clicked() {
+                    reference_image.image = None;
+                }
+            });
+        }
+    });
+    ui.end_row();
+
+    if reference_image.image.is_none() {
+        return;
+    }
+
+    ui.label("");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut reference_image.visible, "Visible");
+        ui.checkbox(&mut reference_image.in_front, "In front")
+            .on_hover_text("Show the image in front of the shapes instead of behind them");
+        ui.label("opacity");
+        ui.add(egui::Slider::new(&mut reference_image.opacity, 0.0..=1.0));
+    });
+    ui.end_row();
+
+    ui.label("");
+    ui.horizontal(|ui| {
+        ui.label("x");
+        ui.add(egui::DragValue::new(&mut reference_image.position.x).speed(1.0));
+        ui.label("y");
+        ui.add(egui::DragValue::new(&mut reference_image.position.y).speed(1.0));
+        ui.label("scale");
+        ui.add(
+            egui::DragValue::new(&mut reference_image.scale)
+                .range(0.001..=f32::MAX)
+                .speed(0.01),
+        )
+        .on_hover_text("Size of an image pixel in world space");
+    });
+    ui.end_row();
+}
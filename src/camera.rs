@@ -0,0 +1,206 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::egui;
+use bevy_smud::SmudShape;
+
+use crate::{
+    ShapeCamera, consts,
+    shape::{ShapeTransforms, shape_bounds},
+    state::{GlobalState, ShapeState},
+};
+
+/// Position and zoom of the camera, `scale` is the size of a logical pixel in world space.
+#[derive(Clone, Copy, PartialEq)]
+struct CameraView {
+    position: Vec2,
+    scale: f32,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            position: consts::DEFAULT_CAMERA_POSITION,
+            scale: 1.0,
+        }
+    }
+}
+
+struct Bookmark {
+    name: String,
+    view: CameraView,
+}
+
+/// A change of the camera requested by the UI, applied by [`apply_camera_requests`].
+enum CameraRequest {
+    View(CameraView),
+    /// Zooms while keeping the center of the area next to the side panel in place.
+    Zoom(f32),
+    /// Fits the area in world space into the area next to the side panel.
+    Frame(Rect),
+}
+
+/// Moves the camera to views chosen in the UI and remembers bookmarked views for the session.
+#[derive(Resource, Default)]
+pub struct CameraTool {
+    /// The view of the camera in the last frame.
+    current: CameraView,
+    request: Option<CameraRequest>,
+    bookmarks: Vec<Bookmark>,
+    bookmark_name: String,
+}
+
+pub fn apply_camera_requests(
+    mut camera_tool: ResMut<CameraTool>,
+    global_state: Res<GlobalState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&mut Transform, &mut Projection), With<ShapeCamera>>,
+) {
+    let (mut transform, mut projection) = camera.into_inner();
+    let Projection::Orthographic(projection) = &mut *projection else {
+        return;
+    };
+
+    let current = CameraView {
+        position: transform.translation.xy(),
+        scale: projection.scale,
+    };
+    // The center of the visible area is offset from the camera by half of the side panel
+    let visible_offset = |scale: f32| Vec2::X * global_state.side_panel_width / 2.0 * scale;
+    let view = match camera_tool.request.take() {
+        None => current,
+        Some(CameraRequest::View(view)) => view,
+        Some(CameraRequest::Zoom(scale)) => {
+            let center = current.position + visible_offset(current.scale);
+            CameraView {
+                position: center - visible_offset(scale),
+                scale,
+            }
+        }
+        Some(CameraRequest::Frame(area)) => {
+            let visible_size =
+                (window.size() - Vec2::X * global_state.side_panel_width).max(Vec2::ONE);
+            let scale = (area.size() / visible_size).max_element() * consts::CAMERA_FRAME_MARGIN;
+            let scale = if scale > 0.0 { scale } else { current.scale };
+            CameraView {
+                position: area.center() - visible_offset(scale),
+                scale,
+            }
+        }
+    };
+
+    if view != current {
+        transform.translation = view.position.extend(transform.translation.z);
+        projection.scale = view.scale;
+    }
+    camera_tool.current = view;
+}
+
+/// Shows buttons for moving the camera, the zoom and the bookmarked views.
+pub fn camera_ui(
+    ui: &mut egui::Ui,
+    camera_tool: &mut CameraTool,
+    global_state: &GlobalState,
+    shape_query: &Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let transforms =
+        ShapeTransforms::new(shape_query.iter().map(|(_, _, _, shape_state)| shape_state));
+    let bounds = |selected_only: bool| {
+        shape_query
+            .iter()
+            .map(|(_, _, _, shape_state)| shape_state)
+            .filter(|shape_state| {
+                shape_state.visible && (!selected_only || global_state.is_selected(shape_state.id))
+            })
+            .map(|shape_state| shape_bounds(shape_state, &transforms.world(shape_state.id)))
+            .reduce(|a, b| a.union(b))
+    };
+
+    egui::Grid::new("grid_camera")
+        .num_columns(2)
+        .spacing([40.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("View:");
+            ui.horizontal(|ui| {
+                if ui.button("Reset").clicked() {
+                    camera_tool.request = Some(CameraRequest::View(CameraView::default()));
+                }
+                let selected = bounds(true);
+                if ui
+                    .add_enabled(selected.is_some(), egui::Button::new("Frame selected"))
+                    .clicked()
+                    && let Some(area) = selected
+                {
+                    camera_tool.request = Some(CameraRequest::Frame(area));
+                }
+                let all = bounds(false);
+                if ui
+                    .add_enabled(all.is_some(), egui::Button::new("Frame all"))
+                    .clicked()
+                    && let Some(area) = all
+                {
+                    camera_tool.request = Some(CameraRequest::Frame(area));
+                }
+            });
+            ui.end_row();
+
+            ui.label("Zoom:");
+            let mut zoom = 100.0 / camera_tool.current.scale;
+            if ui
+                .add(
+                    egui::DragValue::new(&mut zoom)
+                        .range(consts::MIN_CAMERA_ZOOM..=consts::MAX_CAMERA_ZOOM)
+                        .speed(1.0)
+                        .max_decimals(1)
+                        .suffix(" %"),
+                )
+                .changed()
+            {
+                camera_tool.request = Some(CameraRequest::Zoom(100.0 / zoom));
+            }
+            ui.end_row();
+
+            ui.label("Bookmarks:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut camera_tool.bookmark_name)
+                        .hint_text("name")
+                        .desired_width(120.0),
+                );
+                if ui
+                    .button("Add")
+                    .on_hover_text("Remember the current view")
+                    .clicked()
+                {
+                    let name = if camera_tool.bookmark_name.is_empty() {
+                        format!("view_{}", camera_tool.bookmarks.len())
+                    } else {
+                        std::mem::take(&mut camera_tool.bookmark_name)
+                    };
+                    let view = camera_tool.current;
+                    camera_tool.bookmarks.push(Bookmark { name, view });
+                }
+            });
+            ui.end_row();
+
+            let mut removed = None;
+            for (index, bookmark) in camera_tool.bookmarks.iter().enumerate() {
+                ui.label("");
+                ui.horizontal(|ui| {
+                    if ui.button(&bookmark.name).clicked() {
+                        camera_tool.request = Some(CameraRequest::View(bookmark.view));
+                    }
+                    if ui
+                        .small_button("🗑")
+                        .on_hover_text("Remove bookmark")
+                        .clicked()
+                    {
+                        removed = Some(index);
+                    }
+                });
+                ui.end_row();
+            }
+            if let Some(index) = removed {
+                camera_tool.bookmarks.remove(index);
+            }
+        });
+}
@@ -0,0 +1,81 @@
+use bevy::{
+    prelude::*,
+    reflect::Struct,
+    render::{
+        Render, RenderApp, RenderSystems,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        globals::GlobalsBuffer,
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::export::Exporter;
+
+/// Replaces Bevy's time for the animation of shapes, i.e. for expressions and the shader
+/// module `smud_demo::globals`.
+///
+/// Usually it follows Bevy's time, but during an export it's advanced with a fixed frame rate
+/// independent of the real time.
+#[derive(Resource, Clone, Default, ExtractResource)]
+pub struct Clock {
+    pub elapsed: f32,
+    pub delta: f32,
+    pub frame: u32,
+}
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Clock::default())
+            .add_plugins(ExtractResourcePlugin::<Clock>::default());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(
+                Render,
+                write_globals_buffer
+                    .after(RenderSystems::PrepareResources)
+                    .before(RenderSystems::PrepareBindGroups),
+            );
+        }
+    }
+}
+
+pub fn advance_clock(mut clock: ResMut<Clock>, time: Res<Time>, exporter: Res<Exporter>) {
+    // The exporter controls the clock itself
+    if exporter.is_rendering() {
+        return;
+    }
+
+    clock.delta = time.delta_secs();
+    clock.elapsed += clock.delta;
+    clock.frame = clock.frame.wrapping_add(1);
+}
+
+/// Bevy fills its globals uniform with its own time, so we overwrite the uniform afterwards.
+/// The uniform is shared by all views, so the whole renderer sees our clock instead of Bevy's.
+fn write_globals_buffer(
+    clock: Res<Clock>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut globals_buffer: ResMut<GlobalsBuffer>,
+) {
+    // The fields of the uniform are private, but can be set by reflection
+    let uniform = globals_buffer.buffer.get_mut();
+    let mut set = |name: &str, value: &dyn PartialReflect| {
+        let applied = uniform
+            .field_mut(name)
+            .is_some_and(|field| field.try_apply(value).is_ok());
+        if !applied {
+            warn_once!("Failed to set `{name}` in Bevy's globals uniform");
+        }
+    };
+    // Wraps like `Time::elapsed_secs_wrapped`, which Bevy uses for the uniform
+    set("time", &clock.elapsed.rem_euclid(3600.0));
+    set("delta_time", &clock.delta);
+    set("frame_count", &clock.frame);
+
+    globals_buffer
+        .buffer
+        .write_buffer(&render_device, &render_queue);
+}
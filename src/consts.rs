@@ -8,18 +8,120 @@ pub const DEFAULT_CAMERA_POSITION: Vec2 = {
     // Center in the remaining space not taken by the side panel
     Vec2::new(-SIDE_PANEL_WIDTH / 2.0, 0.0)
 };
+/// Framed shapes fill the visible area except for this margin.
+pub const CAMERA_FRAME_MARGIN: f32 = 1.1;
+/// Range of the zoom in percent that can be typed in.
+pub const MIN_CAMERA_ZOOM: f32 = 1.0;
+pub const MAX_CAMERA_ZOOM: f32 = 10000.0;
+/// Minor grid lines are at least this many logical pixels apart.
+pub const GRID_MIN_SPACING: f32 = 10.0;
+pub const GRID_X_AXIS_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 80, 80);
+pub const GRID_Y_AXIS_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 200, 80);
+/// Width of the rulers along the edges of the viewport in logical pixels.
+pub const RULER_SIZE: f32 = 18.0;
+pub const DEFAULT_POSITION_SNAP: f32 = 10.0;
+pub const DEFAULT_ROTATION_SNAP: f32 = std::f32::consts::TAU / 24.0;
+pub const DEFAULT_SCALE_SNAP: f32 = 0.1;
+/// Moved shapes snap to other shapes within this many logical pixels.
+pub const SHAPE_SNAP_DISTANCE: f32 = 8.0;
+/// Distance between contour lines of the distance view.
+pub const DEFAULT_DISTANCE_VIEW_SPACING: f32 = 20.0;
+/// Radius of the markers of pinned probes in logical pixels.
+pub const PROBE_MARKER_RADIUS: f32 = 5.0;
+pub const PROBE_TOOLTIP_OFFSET: egui::Vec2 = egui::vec2(16.0, 16.0);
 pub const DEFAULT_BACKGROUND_COLOR: egui::Color32 = {
     // Same as default ClearColor
     egui::Color32::from_rgb(43, 44, 47)
 };
+/// End color of gradients and the color of every other checkerboard cell.
+pub const DEFAULT_BACKGROUND_SECOND_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 92, 98);
+pub const DEFAULT_CHECKERBOARD_CELL_SIZE: f32 = 16.0;
+/// Render layer of the background and the reference image, which are hidden from exports.
+pub const BACKGROUND_RENDER_LAYER: usize = 1;
+/// Render layer of the image of the shapes, if they are rendered at a different scale.
+pub const DISPLAY_RENDER_LAYER: usize = 2;
+/// Render layer of the export camera if only the selected shape is exported.
+pub const EXPORT_RENDER_LAYER: usize = 3;
+/// Depths within the range visible to the camera, behind or in front of all shapes.
+pub const BACKGROUND_Z: f32 = -999.0;
+pub const REFERENCE_IMAGE_BACK_Z: f32 = -998.0;
+pub const REFERENCE_IMAGE_FRONT_Z: f32 = 998.0;
+/// Same as the intensity of `Bloom::NATURAL`.
+pub const DEFAULT_BLOOM_INTENSITY: f32 = 0.15;
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+pub const MAX_RENDER_SCALE: f32 = 4.0;
+pub const PERFORMANCE_WINDOW_WIDTH: f32 = 420.0;
+pub const PERFORMANCE_PLOT_HEIGHT: f32 = 40.0;
+/// Number of shader compilations listed in the performance window.
+pub const MAX_PERFORMANCE_COMPILATIONS: usize = 20;
+pub const DEFAULT_STRESS_TEST_COUNT: u32 = 1000;
+pub const MAX_STRESS_TEST_COUNT: u32 = 100_000;
+/// Distance between instances in world space.
+pub const DEFAULT_STRESS_TEST_SPACING: f32 = 50.0;
+/// Frames after spawning that are left out of the frame time statistics.
+pub const STRESS_TEST_WARMUP_FRAMES: u32 = 30;
+/// Number of frame times the statistics of the stress test are computed from.
+pub const MAX_STRESS_TEST_SAMPLES: usize = 1000;
+/// Maximum speed of the animated instances in radians per second.
+pub const STRESS_TEST_MAX_SPEED: f32 = 2.0;
+pub const GOLDEN_RATIO: f32 = 1.618_034;
 pub const DEFAULT_SHAPE_POSITION: Vec3 = Vec3::ZERO;
 pub const DEFAULT_SHAPE_ROTATION: f32 = 0.0;
 pub const DEFAULT_SHAPE_SCALE: f32 = 1.0;
 pub const DEFAULT_SHAPE_BOUNDS_LENGTH: f32 = 500.0;
-pub const DEFAULT_SHAPE_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 100, 100);
+pub const DEFAULT_SHAPE_COLOR: Srgba = Srgba::new(200.0 / 255.0, 100.0 / 255.0, 100.0 / 255.0, 1.0);
 pub const DEFAULT_SHAPE_PARAMS: Vec4 = Vec4::ZERO;
 pub const DEFAULT_SHAPE_BLEND_MODE: BlendMode = BlendMode::Alpha;
 pub const DEFAULT_SDF_TEMPLATE: &str = "circle";
 pub const DEFAULT_FILL_TEMPLATE: &str = "cubic_falloff";
 pub static SDF_TEMPLATE_DIR: include_dir::Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/sdf");
 pub static FILL_TEMPLATE_DIR: include_dir::Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/fill");
+pub const EXPRESSION_HELP: &str = "\
+Variables: t, dt, frame, mouse_x, mouse_y, pi, tau, shape_<id>.<field>
+Fields: x, y, z, rotation, scale, params_x..params_w, color_r..color_a (0 to 1)
+Operators: + - * / % ^
+Functions: sin, cos, tan, asin, acos, atan, atan2, abs, sign, sqrt, exp, ln, \
+floor, ceil, round, fract, min, max, clamp, mix, step, smoothstep";
+pub const DEFAULT_IMAGE_EXPORT_PATH: &str = "./export/image.png";
+pub const DEFAULT_ANIMATION_EXPORT_PATH: &str = "./export/animation.gif";
+pub const DEFAULT_SVG_IMPORT_PATH: &str = "./import/icon.svg";
+pub const DEFAULT_IMAGE_IMPORT_PATH: &str = "./import/logo.png";
+pub const DEFAULT_REFERENCE_IMAGE_PATH: &str = "./import/reference.png";
+pub const DEFAULT_SVG_EXPORT_PATH: &str = "./export/shapes.svg";
+/// File that stores user preferences like the input scheme, the browser uses local storage.
+pub const PREFERENCES_PATH: &str = "./preferences.txt";
+/// Time until the URL of a downloaded file is revoked, browsers need it after the click.
+pub const REVOKE_DOWNLOAD_URL_DELAY_MS: i32 = 10_000;
+pub const INPUT_SCHEME_PREFERENCE: &str = "input_scheme";
+pub const MAX_EXPORT_RESOLUTION: u32 = 8192;
+pub const EXPORT_WARMUP_FRAMES: u32 = 2;
+/// Polygon points turning sharper than this are exported as corners instead of smooth curves.
+pub const SVG_CORNER_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+pub const SVG_SDF_IMPORT_PATH: &str = "smud_demo::sdf::svg";
+pub const IMPORTED_SHAPE_BOUNDS_FACTOR: f32 = 1.2;
+/// Outlines with more segments are likely to make the shader too slow.
+pub const OUTLINE_SEGMENT_WARNING: usize = 256;
+pub const DRAWING_SDF_IMPORT_PATH: &str = "smud_demo::sdf::drawing";
+/// Radius in logical pixels around a drawn point in which it can be dragged.
+pub const DRAW_POINT_RADIUS: f32 = 8.0;
+/// Dragging less than this many logical pixels selects the clicked shape instead of a box.
+pub const BOX_SELECT_MIN_DISTANCE: f32 = 4.0;
+pub const OUTLINER_MAX_HEIGHT: f32 = 200.0;
+/// Indentation per level of the shape hierarchy in the outliner.
+pub const OUTLINER_INDENT: f32 = 12.0;
+/// With precise picking, shapes are picked up to this many logical pixels outside of their area.
+pub const DEFAULT_PICKING_THRESHOLD: f32 = 4.0;
+pub const LAYERS_MAX_HEIGHT: f32 = 200.0;
+/// Distance in world space between neighbouring layers when the z values are rewritten, the
+/// camera only shows depths between -1000 and 1000.
+pub const LAYER_Z_STEP: f32 = 0.1;
+pub const DEFAULT_FONT_PATH: &str = "./import/font.ttf";
+pub const DEFAULT_TEXT: &str = "smud";
+/// Font size in world space, the height of an em.
+pub const DEFAULT_TEXT_SIZE: f32 = 100.0;
+/// Maximum deviation of flattened glyph curves in world space.
+pub const TEXT_TOLERANCE: f32 = 0.25;
+pub const TEXT_SDF_IMPORT_PATH: &str = "smud_demo::sdf::text";
+pub const IMAGE_SDF_IMPORT_PATH: &str = "smud_demo::sdf::image";
+/// Distance grids with more samples are likely to make the shader slow to compile.
+pub const DISTANCE_GRID_SAMPLE_WARNING: usize = 16384;
@@ -0,0 +1,1405 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use bevy::{
+    ecs::system::SystemParam,
+    platform::time::Instant,
+    prelude::*,
+    shader::{Shader, ShaderImport},
+};
+use bevy_smud::SmudShape;
+use naga::{
+    Arena, BinaryOperator, DerivativeAxis, Expression, Literal, MathFunction, Module,
+    RelationalFunction, ScalarKind, Statement, SwitchValue, TypeInner, UnaryOperator,
+};
+use naga_oil::compose::{Composer, NagaModuleDescriptor};
+
+use crate::clock::Clock;
+
+/// Shader functions of shapes compiled for evaluation on the CPU, cached by shader asset.
+#[derive(Resource, Default)]
+pub struct CpuShaders {
+    functions: HashMap<AssetId<Shader>, Result<Arc<CpuFunction>, String>>,
+    /// How long composing and parsing each shader took.
+    compile_times: HashMap<AssetId<Shader>, Duration>,
+}
+
+impl CpuShaders {
+    /// Returns the function `name` (i.e. `sdf` or `fill`) of the given shader.
+    pub fn get(
+        &mut self,
+        shaders: &Assets<Shader>,
+        shader: &Handle<Shader>,
+        name: &str,
+    ) -> Result<Arc<CpuFunction>, String> {
+        // Shaders are replaced by new assets when recompiled, so we forget the old ones
+        self.functions.retain(|id, _| shaders.contains(*id));
+        self.compile_times.retain(|id, _| shaders.contains(*id));
+
+        let shader_id = shader.id();
+        self.functions
+            .entry(shader_id)
+            .or_insert_with(|| {
+                let shader = shaders
+                    .get(shader)
+                    .ok_or_else(|| "Shader is not loaded".to_owned())?;
+                let start = Instant::now();
+                let function = CpuFunction::compile(shaders, shader, name).map(Arc::new);
+                self.compile_times.insert(shader_id, start.elapsed());
+                function
+            })
+            .clone()
+    }
+
+    /// Returns how long compiling the shader took, if it was compiled by [`CpuShaders::get`].
+    pub fn compile_time(&self, shader: &Handle<Shader>) -> Option<Duration> {
+        self.compile_times.get(&shader.id()).copied()
+    }
+}
+
+/// The resources for evaluating the shaders of shapes on the CPU at the time of the [`Clock`].
+#[derive(SystemParam)]
+pub struct ShapeEvaluator<'w> {
+    cpu_shaders: ResMut<'w, CpuShaders>,
+    shaders: Res<'w, Assets<Shader>>,
+    clock: Res<'w, Clock>,
+}
+
+impl ShapeEvaluator<'_> {
+    /// Returns the function `name` of the given shader, see [`CpuShaders::get`].
+    pub fn get(&mut self, shader: &Handle<Shader>, name: &str) -> Result<Arc<CpuFunction>, String> {
+        self.cpu_shaders.get(&self.shaders, shader, name)
+    }
+
+    pub fn globals(&self, window: &Window) -> GlobalInputs {
+        GlobalInputs::new(&self.clock, window)
+    }
+}
+
+/// Values of the uniforms provided by `smud_demo::globals`.
+#[derive(Clone, Copy, Default)]
+pub struct GlobalInputs {
+    pub time: f32,
+    pub delta_time: f32,
+    pub frame_count: u32,
+    pub viewport: Vec4,
+}
+
+impl GlobalInputs {
+    pub fn new(clock: &Clock, window: &Window) -> Self {
+        Self {
+            time: clock.elapsed,
+            delta_time: clock.delta,
+            frame_count: clock.frame,
+            viewport: Vec4::new(
+                0.0,
+                0.0,
+                window.physical_width() as f32,
+                window.physical_height() as f32,
+            ),
+        }
+    }
+}
+
+/// Inputs for evaluating `sdf` or `fill` of a shape.
+#[derive(Clone, Copy)]
+pub struct ShapeInputs {
+    pub params: Vec4,
+    pub color: Vec4,
+    /// Size of a pixel in the local space of the shape, used for approximating derivatives like
+    /// `fwidth`.
+    pub pixel_size: f32,
+    pub globals: GlobalInputs,
+}
+
+impl ShapeInputs {
+    /// Returns the inputs of a shape with the given params, `pixel_size` is in world space.
+    pub fn new(
+        params: Vec4,
+        shape: &SmudShape,
+        world: &GlobalTransform,
+        pixel_size: f32,
+        globals: GlobalInputs,
+    ) -> Self {
+        Self {
+            params,
+            color: shape.color.to_linear().to_vec4(),
+            pixel_size: pixel_size / world.scale().x,
+            globals,
+        }
+    }
+}
+
+/// A function of a WGSL shader that can be evaluated on the CPU by interpreting naga's IR.
+pub struct CpuFunction {
+    module: Module,
+    function: naga::Handle<naga::Function>,
+}
+
+impl CpuFunction {
+    fn compile(shaders: &Assets<Shader>, shader: &Shader, name: &str) -> Result<Self, String> {
+        let import_path_shaders: HashMap<_, _> = shaders
+            .iter()
+            .filter_map(|(_, shader)| match &shader.import_path {
+                ShaderImport::Custom(_) => {
+                    Some((shader.import_path.module_name().into_owned(), shader))
+                }
+                ShaderImport::AssetPath(_) => None,
+            })
+            .collect();
+
+        let mut composer = Composer::default();
+        for import in &shader.imports {
+            add_import_to_composer(&mut composer, &import_path_shaders, import)?;
+        }
+
+        // The shader can't be composed with its own import path
+        let source: String = shader
+            .source
+            .as_str()
+            .lines()
+            .filter(|line| !line.contains("#define_import_path"))
+            .flat_map(|line| [line, "\n"])
+            .collect();
+        let module = composer
+            .make_naga_module(NagaModuleDescriptor {
+                source: &source,
+                file_path: &shader.path,
+                ..default()
+            })
+            .map_err(|error| error.emit_to_string(&composer))?;
+
+        let function = module
+            .functions
+            .iter()
+            .find(|(_, function)| function.name.as_deref() == Some(name))
+            .map(|(handle, _)| handle)
+            .ok_or_else(|| format!("There is no function `{name}`"))?;
+
+        Ok(Self { module, function })
+    }
+
+    /// Returns the number of expressions in the function and the functions it calls, as a rough
+    /// measure of its cost.
+    pub fn instruction_count(&self) -> usize {
+        let mut visited = HashSet::new();
+        let mut pending = vec![self.function];
+        let mut count = 0;
+        while let Some(handle) = pending.pop() {
+            if !visited.insert(handle) {
+                continue;
+            }
+            let function = &self.module.functions[handle];
+            count += function.expressions.len();
+            pending.extend(function.expressions.iter().filter_map(
+                |(_, expression)| match expression {
+                    Expression::CallResult(called) => Some(*called),
+                    _ => None,
+                },
+            ));
+        }
+        count
+    }
+
+    pub fn sdf(&self, pos: Vec2, inputs: &ShapeInputs) -> Result<f32, String> {
+        let input = self.input(inputs, |member| match member {
+            "pos" => Some(vec_value(pos.to_array())),
+            _ => None,
+        })?;
+        match self.call(input, inputs)? {
+            Value::Scalar(Scalar::F32(distance)) => Ok(distance),
+            value => Err(format!("`sdf` returned {value:?} instead of f32")),
+        }
+    }
+
+    /// Returns the RGBA color of the fill at the local position with the given distance.
+    pub fn fill(&self, pos: Vec2, distance: f32, inputs: &ShapeInputs) -> Result<Vec4, String> {
+        let input = self.input(inputs, |member| match member {
+            "pos" => Some(vec_value(pos.to_array())),
+            "distance" => Some(Value::Scalar(Scalar::F32(distance))),
+            _ => None,
+        })?;
+        match self.call(input, inputs)? {
+            Value::Vector(components) if components.len() == 4 => {
+                let [r, g, b, a] = [0, 1, 2, 3].map(|index| components[index].as_f32());
+                Ok(Vec4::new(r, g, b, a))
+            }
+            value => Err(format!("`fill` returned {value:?} instead of vec4<f32>")),
+        }
+    }
+
+    /// Creates the input struct (`smud::SdfInput` or `smud::FillInput`) by member name.
+    fn input(
+        &self,
+        inputs: &ShapeInputs,
+        member_value: impl Fn(&str) -> Option<Value>,
+    ) -> Result<Value, String> {
+        let function = &self.module.functions[self.function];
+        let argument = function
+            .arguments
+            .first()
+            .ok_or("The function has no input argument")?;
+        let TypeInner::Struct { members, .. } = &self.module.types[argument.ty].inner else {
+            return Err("The input argument is not a struct".to_owned());
+        };
+        let mut values = Vec::new();
+        for member in members {
+            let name = member.name.as_deref().unwrap_or_default();
+            let value = match member_value(name) {
+                Some(value) => value,
+                None => match name {
+                    "params" => vec_value(inputs.params.to_array()),
+                    "color" => vec_value(inputs.color.to_array()),
+                    _ => zero_value(&self.module, member.ty)?,
+                },
+            };
+            values.push(value);
+        }
+        Ok(Value::Composite(values))
+    }
+
+    fn call(&self, input: Value, inputs: &ShapeInputs) -> Result<Value, String> {
+        let mut interpreter = Interpreter::new(&self.module, inputs)?;
+        interpreter
+            .call(self.function, vec![input])?
+            .ok_or_else(|| "The function returned no value".to_owned())
+    }
+}
+
+fn add_import_to_composer(
+    composer: &mut Composer,
+    import_path_shaders: &HashMap<String, &Shader>,
+    import: &ShaderImport,
+) -> Result<(), String> {
+    let module_name = import.module_name();
+    if composer.contains_module(&module_name) {
+        return Ok(());
+    }
+
+    let shader = import_path_shaders
+        .get(module_name.as_ref())
+        .ok_or_else(|| format!("Unknown import `{module_name}`"))?;
+    for import in &shader.imports {
+        add_import_to_composer(composer, import_path_shaders, import)?;
+    }
+
+    composer
+        .add_composable_module((*shader).into())
+        .map(|_| ())
+        .map_err(|error| error.emit_to_string(composer))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+}
+
+impl Scalar {
+    fn as_f32(self) -> f32 {
+        match self {
+            Scalar::Bool(b) => b as u8 as f32,
+            Scalar::I32(i) => i as f32,
+            Scalar::U32(u) => u as f32,
+            Scalar::F32(f) => f,
+        }
+    }
+
+    fn as_bool(self) -> Result<bool, String> {
+        match self {
+            Scalar::Bool(b) => Ok(b),
+            scalar => Err(format!("Expected bool, found {scalar:?}")),
+        }
+    }
+
+    fn as_index(self) -> Result<usize, String> {
+        match self {
+            Scalar::I32(i) => Ok(i.max(0) as usize),
+            Scalar::U32(u) => Ok(u as usize),
+            scalar => Err(format!("Expected integer, found {scalar:?}")),
+        }
+    }
+
+    fn convert(self, kind: ScalarKind) -> Result<Scalar, String> {
+        Ok(match kind {
+            ScalarKind::Bool => Scalar::Bool(match self {
+                Scalar::Bool(b) => b,
+                Scalar::I32(i) => i != 0,
+                Scalar::U32(u) => u != 0,
+                Scalar::F32(f) => f != 0.0,
+            }),
+            ScalarKind::Sint | ScalarKind::AbstractInt => Scalar::I32(match self {
+                Scalar::Bool(b) => b as i32,
+                Scalar::I32(i) => i,
+                Scalar::U32(u) => u as i32,
+                Scalar::F32(f) => f as i32,
+            }),
+            ScalarKind::Uint => Scalar::U32(match self {
+                Scalar::Bool(b) => b as u32,
+                Scalar::I32(i) => i as u32,
+                Scalar::U32(u) => u,
+                Scalar::F32(f) => f as u32,
+            }),
+            ScalarKind::Float | ScalarKind::AbstractFloat => Scalar::F32(self.as_f32()),
+        })
+    }
+
+    fn bitcast(self, kind: ScalarKind) -> Result<Scalar, String> {
+        let bits = match self {
+            Scalar::I32(i) => i as u32,
+            Scalar::U32(u) => u,
+            Scalar::F32(f) => f.to_bits(),
+            Scalar::Bool(_) => return Err("Bool can't be bitcasted".to_owned()),
+        };
+        Ok(match kind {
+            ScalarKind::Sint | ScalarKind::AbstractInt => Scalar::I32(bits as i32),
+            ScalarKind::Uint => Scalar::U32(bits),
+            ScalarKind::Float | ScalarKind::AbstractFloat => Scalar::F32(f32::from_bits(bits)),
+            ScalarKind::Bool => return Err("Can't bitcast to bool".to_owned()),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    Scalar(Scalar),
+    Vector(Vec<Scalar>),
+    /// Structs, arrays and matrices (as columns)
+    Composite(Vec<Value>),
+    Pointer(Pointer),
+}
+
+fn vec_value<const N: usize>(components: [f32; N]) -> Value {
+    Value::Vector(components.into_iter().map(Scalar::F32).collect())
+}
+
+impl Value {
+    fn scalar(&self) -> Result<Scalar, String> {
+        match self {
+            Value::Scalar(scalar) => Ok(*scalar),
+            value => Err(format!("Expected scalar, found {value:?}")),
+        }
+    }
+
+    fn components(&self) -> Result<Vec<Scalar>, String> {
+        match self {
+            Value::Scalar(scalar) => Ok(vec![*scalar]),
+            Value::Vector(scalars) => Ok(scalars.clone()),
+            value => Err(format!("Expected scalar or vector, found {value:?}")),
+        }
+    }
+
+    fn from_components(mut components: Vec<Scalar>, vector: bool) -> Value {
+        if vector {
+            Value::Vector(components)
+        } else {
+            Value::Scalar(components.remove(0))
+        }
+    }
+
+    fn get(&self, index: usize) -> Result<Value, String> {
+        match self {
+            Value::Vector(scalars) => scalars
+                .get(index)
+                .map(|scalar| Value::Scalar(*scalar))
+                .ok_or_else(|| format!("Index {index} out of bounds")),
+            Value::Composite(values) => values
+                .get(index)
+                .cloned()
+                .ok_or_else(|| format!("Index {index} out of bounds")),
+            value => Err(format!("Can't index {value:?}")),
+        }
+    }
+
+    fn get_mut(&mut self, index: usize) -> Result<&mut Value, String> {
+        match self {
+            Value::Composite(values) => values
+                .get_mut(index)
+                .ok_or_else(|| format!("Index {index} out of bounds")),
+            value => Err(format!("Can't index {value:?}")),
+        }
+    }
+
+    fn store(&mut self, path: &[usize], value: Value) -> Result<(), String> {
+        match path {
+            [] => *self = value,
+            [index] if matches!(self, Value::Vector(_)) => {
+                let Value::Vector(scalars) = self else {
+                    unreachable!()
+                };
+                let scalar = scalars
+                    .get_mut(*index)
+                    .ok_or_else(|| format!("Index {index} out of bounds"))?;
+                *scalar = value.scalar()?;
+            }
+            [index, rest @ ..] => self.get_mut(*index)?.store(rest, value)?,
+        }
+        Ok(())
+    }
+
+    fn load(&self, path: &[usize]) -> Result<Value, String> {
+        match path {
+            [] => Ok(self.clone()),
+            [index, rest @ ..] => self.get(*index)?.load(rest),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Pointer {
+    root: Root,
+    path: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Root {
+    Local { frame: usize, index: usize },
+    Global(usize),
+}
+
+fn zero_value(module: &Module, ty: naga::Handle<naga::Type>) -> Result<Value, String> {
+    let zero = |scalar: naga::Scalar| match scalar.kind {
+        ScalarKind::Bool => Scalar::Bool(false),
+        ScalarKind::Sint | ScalarKind::AbstractInt => Scalar::I32(0),
+        ScalarKind::Uint => Scalar::U32(0),
+        ScalarKind::Float | ScalarKind::AbstractFloat => Scalar::F32(0.0),
+    };
+    Ok(match &module.types[ty].inner {
+        TypeInner::Scalar(scalar) | TypeInner::Atomic(scalar) => Value::Scalar(zero(*scalar)),
+        TypeInner::Vector { size, scalar } => Value::Vector(vec![zero(*scalar); *size as usize]),
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => Value::Composite(vec![
+            Value::Vector(vec![zero(*scalar); *rows as usize]);
+            *columns as usize
+        ]),
+        TypeInner::Array {
+            base,
+            size: naga::ArraySize::Constant(size),
+            ..
+        } => Value::Composite(vec![zero_value(module, *base)?; size.get() as usize]),
+        TypeInner::Struct { members, .. } => Value::Composite(
+            members
+                .iter()
+                .map(|member| zero_value(module, member.ty))
+                .collect::<Result<_, _>>()?,
+        ),
+        // Textures of imported bindings, like the tonemapping LUT of the view bindings, are
+        // only an error if they are sampled
+        TypeInner::Image { .. } | TypeInner::Sampler { .. } => Value::Composite(Vec::new()),
+        inner => return Err(format!("Unsupported type {inner:?}")),
+    })
+}
+
+/// Evaluation state of a function call, or of the module's global expressions.
+struct Frame<'a> {
+    expressions: &'a Arena<Expression>,
+    values: Vec<Option<Value>>,
+    arguments: Vec<Value>,
+    index: usize,
+}
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+struct Interpreter<'a> {
+    module: &'a Module,
+    inputs: &'a ShapeInputs,
+    globals: Vec<Value>,
+    /// Lazily evaluated values of the module's constants.
+    constants: Vec<Option<Value>>,
+    locals: Vec<Vec<Value>>,
+    steps: usize,
+}
+
+const MAX_STEPS: usize = 1_000_000;
+
+impl<'a> Interpreter<'a> {
+    fn new(module: &'a Module, inputs: &'a ShapeInputs) -> Result<Self, String> {
+        let mut interpreter = Self {
+            module,
+            inputs,
+            globals: Vec::new(),
+            constants: vec![None; module.constants.len()],
+            locals: Vec::new(),
+            steps: 0,
+        };
+        let mut frame = interpreter.global_frame();
+        for (_, global) in module.global_variables.iter() {
+            let mut value = match global.init {
+                Some(init) => interpreter.eval(&mut frame, init)?,
+                None => zero_value(module, global.ty)?,
+            };
+            interpreter.init_uniform(global.ty, &mut value);
+            interpreter.globals.push(value);
+        }
+        Ok(interpreter)
+    }
+
+    fn global_frame(&self) -> Frame<'a> {
+        Frame {
+            expressions: &self.module.global_expressions,
+            values: vec![None; self.module.global_expressions.len()],
+            arguments: Vec::new(),
+            index: usize::MAX,
+        }
+    }
+
+    /// Fills Bevy's `Globals` and `View` uniforms as far as we know them.
+    fn init_uniform(&self, ty: naga::Handle<naga::Type>, value: &mut Value) {
+        let TypeInner::Struct { members, .. } = &self.module.types[ty].inner else {
+            return;
+        };
+        let globals = &self.inputs.globals;
+        for (index, member) in members.iter().enumerate() {
+            let member_value = match member.name.as_deref() {
+                Some("time") => Value::Scalar(Scalar::F32(globals.time)),
+                Some("delta_time") => Value::Scalar(Scalar::F32(globals.delta_time)),
+                Some("frame_count") => Value::Scalar(Scalar::U32(globals.frame_count)),
+                Some("viewport") => vec_value(globals.viewport.to_array()),
+                _ => continue,
+            };
+            let _ = value.store(&[index], member_value);
+        }
+    }
+
+    fn call(
+        &mut self,
+        function: naga::Handle<naga::Function>,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, String> {
+        let function = &self.module.functions[function];
+        let mut frame = Frame {
+            expressions: &function.expressions,
+            values: vec![None; function.expressions.len()],
+            arguments,
+            index: self.locals.len(),
+        };
+
+        let mut locals = Vec::new();
+        for (_, local) in function.local_variables.iter() {
+            locals.push(match local.init {
+                Some(init) => self.eval(&mut frame, init)?,
+                None => zero_value(self.module, local.ty)?,
+            });
+        }
+        self.locals.push(locals);
+
+        let flow = self.exec(&mut frame, &function.body);
+        self.locals.pop();
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(None),
+        }
+    }
+
+    fn exec(&mut self, frame: &mut Frame, block: &naga::Block) -> Result<Flow, String> {
+        for statement in block.iter() {
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return Err("Evaluation takes too long".to_owned());
+            }
+
+            let flow = match statement {
+                Statement::Emit(range) => {
+                    for handle in range.clone() {
+                        let value = self.compute(frame, handle)?;
+                        frame.values[handle.index()] = Some(value);
+                    }
+                    Flow::Next
+                }
+                Statement::Block(block) => self.exec(frame, block)?,
+                Statement::If {
+                    condition,
+                    accept,
+                    reject,
+                } => {
+                    if self.eval(frame, *condition)?.scalar()?.as_bool()? {
+                        self.exec(frame, accept)?
+                    } else {
+                        self.exec(frame, reject)?
+                    }
+                }
+                Statement::Switch { selector, cases } => {
+                    let selector = self.eval(frame, *selector)?.scalar()?;
+                    let start = cases
+                        .iter()
+                        .position(|case| match case.value {
+                            SwitchValue::I32(i) => selector == Scalar::I32(i),
+                            SwitchValue::U32(u) => selector == Scalar::U32(u),
+                            SwitchValue::Default => false,
+                        })
+                        .or_else(|| {
+                            cases
+                                .iter()
+                                .position(|case| case.value == SwitchValue::Default)
+                        });
+                    let mut flow = Flow::Next;
+                    if let Some(start) = start {
+                        for case in &cases[start..] {
+                            flow = self.exec(frame, &case.body)?;
+                            if !case.fall_through || !matches!(flow, Flow::Next) {
+                                break;
+                            }
+                        }
+                    }
+                    match flow {
+                        Flow::Break => Flow::Next,
+                        flow => flow,
+                    }
+                }
+                Statement::Loop {
+                    body,
+                    continuing,
+                    break_if,
+                } => loop {
+                    match self.exec(frame, body)? {
+                        Flow::Break => break Flow::Next,
+                        Flow::Return(value) => break Flow::Return(value),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                    if let Flow::Return(value) = self.exec(frame, continuing)? {
+                        break Flow::Return(value);
+                    }
+                    if let Some(break_if) = break_if
+                        && self.eval(frame, *break_if)?.scalar()?.as_bool()?
+                    {
+                        break Flow::Next;
+                    }
+                    self.steps += 1;
+                    if self.steps > MAX_STEPS {
+                        return Err("Evaluation takes too long".to_owned());
+                    }
+                },
+                Statement::Break => Flow::Break,
+                Statement::Continue => Flow::Continue,
+                Statement::Return { value } => Flow::Return(match value {
+                    Some(value) => Some(self.eval(frame, *value)?),
+                    None => None,
+                }),
+                Statement::Kill => return Err("`discard` is not supported".to_owned()),
+                Statement::Store { pointer, value } => {
+                    let Value::Pointer(pointer) = self.eval(frame, *pointer)? else {
+                        return Err("Store to non-pointer".to_owned());
+                    };
+                    let value = self.eval(frame, *value)?;
+                    self.root_mut(pointer.root)?.store(&pointer.path, value)?;
+                    Flow::Next
+                }
+                Statement::Call {
+                    function,
+                    arguments,
+                    result,
+                } => {
+                    let arguments = arguments
+                        .iter()
+                        .map(|argument| self.eval(frame, *argument))
+                        .collect::<Result<_, _>>()?;
+                    let value = self.call(*function, arguments)?;
+                    if let (Some(result), Some(value)) = (result, value) {
+                        frame.values[result.index()] = Some(value);
+                    }
+                    Flow::Next
+                }
+                Statement::ControlBarrier(_) | Statement::MemoryBarrier(_) => Flow::Next,
+                statement => return Err(format!("Unsupported statement {statement:?}")),
+            };
+
+            if !matches!(flow, Flow::Next) {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn root_mut(&mut self, root: Root) -> Result<&mut Value, String> {
+        match root {
+            Root::Local { frame, index } => self.locals[frame]
+                .get_mut(index)
+                .ok_or_else(|| "Invalid local variable".to_owned()),
+            Root::Global(index) => self
+                .globals
+                .get_mut(index)
+                .ok_or_else(|| "Invalid global variable".to_owned()),
+        }
+    }
+
+    /// Returns the value of an already emitted expression or computes it.
+    fn eval(
+        &mut self,
+        frame: &mut Frame,
+        handle: naga::Handle<Expression>,
+    ) -> Result<Value, String> {
+        match &frame.values[handle.index()] {
+            Some(value) => Ok(value.clone()),
+            None => self.compute(frame, handle),
+        }
+    }
+
+    fn compute(
+        &mut self,
+        frame: &mut Frame,
+        handle: naga::Handle<Expression>,
+    ) -> Result<Value, String> {
+        let module = self.module;
+        Ok(match &frame.expressions[handle] {
+            Expression::Literal(literal) => Value::Scalar(match *literal {
+                Literal::F32(f) => Scalar::F32(f),
+                Literal::F64(f) | Literal::AbstractFloat(f) => Scalar::F32(f as f32),
+                Literal::U32(u) => Scalar::U32(u),
+                Literal::I32(i) => Scalar::I32(i),
+                Literal::U64(u) => Scalar::U32(u as u32),
+                Literal::I64(i) | Literal::AbstractInt(i) => Scalar::I32(i as i32),
+                Literal::Bool(b) => Scalar::Bool(b),
+                literal => return Err(format!("Unsupported literal {literal:?}")),
+            }),
+            Expression::Constant(constant) => match &self.constants[constant.index()] {
+                Some(value) => value.clone(),
+                None => {
+                    let mut global_frame = self.global_frame();
+                    let value = self.eval(&mut global_frame, module.constants[*constant].init)?;
+                    self.constants[constant.index()] = Some(value.clone());
+                    value
+                }
+            },
+            Expression::ZeroValue(ty) => zero_value(module, *ty)?,
+            Expression::Compose { ty, components } => {
+                let components = components
+                    .iter()
+                    .map(|component| self.eval(frame, *component))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match &module.types[*ty].inner {
+                    TypeInner::Vector { .. } => Value::Vector(
+                        components
+                            .iter()
+                            .map(Value::components)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .concat(),
+                    ),
+                    TypeInner::Matrix { rows, .. }
+                        if components.iter().all(|c| matches!(c, Value::Scalar(_))) =>
+                    {
+                        let scalars = components
+                            .iter()
+                            .map(Value::scalar)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Value::Composite(
+                            scalars
+                                .chunks(*rows as usize)
+                                .map(|column| Value::Vector(column.to_vec()))
+                                .collect(),
+                        )
+                    }
+                    _ => Value::Composite(components),
+                }
+            }
+            Expression::Access { base, index } => {
+                let index = self.eval(frame, *index)?.scalar()?.as_index()?;
+                match self.eval(frame, *base)? {
+                    Value::Pointer(mut pointer) => {
+                        pointer.path.push(index);
+                        Value::Pointer(pointer)
+                    }
+                    value => value.get(index)?,
+                }
+            }
+            Expression::AccessIndex { base, index } => match self.eval(frame, *base)? {
+                Value::Pointer(mut pointer) => {
+                    pointer.path.push(*index as usize);
+                    Value::Pointer(pointer)
+                }
+                value => value.get(*index as usize)?,
+            },
+            Expression::Splat { size, value } => {
+                let scalar = self.eval(frame, *value)?.scalar()?;
+                Value::Vector(vec![scalar; *size as usize])
+            }
+            Expression::Swizzle {
+                size,
+                vector,
+                pattern,
+            } => {
+                let components = self.eval(frame, *vector)?.components()?;
+                Value::Vector(
+                    pattern[..*size as usize]
+                        .iter()
+                        .map(|component| components[*component as usize])
+                        .collect(),
+                )
+            }
+            Expression::FunctionArgument(index) => frame
+                .arguments
+                .get(*index as usize)
+                .cloned()
+                .ok_or("Invalid function argument")?,
+            Expression::GlobalVariable(global) => Value::Pointer(Pointer {
+                root: Root::Global(global.index()),
+                path: Vec::new(),
+            }),
+            Expression::LocalVariable(local) => Value::Pointer(Pointer {
+                root: Root::Local {
+                    frame: frame.index,
+                    index: local.index(),
+                },
+                path: Vec::new(),
+            }),
+            Expression::Load { pointer } => {
+                let Value::Pointer(pointer) = self.eval(frame, *pointer)? else {
+                    return Err("Load from non-pointer".to_owned());
+                };
+                self.root_mut(pointer.root)?.load(&pointer.path)?
+            }
+            Expression::Unary { op, expr } => {
+                let value = self.eval(frame, *expr)?;
+                map(&[value], |s| {
+                    Ok(match (op, s[0]) {
+                        (UnaryOperator::Negate, Scalar::F32(f)) => Scalar::F32(-f),
+                        (UnaryOperator::Negate, Scalar::I32(i)) => Scalar::I32(i.wrapping_neg()),
+                        (UnaryOperator::LogicalNot, Scalar::Bool(b)) => Scalar::Bool(!b),
+                        (UnaryOperator::BitwiseNot, Scalar::I32(i)) => Scalar::I32(!i),
+                        (UnaryOperator::BitwiseNot, Scalar::U32(u)) => Scalar::U32(!u),
+                        (op, scalar) => return Err(format!("Unsupported {op:?} for {scalar:?}")),
+                    })
+                })?
+            }
+            Expression::Binary { op, left, right } => {
+                let left = self.eval(frame, *left)?;
+                let right = self.eval(frame, *right)?;
+                binary(*op, left, right)?
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = self.eval(frame, *condition)?;
+                let accept = self.eval(frame, *accept)?;
+                let reject = self.eval(frame, *reject)?;
+                match condition {
+                    Value::Scalar(condition) => {
+                        if condition.as_bool()? {
+                            accept
+                        } else {
+                            reject
+                        }
+                    }
+                    condition => map(&[condition, accept, reject], |s| {
+                        Ok(if s[0].as_bool()? { s[1] } else { s[2] })
+                    })?,
+                }
+            }
+            Expression::Derivative { axis, expr, .. } => {
+                // There are no neighboring pixels, so we assume a gradient with length 1
+                let pixel_size = self.inputs.pixel_size;
+                let derivative = match axis {
+                    DerivativeAxis::X | DerivativeAxis::Y => pixel_size / 2.0,
+                    DerivativeAxis::Width => pixel_size,
+                };
+                let value = self.eval(frame, *expr)?;
+                map(&[value], |_| Ok(Scalar::F32(derivative)))?
+            }
+            Expression::Relational { fun, argument } => {
+                let argument = self.eval(frame, *argument)?;
+                match fun {
+                    RelationalFunction::All | RelationalFunction::Any => {
+                        let components = argument
+                            .components()?
+                            .into_iter()
+                            .map(Scalar::as_bool)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Value::Scalar(Scalar::Bool(match fun {
+                            RelationalFunction::All => components.iter().all(|b| *b),
+                            _ => components.iter().any(|b| *b),
+                        }))
+                    }
+                    RelationalFunction::IsNan => {
+                        map(&[argument], |s| Ok(Scalar::Bool(s[0].as_f32().is_nan())))?
+                    }
+                    RelationalFunction::IsInf => map(&[argument], |s| {
+                        Ok(Scalar::Bool(s[0].as_f32().is_infinite()))
+                    })?,
+                }
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                let mut args = vec![self.eval(frame, *arg)?];
+                for arg in [arg1, arg2, arg3].into_iter().flatten() {
+                    args.push(self.eval(frame, *arg)?);
+                }
+                math(*fun, args)?
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let value = self.eval(frame, *expr)?;
+                match convert {
+                    Some(_) => map(&[value], |s| s[0].convert(*kind))?,
+                    None => map(&[value], |s| s[0].bitcast(*kind))?,
+                }
+            }
+            Expression::CallResult(_) => return Err("Call result is not available".to_owned()),
+            expression => return Err(format!("Unsupported expression {expression:?}")),
+        })
+    }
+}
+
+/// Applies `f` component-wise, scalars are broadcasted to the length of vectors.
+fn map(args: &[Value], f: impl Fn(&[Scalar]) -> Result<Scalar, String>) -> Result<Value, String> {
+    map_dyn(args, &f)
+}
+
+fn map_dyn(
+    args: &[Value],
+    f: &dyn Fn(&[Scalar]) -> Result<Scalar, String>,
+) -> Result<Value, String> {
+    // Matrices are mapped column-wise
+    if let Some(Value::Composite(columns)) = args.iter().find(|a| matches!(a, Value::Composite(_)))
+    {
+        let len = columns.len();
+        return Ok(Value::Composite(
+            (0..len)
+                .map(|i| {
+                    let column_args = args
+                        .iter()
+                        .map(|arg| match arg {
+                            Value::Composite(_) => arg.get(i),
+                            arg => Ok(arg.clone()),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    map_dyn(&column_args, f)
+                })
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let components = args
+        .iter()
+        .map(Value::components)
+        .collect::<Result<Vec<_>, _>>()?;
+    let vector = args.iter().any(|arg| matches!(arg, Value::Vector(_)));
+    let len = components.iter().map(Vec::len).max().unwrap_or(1);
+    let result = (0..len)
+        .map(|i| {
+            let scalars: Vec<_> = components
+                .iter()
+                .map(|c| if c.len() == 1 { c[0] } else { c[i] })
+                .collect();
+            f(&scalars)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from_components(result, vector))
+}
+
+fn binary(op: BinaryOperator, left: Value, right: Value) -> Result<Value, String> {
+    // Linear algebra
+    if op == BinaryOperator::Multiply {
+        match (&left, &right) {
+            (Value::Composite(columns), Value::Vector(v)) => {
+                let mut result = Value::Vector(vec![Scalar::F32(0.0); column_len(columns)?]);
+                for (column, scalar) in columns.iter().zip(v) {
+                    let scaled = binary(op, column.clone(), Value::Scalar(*scalar))?;
+                    result = binary(BinaryOperator::Add, result, scaled)?;
+                }
+                return Ok(result);
+            }
+            (Value::Vector(v), Value::Composite(columns)) => {
+                return Ok(Value::Vector(
+                    columns
+                        .iter()
+                        .map(|column| dot(&column.components()?, v))
+                        .collect::<Result<_, _>>()?,
+                ));
+            }
+            (Value::Composite(_), Value::Composite(columns)) => {
+                return Ok(Value::Composite(
+                    columns
+                        .iter()
+                        .map(|column| binary(op, left.clone(), column.clone()))
+                        .collect::<Result<_, _>>()?,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    map(&[left, right], |s| {
+        Ok(match (s[0], s[1]) {
+            (Scalar::F32(a), Scalar::F32(b)) => match op {
+                BinaryOperator::Add => Scalar::F32(a + b),
+                BinaryOperator::Subtract => Scalar::F32(a - b),
+                BinaryOperator::Multiply => Scalar::F32(a * b),
+                BinaryOperator::Divide => Scalar::F32(a / b),
+                // WGSL uses truncated division for the remainder
+                BinaryOperator::Modulo => Scalar::F32(a % b),
+                _ => Scalar::Bool(compare(op, a.partial_cmp(&b))?),
+            },
+            (Scalar::I32(a), Scalar::I32(b)) => match op {
+                BinaryOperator::Add => Scalar::I32(a.wrapping_add(b)),
+                BinaryOperator::Subtract => Scalar::I32(a.wrapping_sub(b)),
+                BinaryOperator::Multiply => Scalar::I32(a.wrapping_mul(b)),
+                BinaryOperator::Divide => Scalar::I32(if b == 0 { a } else { a.wrapping_div(b) }),
+                BinaryOperator::Modulo => Scalar::I32(if b == 0 { 0 } else { a.wrapping_rem(b) }),
+                BinaryOperator::And => Scalar::I32(a & b),
+                BinaryOperator::InclusiveOr => Scalar::I32(a | b),
+                BinaryOperator::ExclusiveOr => Scalar::I32(a ^ b),
+                _ => Scalar::Bool(compare(op, a.partial_cmp(&b))?),
+            },
+            (Scalar::U32(a), Scalar::U32(b)) => match op {
+                BinaryOperator::Add => Scalar::U32(a.wrapping_add(b)),
+                BinaryOperator::Subtract => Scalar::U32(a.wrapping_sub(b)),
+                BinaryOperator::Multiply => Scalar::U32(a.wrapping_mul(b)),
+                BinaryOperator::Divide => Scalar::U32(a.checked_div(b).unwrap_or(a)),
+                BinaryOperator::Modulo => Scalar::U32(a.checked_rem(b).unwrap_or(0)),
+                BinaryOperator::And => Scalar::U32(a & b),
+                BinaryOperator::InclusiveOr => Scalar::U32(a | b),
+                BinaryOperator::ExclusiveOr => Scalar::U32(a ^ b),
+                BinaryOperator::ShiftLeft => Scalar::U32(a.wrapping_shl(b)),
+                BinaryOperator::ShiftRight => Scalar::U32(a.wrapping_shr(b)),
+                _ => Scalar::Bool(compare(op, a.partial_cmp(&b))?),
+            },
+            (Scalar::I32(a), Scalar::U32(b)) => match op {
+                BinaryOperator::ShiftLeft => Scalar::I32(a.wrapping_shl(b)),
+                BinaryOperator::ShiftRight => Scalar::I32(a.wrapping_shr(b)),
+                _ => return Err(format!("Unsupported {op:?} for i32 and u32")),
+            },
+            (Scalar::Bool(a), Scalar::Bool(b)) => Scalar::Bool(match op {
+                BinaryOperator::LogicalAnd | BinaryOperator::And => a && b,
+                BinaryOperator::LogicalOr | BinaryOperator::InclusiveOr => a || b,
+                BinaryOperator::Equal => a == b,
+                BinaryOperator::NotEqual => a != b,
+                _ => return Err(format!("Unsupported {op:?} for bool")),
+            }),
+            (a, b) => return Err(format!("Unsupported {op:?} for {a:?} and {b:?}")),
+        })
+    })
+}
+
+fn column_len(columns: &[Value]) -> Result<usize, String> {
+    Ok(columns.first().ok_or("Empty matrix")?.components()?.len())
+}
+
+fn compare(op: BinaryOperator, ordering: Option<std::cmp::Ordering>) -> Result<bool, String> {
+    use std::cmp::Ordering::*;
+    Ok(match op {
+        BinaryOperator::Equal => ordering == Some(Equal),
+        BinaryOperator::NotEqual => ordering != Some(Equal),
+        BinaryOperator::Less => ordering == Some(Less),
+        BinaryOperator::LessEqual => matches!(ordering, Some(Less | Equal)),
+        BinaryOperator::Greater => ordering == Some(Greater),
+        BinaryOperator::GreaterEqual => matches!(ordering, Some(Greater | Equal)),
+        op => return Err(format!("Unsupported operator {op:?}")),
+    })
+}
+
+fn dot(a: &[Scalar], b: &[Scalar]) -> Result<Scalar, String> {
+    Ok(match (a.first(), b.first()) {
+        (Some(Scalar::I32(_)), _) => Scalar::I32(
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| a.as_f32() as i32 * b.as_f32() as i32)
+                .sum(),
+        ),
+        _ => Scalar::F32(a.iter().zip(b).map(|(a, b)| a.as_f32() * b.as_f32()).sum()),
+    })
+}
+
+fn floats(value: &Value) -> Result<Vec<f32>, String> {
+    Ok(value
+        .components()?
+        .into_iter()
+        .map(Scalar::as_f32)
+        .collect())
+}
+
+fn float_vector(components: Vec<f32>) -> Value {
+    Value::Vector(components.into_iter().map(Scalar::F32).collect())
+}
+
+fn math(fun: MathFunction, args: Vec<Value>) -> Result<Value, String> {
+    use MathFunction as M;
+
+    let float1 = |f: fn(f32) -> f32| map(&args, |s| Ok(Scalar::F32(f(s[0].as_f32()))));
+    let float2 =
+        |f: fn(f32, f32) -> f32| map(&args, |s| Ok(Scalar::F32(f(s[0].as_f32(), s[1].as_f32()))));
+    let float3 = |f: fn(f32, f32, f32) -> f32| {
+        map(&args, |s| {
+            Ok(Scalar::F32(f(s[0].as_f32(), s[1].as_f32(), s[2].as_f32())))
+        })
+    };
+    let length = |v: &[f32]| v.iter().map(|c| c * c).sum::<f32>().sqrt();
+
+    match fun {
+        M::Abs => map(&args, |s| {
+            Ok(match s[0] {
+                Scalar::F32(f) => Scalar::F32(f.abs()),
+                Scalar::I32(i) => Scalar::I32(i.wrapping_abs()),
+                scalar => scalar,
+            })
+        }),
+        M::Min => map(&args, |s| {
+            Ok(match (s[0], s[1]) {
+                (Scalar::F32(a), Scalar::F32(b)) => Scalar::F32(a.min(b)),
+                (Scalar::I32(a), Scalar::I32(b)) => Scalar::I32(a.min(b)),
+                (Scalar::U32(a), Scalar::U32(b)) => Scalar::U32(a.min(b)),
+                (a, b) => return Err(format!("Unsupported min for {a:?} and {b:?}")),
+            })
+        }),
+        M::Max => map(&args, |s| {
+            Ok(match (s[0], s[1]) {
+                (Scalar::F32(a), Scalar::F32(b)) => Scalar::F32(a.max(b)),
+                (Scalar::I32(a), Scalar::I32(b)) => Scalar::I32(a.max(b)),
+                (Scalar::U32(a), Scalar::U32(b)) => Scalar::U32(a.max(b)),
+                (a, b) => return Err(format!("Unsupported max for {a:?} and {b:?}")),
+            })
+        }),
+        M::Clamp => map(&args, |s| {
+            Ok(match (s[0], s[1], s[2]) {
+                (Scalar::F32(x), Scalar::F32(lo), Scalar::F32(hi)) => {
+                    Scalar::F32(x.max(lo).min(hi))
+                }
+                (Scalar::I32(x), Scalar::I32(lo), Scalar::I32(hi)) => {
+                    Scalar::I32(x.max(lo).min(hi))
+                }
+                (Scalar::U32(x), Scalar::U32(lo), Scalar::U32(hi)) => {
+                    Scalar::U32(x.max(lo).min(hi))
+                }
+                (x, _, _) => return Err(format!("Unsupported clamp for {x:?}")),
+            })
+        }),
+        M::Saturate => float1(|x| x.clamp(0.0, 1.0)),
+        M::Cos => float1(f32::cos),
+        M::Cosh => float1(f32::cosh),
+        M::Sin => float1(f32::sin),
+        M::Sinh => float1(f32::sinh),
+        M::Tan => float1(f32::tan),
+        M::Tanh => float1(f32::tanh),
+        M::Acos => float1(f32::acos),
+        M::Asin => float1(f32::asin),
+        M::Atan => float1(f32::atan),
+        M::Atan2 => float2(f32::atan2),
+        M::Asinh => float1(f32::asinh),
+        M::Acosh => float1(f32::acosh),
+        M::Atanh => float1(f32::atanh),
+        M::Radians => float1(f32::to_radians),
+        M::Degrees => float1(f32::to_degrees),
+        M::Ceil => float1(f32::ceil),
+        M::Floor => float1(f32::floor),
+        M::Round => float1(f32::round_ties_even),
+        M::Fract => float1(|x| x - x.floor()),
+        M::Trunc => float1(f32::trunc),
+        M::Ldexp => map(&args, |s| {
+            Ok(Scalar::F32(s[0].as_f32() * 2f32.powi(s[1].as_f32() as i32)))
+        }),
+        M::Exp => float1(f32::exp),
+        M::Exp2 => float1(f32::exp2),
+        M::Log => float1(f32::ln),
+        M::Log2 => float1(f32::log2),
+        M::Pow => float2(f32::powf),
+        M::Dot => Ok(Value::Scalar(dot(
+            &args[0].components()?,
+            &args[1].components()?,
+        )?)),
+        M::Cross => {
+            let (a, b) = (floats(&args[0])?, floats(&args[1])?);
+            let cross = Vec3::from_slice(&a).cross(Vec3::from_slice(&b));
+            Ok(float_vector(cross.to_array().to_vec()))
+        }
+        M::Distance => {
+            let (a, b) = (floats(&args[0])?, floats(&args[1])?);
+            let d: Vec<_> = a.iter().zip(&b).map(|(a, b)| a - b).collect();
+            Ok(Value::Scalar(Scalar::F32(length(&d))))
+        }
+        M::Length => Ok(Value::Scalar(Scalar::F32(length(&floats(&args[0])?)))),
+        M::Normalize => {
+            let v = floats(&args[0])?;
+            let l = length(&v);
+            Ok(float_vector(v.iter().map(|c| c / l).collect()))
+        }
+        M::FaceForward => {
+            let (n, i, r) = (floats(&args[0])?, floats(&args[1])?, floats(&args[2])?);
+            let d: f32 = r.iter().zip(&i).map(|(a, b)| a * b).sum();
+            let sign = if d < 0.0 { 1.0 } else { -1.0 };
+            Ok(float_vector(n.iter().map(|c| c * sign).collect()))
+        }
+        M::Reflect => {
+            let (e1, e2) = (floats(&args[0])?, floats(&args[1])?);
+            let d: f32 = e1.iter().zip(&e2).map(|(a, b)| a * b).sum();
+            Ok(float_vector(
+                e1.iter().zip(&e2).map(|(a, b)| a - 2.0 * d * b).collect(),
+            ))
+        }
+        M::Sign => map(&args, |s| {
+            Ok(match s[0] {
+                Scalar::F32(f) => Scalar::F32(if f == 0.0 { 0.0 } else { f.signum() }),
+                Scalar::I32(i) => Scalar::I32(i.signum()),
+                scalar => scalar,
+            })
+        }),
+        M::Fma => float3(|a, b, c| a * b + c),
+        M::Mix => float3(|a, b, t| a + (b - a) * t),
+        M::Step => float2(|edge, x| if x < edge { 0.0 } else { 1.0 }),
+        M::SmoothStep => float3(|lo, hi, x| {
+            let t = ((x - lo) / (hi - lo)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        }),
+        M::Sqrt => float1(f32::sqrt),
+        M::InverseSqrt => float1(|x| 1.0 / x.sqrt()),
+        M::Transpose => {
+            let Value::Composite(columns) = &args[0] else {
+                return Err("Expected matrix".to_owned());
+            };
+            let columns = columns
+                .iter()
+                .map(Value::components)
+                .collect::<Result<Vec<_>, _>>()?;
+            let rows = columns.first().map_or(0, Vec::len);
+            Ok(Value::Composite(
+                (0..rows)
+                    .map(|r| Value::Vector(columns.iter().map(|c| c[r]).collect()))
+                    .collect(),
+            ))
+        }
+        M::Determinant => {
+            let Value::Composite(columns) = &args[0] else {
+                return Err("Expected matrix".to_owned());
+            };
+            let m = columns.iter().map(floats).collect::<Result<Vec<_>, _>>()?;
+            let det = match m.len() {
+                2 => m[0][0] * m[1][1] - m[1][0] * m[0][1],
+                3 => Mat3::from_cols_slice(&m.concat()).determinant(),
+                4 => Mat4::from_cols_slice(&m.concat()).determinant(),
+                _ => return Err("Invalid matrix".to_owned()),
+            };
+            Ok(Value::Scalar(Scalar::F32(det)))
+        }
+        fun => Err(format!("Unsupported function {fun:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The parts of the `smud` module of bevy_smud that the tested shaders use.
+    const SMUD: &str = "
+        #define_import_path smud
+
+        struct SdfInput {
+            pos: vec2<f32>,
+            params: vec4<f32>,
+            color: vec4<f32>,
+        }
+
+        fn sd_circle(p: vec2<f32>, r: f32) -> f32 {
+            return length(p) - r;
+        }
+    ";
+
+    const POSITIONS: [Vec2; 5] = [
+        Vec2::ZERO,
+        Vec2::new(30.0, 40.0),
+        Vec2::new(-90.0, 0.0),
+        Vec2::new(0.0, 130.0),
+        Vec2::new(200.0, -150.0),
+    ];
+
+    /// Evaluates the `sdf` function of the shader at [`POSITIONS`].
+    fn distances(source: &'static str, params: Vec4) -> Vec<f32> {
+        let mut shaders = Assets::<Shader>::default();
+        shaders.add(Shader::from_wgsl(SMUD, "smud.wgsl"));
+        let shader = shaders.add(Shader::from_wgsl(source, "test.wgsl"));
+        let sdf = CpuShaders::default().get(&shaders, &shader, "sdf").unwrap();
+        let inputs = ShapeInputs {
+            params,
+            color: Vec4::ONE,
+            pixel_size: 1.0,
+            globals: GlobalInputs::default(),
+        };
+        POSITIONS
+            .iter()
+            .map(|pos| sdf.sdf(*pos, &inputs).unwrap())
+            .collect()
+    }
+
+    fn assert_distances(distances: Vec<f32>, expected: impl Fn(Vec2) -> f32) {
+        for (pos, distance) in POSITIONS.into_iter().zip(distances) {
+            assert!(
+                (distance - expected(pos)).abs() < 1e-3,
+                "{distance} at {pos}, expected {}",
+                expected(pos)
+            );
+        }
+    }
+
+    #[test]
+    fn circle_template() {
+        let distances = distances(
+            include_str!("../templates/sdf/circle.wgsl"),
+            Vec4::new(20.0, 0.0, 0.0, 0.0),
+        );
+        assert_distances(distances, |pos| pos.length() - 120.0);
+    }
+
+    #[test]
+    fn donut_template() {
+        let distances = distances(
+            include_str!("../templates/sdf/donut.wgsl"),
+            Vec4::new(0.0, 5.0, 0.0, 0.0),
+        );
+        // A ring with radius 100 - 30 and thickness 2 * 30
+        assert_distances(distances, |pos| (pos.length() - 70.0).abs() - 30.0);
+    }
+
+    #[test]
+    fn loops_and_branches() {
+        let source = "
+            #import smud
+
+            fn sdf(input: smud::SdfInput) -> f32 {
+                var distance = 1e9;
+                for (var i = 0; i < 4; i++) {
+                    if i == 2 {
+                        continue;
+                    }
+                    let angle = f32(i) * 1.5707964;
+                    let center = vec2(cos(angle), sin(angle)) * input.params.x;
+                    distance = min(distance, smud::sd_circle(input.pos - center, 10.0));
+                }
+                return select(distance, -distance, input.params.y > 0.0);
+            }
+        ";
+        let centers = [
+            Vec2::new(50.0, 0.0),
+            Vec2::new(0.0, 50.0),
+            Vec2::new(0.0, -50.0),
+        ];
+        let expected = |pos: Vec2| {
+            centers
+                .iter()
+                .map(|center| pos.distance(*center) - 10.0)
+                .fold(f32::INFINITY, f32::min)
+        };
+        assert_distances(distances(source, Vec4::new(50.0, 0.0, 0.0, 0.0)), expected);
+        assert_distances(distances(source, Vec4::new(50.0, 1.0, 0.0, 0.0)), |pos| {
+            -expected(pos)
+        });
+    }
+}
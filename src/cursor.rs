@@ -0,0 +1,39 @@
+use bevy::{ecs::system::SystemParam, prelude::*, window::PrimaryWindow};
+use bevy_egui::input::EguiWantsInput;
+
+use crate::ShapeCamera;
+
+/// The primary window and the [`ShapeCamera`], for converting cursor positions to world space.
+#[derive(SystemParam)]
+pub struct Viewport<'w, 's> {
+    pub window: Single<'w, 's, &'static Window, With<PrimaryWindow>>,
+    pub camera: Single<'w, 's, (&'static Camera, &'static GlobalTransform), With<ShapeCamera>>,
+}
+
+/// Mouse and keyboard input, and whether egui uses it instead.
+#[derive(SystemParam)]
+pub struct UserInput<'w> {
+    pub mouse: Res<'w, ButtonInput<MouseButton>>,
+    pub keys: Res<'w, ButtonInput<KeyCode>>,
+    pub egui_wants_input: Res<'w, EguiWantsInput>,
+}
+
+/// Position of the mouse cursor in the world space of the [`ShapeCamera`].
+#[derive(Resource, Default)]
+pub struct Cursor {
+    /// `None` if the cursor is outside of the window.
+    pub world_position: Option<Vec2>,
+    /// Keeps the last known position when the cursor leaves the window.
+    pub last_world_position: Vec2,
+}
+
+pub fn update_cursor(mut cursor: ResMut<Cursor>, viewport: Viewport) {
+    let Viewport { window, camera } = viewport;
+    let (camera, camera_transform) = *camera;
+    cursor.world_position = window
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok());
+    if let Some(position) = cursor.world_position {
+        cursor.last_world_position = position;
+    }
+}
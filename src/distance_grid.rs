@@ -0,0 +1,275 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+
+/// Signed distances sampled on a regular grid that can be turned into SDF code.
+pub struct DistanceGrid {
+    /// Number of samples along each axis, at least 2.
+    pub size: UVec2,
+    /// Position of the first sample, the grid is centered at the origin.
+    pub min: Vec2,
+    /// Distance between neighbouring samples.
+    pub cell: f32,
+    /// Samples row by row, starting at the bottom.
+    pub distances: Vec<f32>,
+}
+
+/// A binary image, with the first row at the top.
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    pub inside: Vec<bool>,
+}
+
+impl Mask {
+    pub fn is_empty(&self) -> bool {
+        !self.inside.contains(&true)
+    }
+
+    /// Returns the signed distance in pixels from the center of every pixel to the edge of the
+    /// mask, with a border of outside pixels added around it.
+    fn signed_distances(&self) -> (usize, usize, Vec<f32>) {
+        let (width, height) = (self.width + 2, self.height + 2);
+        let mut inside = vec![false; width * height];
+        for (y, row) in self.inside.chunks(self.width).enumerate() {
+            let start = (y + 1) * width + 1;
+            inside[start..start + self.width].copy_from_slice(row);
+        }
+
+        let to_inside = squared_distances(&inside, width, height, true);
+        let to_outside = squared_distances(&inside, width, height, false);
+        // The edge lies between the centers of an inside and an outside pixel
+        let distances = inside
+            .iter()
+            .zip(to_inside.iter().zip(&to_outside))
+            .map(|(&inside, (&to_inside, &to_outside))| {
+                if inside {
+                    0.5 - to_outside.sqrt()
+                } else {
+                    to_inside.sqrt() - 0.5
+                }
+            })
+            .collect();
+        (width, height, distances)
+    }
+}
+
+impl DistanceGrid {
+    /// Computes the distance transform of the mask and samples it with `resolution` samples along
+    /// the larger side, the larger side of the mask has the given size.
+    pub fn from_mask(mask: &Mask, resolution: u32, size: f32) -> Self {
+        let (width, height, distances) = mask.signed_distances();
+        let scale = size / mask.width.max(mask.height) as f32;
+        let extent = Vec2::new(width as f32, height as f32);
+
+        let cell = extent.max_element() / resolution as f32;
+        let samples = (extent / cell).ceil().as_uvec2().max(UVec2::splat(2));
+        let min = -(samples - 1).as_vec2() * cell / 2.0;
+
+        let sample = |x: f32, y: f32| {
+            // Pixel centers are at half coordinates
+            let (x, y) = (
+                (x - 0.5).clamp(0.0, (width - 1) as f32),
+                (y - 0.5).clamp(0.0, (height - 1) as f32),
+            );
+            let (x0, y0) = (x as usize, y as usize);
+            let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+            let (tx, ty) = (x.fract(), y.fract());
+            let at = |x: usize, y: usize| distances[y * width + x];
+            let top = at(x0, y0).lerp(at(x1, y0), tx);
+            let bottom = at(x0, y1).lerp(at(x1, y1), tx);
+            top.lerp(bottom, ty)
+        };
+
+        let mut grid_distances = Vec::with_capacity((samples.x * samples.y) as usize);
+        for j in 0..samples.y {
+            for i in 0..samples.x {
+                // The y axis of the image points down
+                let position = min + UVec2::new(i, j).as_vec2() * cell;
+                let pixel = Vec2::new(position.x, -position.y) + extent / 2.0;
+                grid_distances.push(sample(pixel.x, pixel.y) * scale);
+            }
+        }
+
+        Self {
+            size: samples,
+            min: min * scale,
+            cell: cell * scale,
+            distances: grid_distances,
+        }
+    }
+
+    /// Returns the WGSL code of an SDF that bilinearly interpolates the embedded samples.
+    pub fn sdf_code(&self, import_path: &str, comment: &str) -> String {
+        let mut code = format!("#define_import_path {import_path}\n\n#import smud\n\n");
+        for line in comment.lines() {
+            let _ = writeln!(code, "// {line}");
+        }
+        code.push('\n');
+
+        let _ = writeln!(
+            code,
+            "const SIZE = vec2<i32>({}, {});\n\
+             const MIN = vec2<f32>({:?}, {:?});\n\
+             const CELL = {:?};\n",
+            self.size.x, self.size.y, self.min.x, self.min.y, self.cell
+        );
+
+        // Rounding keeps the code short, the interpolation is less precise anyway
+        let _ = writeln!(
+            code,
+            "const DISTANCES = array<f32, {}>(",
+            self.distances.len()
+        );
+        for row in self.distances.chunks(self.size.x as usize) {
+            code.push_str("   ");
+            for distance in row {
+                let _ = write!(code, " {:?},", (distance * 100.0).round() / 100.0);
+            }
+            code.push('\n');
+        }
+        code.push_str(");\n\n");
+
+        code.push_str(
+            "fn distance_at(x: i32, y: i32) -> f32 {\n    \
+                 return DISTANCES[y * SIZE.x + x];\n\
+             }\n\n\
+             fn sdf(input: smud::SdfInput) -> f32 {\n    \
+                 let grid = (input.pos - MIN) / CELL;\n    \
+                 // Outside of the grid, the distance to the grid is added\n    \
+                 let clamped = clamp(grid, vec2(0.0), vec2<f32>(SIZE - 1));\n    \
+                 let i = min(vec2<i32>(clamped), SIZE - 2);\n    \
+                 let t = clamped - vec2<f32>(i);\n    \
+                 let bottom = mix(distance_at(i.x, i.y), distance_at(i.x + 1, i.y), t.x);\n    \
+                 let top = mix(distance_at(i.x, i.y + 1), distance_at(i.x + 1, i.y + 1), t.x);\n    \
+                 return mix(bottom, top, t.y) + length(grid - clamped) * CELL;\n\
+             }\n",
+        );
+        code
+    }
+}
+
+/// Returns the squared distance from every pixel to the closest pixel where `inside` equals
+/// `target`, see "Distance Transforms of Sampled Functions" by Felzenszwalb and Huttenlocher.
+fn squared_distances(inside: &[bool], width: usize, height: usize, target: bool) -> Vec<f32> {
+    let mut distances: Vec<_> = inside
+        .iter()
+        .map(|&inside| if inside == target { 0.0 } else { f32::INFINITY })
+        .collect();
+
+    let mut column = vec![0.0; height];
+    let mut output = vec![0.0; width.max(height)];
+    let mut parabolas = vec![0; width.max(height)];
+    let mut boundaries = vec![0.0; width.max(height) + 1];
+    for x in 0..width {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = distances[y * width + x];
+        }
+        transform_1d(&column, &mut output, &mut parabolas, &mut boundaries);
+        for (y, value) in output[..height].iter().enumerate() {
+            distances[y * width + x] = *value;
+        }
+    }
+    for y in 0..height {
+        let row = &mut distances[y * width..(y + 1) * width];
+        transform_1d(row, &mut output, &mut parabolas, &mut boundaries);
+        row.copy_from_slice(&output[..width]);
+    }
+    distances
+}
+
+/// The one-dimensional squared distance transform, computed as the lower envelope of parabolas
+/// rooted at every sample.
+fn transform_1d(f: &[f32], output: &mut [f32], parabolas: &mut [usize], boundaries: &mut [f32]) {
+    let n = f.len();
+    // Samples that are infinitely far away have no parabola
+    let Some(first) = f.iter().position(|value| value.is_finite()) else {
+        output[..n].fill(f32::INFINITY);
+        return;
+    };
+
+    let intersection = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2 * q - 2 * p) as f32
+    };
+    let mut k = 0;
+    parabolas[0] = first;
+    boundaries[0] = f32::NEG_INFINITY;
+    boundaries[1] = f32::INFINITY;
+    for (q, value) in f.iter().enumerate().skip(first + 1) {
+        if !value.is_finite() {
+            continue;
+        }
+        let mut s = intersection(q, parabolas[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = f32::INFINITY;
+    }
+
+    k = 0;
+    for (q, output) in output[..n].iter_mut().enumerate() {
+        while boundaries[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q as f32 - parabolas[k] as f32;
+        *output = offset * offset + f[parabolas[k]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squared_distances_match_brute_force() {
+        let (width, height) = (7, 5);
+        // Rows from the top, `#` marks the target pixels
+        let bitmap = ".......\
+                      .#.....\
+                      .....#.\
+                      ...#...\
+                      .......";
+        let inside: Vec<_> = bitmap.chars().map(|c| c == '#').collect();
+
+        let distances = squared_distances(&inside, width, height, true);
+        for y in 0..height {
+            for x in 0..width {
+                let expected = (0..inside.len())
+                    .filter(|&index| inside[index])
+                    .map(|index| {
+                        let dx = (index % width) as f32 - x as f32;
+                        let dy = (index / width) as f32 - y as f32;
+                        dx * dx + dy * dy
+                    })
+                    .fold(f32::INFINITY, f32::min);
+                assert_eq!(distances[y * width + x], expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn squared_distances_without_target() {
+        let distances = squared_distances(&[false; 6], 3, 2, true);
+        assert!(distances.iter().all(|distance| *distance == f32::INFINITY));
+    }
+
+    #[test]
+    fn signed_distances_of_a_pixel() {
+        let mask = Mask {
+            width: 3,
+            height: 3,
+            inside: [false, false, false, false, true, false, false, false, false].into(),
+        };
+        let (width, height, distances) = mask.signed_distances();
+        assert_eq!((width, height), (5, 5));
+        let at = |x: usize, y: usize| distances[y * width + x];
+        assert_eq!(at(2, 2), -0.5);
+        assert_eq!(at(1, 2), 0.5);
+        assert_eq!(at(3, 1), 2.0f32.sqrt() - 0.5);
+        assert_eq!(at(0, 0), 8.0f32.sqrt() - 0.5);
+    }
+}
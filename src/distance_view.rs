@@ -0,0 +1,96 @@
+use std::borrow::Cow;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_smud::SmudShape;
+
+use crate::{
+    consts,
+    shader::create_shader,
+    state::{GlobalState, ShapeState},
+};
+
+/// Replaces the fill of shapes with contour lines of their raw distance, for checking whether an
+/// SDF is an exact distance or only a bound.
+#[derive(Clone, PartialEq)]
+pub struct DistanceView {
+    /// Shows the distance of all shapes, otherwise only of shapes that enable it themselves.
+    pub all_shapes: bool,
+    /// Distance between contour lines.
+    pub spacing: f32,
+    /// Colors the shapes by the magnitude of the gradient of the distance instead.
+    pub heatmap: bool,
+}
+
+impl Default for DistanceView {
+    fn default() -> Self {
+        Self {
+            all_shapes: false,
+            spacing: consts::DEFAULT_DISTANCE_VIEW_SPACING,
+            heatmap: false,
+        }
+    }
+}
+
+impl DistanceView {
+    /// Returns the fill code of the shape, which is replaced if the distance view applies to it.
+    pub fn fill_code<'a>(&self, shape_state: &'a ShapeState) -> Cow<'a, str> {
+        if self.all_shapes || shape_state.show_distance {
+            Cow::Owned(self.code())
+        } else {
+            Cow::Borrowed(&shape_state.fill_code)
+        }
+    }
+
+    fn code(&self) -> String {
+        format!(
+            "#define_import_path smud_demo::fill::distance_view\n\n\
+             #import smud\n\n\
+             const SPACING = {:?};\n\
+             const HEATMAP = {};\n\n\
+             {}",
+            self.spacing.max(f32::EPSILON),
+            self.heatmap,
+            include_str!("../shaders/distance_view.wgsl"),
+        )
+    }
+}
+
+/// Recompiles the fills of all shapes when the settings of the distance view changed.
+pub fn update_distance_view(
+    mut applied: Local<DistanceView>,
+    mut global_state: ResMut<GlobalState>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut shape_query: Query<(&mut SmudShape, &ShapeState)>,
+) {
+    if *applied == global_state.distance_view {
+        return;
+    }
+    let distance_view = global_state.distance_view.clone();
+    for (mut shape, shape_state) in &mut shape_query {
+        let code = distance_view.fill_code(shape_state);
+        shape.fill = create_shader(&mut shaders, &mut global_state, &code);
+    }
+    *applied = distance_view;
+}
+
+pub fn distance_view_ui(ui: &mut egui::Ui, distance_view: &mut DistanceView) {
+    ui.label("Distance view:");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut distance_view.all_shapes, "All shapes")
+            .on_hover_text("Shapes can also enable the distance view individually");
+        ui.label("spacing");
+        ui.add(
+            egui::DragValue::new(&mut distance_view.spacing)
+                .range(1.0..=f32::MAX)
+                .speed(1.0),
+        )
+        .on_hover_text("Distance between contour lines");
+        ui.checkbox(&mut distance_view.heatmap, "Gradient heatmap")
+            .on_hover_text(
+                "Green where the gradient has length 1 like an exact distance, blue where the SDF \
+                 underestimates and red where it overestimates the distance",
+            );
+    });
+    ui.end_row();
+}
@@ -0,0 +1,360 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use bevy_smud::SmudShape;
+
+use crate::{
+    ShapeCamera, consts,
+    cursor::{UserInput, Viewport},
+    outline::{LINE_FUNCTIONS, QUADRATIC_FUNCTIONS},
+    shape::{ShapeTransforms, update_shape},
+    state::{GlobalState, SelectedTab, ShapeId, ShapeState},
+};
+
+/// An outline of a shape drawn in the viewport.
+#[derive(Clone, Debug, Default)]
+pub struct Drawing {
+    pub mode: DrawMode,
+    /// Points in the local space of the shape.
+    pub points: Vec<Vec2>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DrawMode {
+    #[default]
+    Polygon,
+    /// Every second point is the control point of a quadratic Bézier segment.
+    Curves,
+}
+
+/// Edits the [`Drawing`] of a shape with the mouse.
+#[derive(Resource, Default)]
+pub struct DrawTool {
+    /// The shape that is currently drawn.
+    pub shape: Option<ShapeId>,
+    /// The index of the point that is dragged.
+    dragging: Option<usize>,
+    /// Whether the SDF code needs to be regenerated.
+    pub changed: bool,
+}
+
+impl DrawTool {
+    pub fn is_drawing(&self, shape: ShapeId) -> bool {
+        self.shape == Some(shape)
+    }
+}
+
+impl Drawing {
+    /// Returns the WGSL code of an SDF with the points embedded as a constant array.
+    pub fn sdf_code(&self) -> String {
+        let mut points = self.points.clone();
+        if self.mode == DrawMode::Curves && points.len() % 2 == 1 {
+            // The closing segment is straight
+            points.push(points[points.len() - 1].midpoint(points[0]));
+        }
+        let n = points.len();
+
+        let mut code = format!(
+            "#define_import_path {}\n\n#import smud\n\n\
+             // Drawn in the viewport, editing the drawing replaces this code\n",
+            consts::DRAWING_SDF_IMPORT_PATH
+        );
+        if n < 3 {
+            code.push_str("\nfn sdf(input: smud::SdfInput) -> f32 {\n    return 1e20;\n}\n");
+            return code;
+        }
+
+        let _ = writeln!(code, "const POINTS = array<vec2<f32>, {n}>(");
+        for point in &points {
+            let _ = writeln!(code, "    vec2({:?}, {:?}),", point.x, point.y);
+        }
+        code.push_str(");\n\n");
+
+        code.push_str(
+            "fn sdf(input: smud::SdfInput) -> f32 {\n    \
+                 let p = input.pos;\n    \
+                 var distance = 1e20;\n    \
+                 var winding = 0;\n",
+        );
+        let _ = match self.mode {
+            DrawMode::Polygon => write!(
+                code,
+                "    for (var i = 0; i < {n}; i++) {{\n        \
+                         let a = POINTS[i];\n        \
+                         let b = POINTS[(i + 1) % {n}];\n        \
+                         distance = min(distance, line_distance(p, a, b));\n        \
+                         winding += line_winding(p, a, b);\n    \
+                     }}\n"
+            ),
+            DrawMode::Curves => write!(
+                code,
+                "    for (var i = 0; i < {n}; i += 2) {{\n        \
+                         let a = POINTS[i];\n        \
+                         let b = POINTS[i + 1];\n        \
+                         let c = POINTS[(i + 2) % {n}];\n        \
+                         distance = min(distance, quadratic_distance(p, a, b, c));\n        \
+                         winding += line_winding(p, a, c) + quadratic_region_winding(p, a, b, c);\n    \
+                     }}\n"
+            ),
+        };
+        code.push_str("    return select(distance, -distance, winding != 0);\n}\n");
+
+        code.push_str(LINE_FUNCTIONS);
+        if self.mode == DrawMode::Curves {
+            code.push_str(QUADRATIC_FUNCTIONS);
+        }
+        code
+    }
+}
+
+fn local_to_world(shape_world: &GlobalTransform, local: Vec2) -> Vec2 {
+    shape_world.transform_point(local.extend(0.0)).xy()
+}
+
+/// Returns `None` if the shape is scaled to zero, so no point maps into its local space.
+fn world_to_local(shape_world: &GlobalTransform, world: Vec2) -> Option<Vec2> {
+    let affine = shape_world.affine();
+    if affine.matrix3.determinant() == 0.0 {
+        return None;
+    }
+    Some(affine.inverse().transform_point3(world.extend(0.0)).xy())
+}
+
+/// Adds points by clicking and moves them by dragging, then regenerates the SDF of the shape.
+pub fn draw_tool_input(
+    mut draw_tool: ResMut<DrawTool>,
+    mut global_state: ResMut<GlobalState>,
+    mut shaders: ResMut<Assets<Shader>>,
+    input: UserInput,
+    viewport: Viewport,
+    mut shape_query: Query<(&mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let UserInput {
+        mouse,
+        egui_wants_input,
+        ..
+    } = input;
+    let Viewport { window, camera } = viewport;
+    let Some(id) = draw_tool.shape else {
+        return;
+    };
+    let shape_world =
+        ShapeTransforms::new(shape_query.iter().map(|(_, _, shape_state)| shape_state)).world(id);
+    let Some((mut transform, mut shape, mut shape_state)) = shape_query
+        .iter_mut()
+        .find(|(_, _, shape_state)| shape_state.id == id)
+        .filter(|(_, _, shape_state)| {
+            global_state.selected_tab() == SelectedTab::Shape(id) && !shape_state.locked
+        })
+    else {
+        // The shape was deleted, deselected or locked
+        *draw_tool = DrawTool::default();
+        return;
+    };
+
+    let (camera, camera_transform) = *camera;
+    if let Some(cursor) = window.cursor_position()
+        && let Ok(world) = camera.viewport_to_world_2d(camera_transform, cursor)
+        && let Some(local) = world_to_local(&shape_world, world)
+    {
+        if mouse.just_pressed(MouseButton::Left) && !egui_wants_input.is_pointer_over_area() {
+            let points = shape_state
+                .drawing
+                .as_ref()
+                .map_or(&[][..], |drawing| &drawing.points);
+            let hovered = points.iter().position(|point| {
+                let world = local_to_world(&shape_world, *point);
+                camera
+                    .world_to_viewport(camera_transform, world.extend(0.0))
+                    .is_ok_and(|viewport| viewport.distance(cursor) <= consts::DRAW_POINT_RADIUS)
+            });
+            match hovered {
+                Some(index) => draw_tool.dragging = Some(index),
+                None => {
+                    shape_state
+                        .drawing
+                        .get_or_insert_default()
+                        .points
+                        .push(local);
+                    draw_tool.changed = true;
+                }
+            }
+        }
+
+        if let Some(index) = draw_tool.dragging
+            && let Some(point) = shape_state
+                .drawing
+                .as_mut()
+                .and_then(|drawing| drawing.points.get_mut(index))
+        {
+            *point = local;
+        }
+    }
+
+    // The shader is only compiled once the point is dropped
+    if mouse.just_released(MouseButton::Left) && draw_tool.dragging.take().is_some() {
+        draw_tool.changed = true;
+    }
+
+    if std::mem::take(&mut draw_tool.changed)
+        && let Some(drawing) = shape_state.drawing.clone()
+    {
+        shape_state.sdf_code = drawing.sdf_code();
+
+        // Make sure all points are within the bounds
+        let extent = drawing
+            .points
+            .iter()
+            .map(|point| point.abs().max_element())
+            .fold(0.0, f32::max);
+        let bounds_length = 2.0 * extent * consts::IMPORTED_SHAPE_BOUNDS_FACTOR;
+        shape_state.bounds_length = shape_state.bounds_length.max(bounds_length);
+
+        update_shape(
+            &mut global_state,
+            &mut shaders,
+            &mut transform,
+            &mut shape,
+            &shape_state,
+            true,
+        );
+    }
+}
+
+/// Shows the points and segments of the drawing in the viewport.
+pub fn draw_tool_overlay(
+    mut contexts: EguiContexts,
+    draw_tool: Res<DrawTool>,
+    camera: Single<(&Camera, &GlobalTransform), With<ShapeCamera>>,
+    shape_query: Query<&ShapeState>,
+) -> Result {
+    let Some(shape_state) = draw_tool
+        .shape
+        .and_then(|id| shape_query.iter().find(|shape_state| shape_state.id == id))
+    else {
+        return Ok(());
+    };
+    let Some(drawing) = &shape_state.drawing else {
+        return Ok(());
+    };
+
+    let ctx = contexts.ctx_mut()?;
+    let (camera, camera_transform) = *camera;
+    let shape_world = ShapeTransforms::new(&shape_query).world(shape_state.id);
+    let points: Vec<_> = drawing
+        .points
+        .iter()
+        .filter_map(|point| {
+            let world = local_to_world(&shape_world, *point);
+            let viewport = camera
+                .world_to_viewport(camera_transform, world.extend(0.0))
+                .ok()?;
+            Some(egui::pos2(viewport.x, viewport.y))
+        })
+        .collect();
+    if points.len() != drawing.points.len() {
+        return Ok(());
+    }
+
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("draw_tool"),
+    ));
+    let color = ctx.style().visuals.selection.stroke.color;
+    let stroke = egui::Stroke::new(2.0, color);
+    let thin_stroke = egui::Stroke::new(1.0, color.gamma_multiply(0.5));
+
+    let n = points.len();
+    match drawing.mode {
+        DrawMode::Polygon => {
+            if n >= 2 {
+                painter.add(egui::Shape::closed_line(points.clone(), stroke));
+            }
+        }
+        DrawMode::Curves => {
+            // With an odd number of points the closing segment is straight
+            for i in (0..n).step_by(2) {
+                let a = points[i];
+                if i + 1 < n {
+                    let (b, c) = (points[i + 1], points[(i + 2) % n]);
+                    painter.add(egui::epaint::QuadraticBezierShape::from_points_stroke(
+                        [a, b, c],
+                        false,
+                        egui::Color32::TRANSPARENT,
+                        stroke,
+                    ));
+                    painter.line_segment([a, b], thin_stroke);
+                    painter.line_segment([b, c], thin_stroke);
+                } else {
+                    painter.line_segment([a, points[0]], stroke);
+                }
+            }
+        }
+    }
+
+    for (i, point) in points.iter().enumerate() {
+        let radius = consts::DRAW_POINT_RADIUS / 2.0;
+        if drawing.mode == DrawMode::Curves && i % 2 == 1 {
+            painter.circle_stroke(*point, radius, stroke);
+        } else {
+            painter.circle_filled(*point, radius, color);
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the UI for drawing the outline of the shape.
+pub fn drawing_ui(ui: &mut egui::Ui, shape_state: &mut ShapeState, draw_tool: &mut DrawTool) {
+    if !draw_tool.is_drawing(shape_state.id) {
+        if ui
+            .add_enabled(!shape_state.locked, egui::Button::new("✏ Draw"))
+            .on_hover_text("Draw the outline of the shape in the viewport")
+            .clicked()
+        {
+            *draw_tool = DrawTool {
+                shape: Some(shape_state.id),
+                ..default()
+            };
+            shape_state.text = None;
+        }
+        return;
+    }
+
+    let drawing = shape_state.drawing.get_or_insert_default();
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            if ui.button("Finish").clicked() {
+                *draw_tool = DrawTool::default();
+            }
+            draw_tool.changed |= ui
+                .selectable_value(&mut drawing.mode, DrawMode::Polygon, "Polygon")
+                .changed();
+            draw_tool.changed |= ui
+                .selectable_value(&mut drawing.mode, DrawMode::Curves, "Curves")
+                .changed();
+            if ui
+                .add_enabled(!drawing.points.is_empty(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                drawing.points.pop();
+                draw_tool.changed = true;
+            }
+            if ui
+                .add_enabled(!drawing.points.is_empty(), egui::Button::new("Clear"))
+                .clicked()
+            {
+                drawing.points.clear();
+                draw_tool.changed = true;
+            }
+        });
+        ui.label(match drawing.mode {
+            DrawMode::Polygon => "Click to add points, drag points to move them",
+            DrawMode::Curves => {
+                "Click to add points, every second point is a control point, drag points to move \
+                 them"
+            }
+        });
+    });
+}
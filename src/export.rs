@@ -0,0 +1,1059 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    camera::{RenderTarget, ScalingMode, visibility::RenderLayers},
+    color::ColorToPacked,
+    prelude::*,
+    render::{
+        render_resource::TextureFormat,
+        view::screenshot::{Screenshot, ScreenshotCaptured},
+    },
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+    window::PrimaryWindow,
+};
+use bevy_egui::egui::{self, Widget};
+use bevy_smud::{BlendMode, SmudShape};
+use image::{
+    Delay, Frame, RgbaImage,
+    codecs::{
+        gif::{GifEncoder, Repeat},
+        png::PngEncoder,
+        webp::WebPEncoder,
+    },
+    imageops,
+};
+
+use crate::{
+    clock::Clock,
+    consts,
+    cpu_shader::{GlobalInputs, ShapeEvaluator, ShapeInputs},
+    cursor::Viewport,
+    file::save_file,
+    shape::{ShapeTransforms, shape_bounds},
+    state::{GlobalState, SelectedTab, ShapeState},
+    trace,
+    util::convert_color,
+};
+
+/// Renders the scene without the UI into offscreen images or traces the shapes into SVG paths,
+/// and saves them.
+#[derive(Resource, Default)]
+pub struct Exporter {
+    pub image_window_open: bool,
+    pub image: ImageSettings,
+    pub animation_window_open: bool,
+    pub animation: AnimationSettings,
+    pub svg_window_open: bool,
+    pub svg: SvgSettings,
+    svg_requested: bool,
+    request: Option<Request>,
+    job: Option<Job>,
+    /// Downsamples, encodes and saves the frames of a finished job without blocking the app.
+    encoding: Option<Task<Result<String, String>>>,
+    status: Option<Result<String, String>>,
+}
+
+impl Exporter {
+    pub fn is_running(&self) -> bool {
+        self.job.is_some() || self.encoding.is_some()
+    }
+
+    /// Whether frames are rendered, which controls the [`Clock`].
+    pub fn is_rendering(&self) -> bool {
+        self.job.is_some()
+    }
+}
+
+pub struct ImageSettings {
+    pub area: ExportArea,
+    /// Only used for [`ExportArea::Rectangle`].
+    pub rect: Rect,
+    pub width: u32,
+    pub height: u32,
+    pub supersampling: u32,
+    pub transparent: bool,
+    pub path: String,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        Self {
+            area: ExportArea::View,
+            rect: Rect::from_center_size(Vec2::ZERO, Vec2::splat(500.0)),
+            width: 1920,
+            height: 1080,
+            supersampling: 2,
+            transparent: false,
+            path: consts::DEFAULT_IMAGE_EXPORT_PATH.to_owned(),
+        }
+    }
+}
+
+/// The area in world space that should be exported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportArea {
+    /// The area visible in the window that is not covered by the side panel.
+    View,
+    SelectedShape,
+    Rectangle,
+}
+
+pub struct AnimationSettings {
+    pub start: f32,
+    pub end: f32,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: AnimationFormat,
+    pub path: String,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            end: 2.0,
+            fps: 30,
+            width: 800,
+            height: 600,
+            format: AnimationFormat::Gif,
+            path: consts::DEFAULT_ANIMATION_EXPORT_PATH.to_owned(),
+        }
+    }
+}
+
+pub struct SvgSettings {
+    /// Number of samples along each side of a shape's bounds.
+    pub resolution: u32,
+    /// Maximum deviation of the simplified paths in world space.
+    pub tolerance: f32,
+    pub path: String,
+}
+
+impl Default for SvgSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 128,
+            tolerance: 0.5,
+            path: consts::DEFAULT_SVG_EXPORT_PATH.to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimationFormat {
+    PngSequence,
+    Gif,
+    WebP,
+}
+
+impl AnimationFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::PngSequence => "png",
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::WebP => "webp",
+        }
+    }
+}
+
+/// What should be rendered, requested by the UI.
+struct Request {
+    area: ExportArea,
+    rect: Rect,
+    width: u32,
+    height: u32,
+    supersampling: u32,
+    transparent: bool,
+    /// Only used for [`Output::Animation`], still images are rendered at the current time.
+    times: Vec<f32>,
+    output: Output,
+}
+
+enum Output {
+    Image {
+        path: PathBuf,
+    },
+    Animation {
+        format: AnimationFormat,
+        fps: u32,
+        path: PathBuf,
+    },
+}
+
+struct Job {
+    camera: Entity,
+    /// The shape that is moved to the render layer of the camera, if only it is exported.
+    isolated: Option<Entity>,
+    target: Handle<Image>,
+    /// The size of the saved images, the target might be larger due to supersampling.
+    size: UVec2,
+    times: Vec<f32>,
+    delta: f32,
+    /// Number of frames to wait before the first capture, so the new camera is ready.
+    warmup: u32,
+    next_frame: usize,
+    frames: Vec<Option<RgbaImage>>,
+    error: Option<String>,
+    output: Output,
+    /// Restored after the job is finished.
+    clock: Clock,
+}
+
+pub fn export_windows(ctx: &egui::Context, exporter: &mut Exporter, selected_shape: bool) {
+    image_window(ctx, exporter, selected_shape);
+    animation_window(ctx, exporter);
+    svg_window(ctx, exporter);
+}
+
+fn image_window(ctx: &egui::Context, exporter: &mut Exporter, selected_shape: bool) {
+    let mut open = exporter.image_window_open;
+    egui::Window::new("Export image")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let running = exporter.is_running();
+            let settings = &mut exporter.image;
+
+            ui.add_enabled_ui(!running, |ui| {
+                egui::Grid::new("grid_export_image")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Area:");
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut settings.area, ExportArea::View, "View");
+                            ui.add_enabled_ui(selected_shape, |ui| {
+                                ui.selectable_value(
+                                    &mut settings.area,
+                                    ExportArea::SelectedShape,
+                                    "Selected shape",
+                                );
+                            });
+                            ui.selectable_value(
+                                &mut settings.area,
+                                ExportArea::Rectangle,
+                                "Rectangle",
+                            );
+                        });
+                        ui.end_row();
+
+                        if settings.area == ExportArea::Rectangle {
+                            ui.label("Rectangle:");
+                            ui.horizontal(|ui| {
+                                ui.label("min");
+                                egui::DragValue::new(&mut settings.rect.min.x).ui(ui);
+                                egui::DragValue::new(&mut settings.rect.min.y).ui(ui);
+                                ui.label("max");
+                                egui::DragValue::new(&mut settings.rect.max.x).ui(ui);
+                                egui::DragValue::new(&mut settings.rect.max.y).ui(ui);
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("Resolution:").on_hover_text(
+                            "The image keeps the aspect ratio of the area and fits into this size",
+                        );
+                        resolution_ui(ui, &mut settings.width, &mut settings.height);
+                        ui.end_row();
+
+                        ui.label("Supersampling:");
+                        egui::Slider::new(&mut settings.supersampling, 1..=4)
+                            .suffix("×")
+                            .ui(ui);
+                        ui.end_row();
+
+                        ui.label("Transparent background:");
+                        ui.checkbox(&mut settings.transparent, "");
+                        ui.end_row();
+
+                        ui.label(if cfg!(target_arch = "wasm32") {
+                            "File name:"
+                        } else {
+                            "Path:"
+                        });
+                        ui.text_edit_singleline(&mut settings.path);
+                        ui.end_row();
+                    });
+            });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!running, egui::Button::new("Export"))
+                    .clicked()
+                {
+                    exporter.request = Some(Request {
+                        area: settings.area,
+                        rect: settings.rect,
+                        width: settings.width,
+                        height: settings.height,
+                        supersampling: settings.supersampling,
+                        transparent: settings.transparent,
+                        times: Vec::new(),
+                        output: Output::Image {
+                            path: PathBuf::from(&settings.path),
+                        },
+                    });
+                    exporter.status = None;
+                }
+                progress_ui(ui, exporter);
+            });
+
+            status_ui(ui, &exporter.status);
+        });
+    exporter.image_window_open = open;
+}
+
+fn animation_window(ctx: &egui::Context, exporter: &mut Exporter) {
+    let mut open = exporter.animation_window_open;
+    egui::Window::new("Export animation")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let running = exporter.is_running();
+            let settings = &mut exporter.animation;
+
+            ui.add_enabled_ui(!running, |ui| {
+                egui::Grid::new("grid_export_animation")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Time range:");
+                        ui.horizontal(|ui| {
+                            egui::DragValue::new(&mut settings.start)
+                                .range(0.0..=f32::MAX)
+                                .speed(0.1)
+                                .suffix(" s")
+                                .ui(ui);
+                            ui.label("to");
+                            egui::DragValue::new(&mut settings.end)
+                                .range(settings.start..=f32::MAX)
+                                .speed(0.1)
+                                .suffix(" s")
+                                .ui(ui);
+                        });
+                        ui.end_row();
+
+                        ui.label("Frame rate:");
+                        egui::DragValue::new(&mut settings.fps)
+                            .range(1..=120)
+                            .suffix(" fps")
+                            .ui(ui);
+                        ui.end_row();
+
+                        ui.label("Resolution:").on_hover_text(
+                            "The image keeps the aspect ratio of the area and fits into this size",
+                        );
+                        resolution_ui(ui, &mut settings.width, &mut settings.height);
+                        ui.end_row();
+
+                        ui.label("Format:");
+                        egui::ComboBox::from_id_salt("animation_format")
+                            .selected_text(format!("{:?}", settings.format))
+                            .show_ui(ui, |ui| {
+                                for format in [
+                                    AnimationFormat::PngSequence,
+                                    AnimationFormat::Gif,
+                                    AnimationFormat::WebP,
+                                ] {
+                                    let changed = ui
+                                        .selectable_value(
+                                            &mut settings.format,
+                                            format,
+                                            format!("{format:?}"),
+                                        )
+                                        .changed();
+                                    if changed {
+                                        settings.path = Path::new(&settings.path)
+                                            .with_extension(format.extension())
+                                            .to_string_lossy()
+                                            .into_owned();
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label(if cfg!(target_arch = "wasm32") {
+                            "File name:"
+                        } else {
+                            "Path:"
+                        });
+                        ui.text_edit_singleline(&mut settings.path);
+                        ui.end_row();
+                    });
+            });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let frame_count = settings.frame_count();
+                if ui
+                    .add_enabled(!running, egui::Button::new("Export"))
+                    .clicked()
+                {
+                    let delta = 1.0 / settings.fps as f32;
+                    exporter.request = Some(Request {
+                        area: ExportArea::View,
+                        rect: Rect::EMPTY,
+                        width: settings.width,
+                        height: settings.height,
+                        supersampling: 1,
+                        transparent: false,
+                        times: (0..frame_count)
+                            .map(|i| settings.start + i as f32 * delta)
+                            .collect(),
+                        output: Output::Animation {
+                            format: settings.format,
+                            fps: settings.fps,
+                            path: PathBuf::from(&settings.path),
+                        },
+                    });
+                    exporter.status = None;
+                }
+                if exporter.is_running() {
+                    progress_ui(ui, exporter);
+                } else {
+                    ui.label(format!("{frame_count} frames"));
+                }
+            });
+
+            status_ui(ui, &exporter.status);
+        });
+    exporter.animation_window_open = open;
+}
+
+fn svg_window(ctx: &egui::Context, exporter: &mut Exporter) {
+    let mut open = exporter.svg_window_open;
+    egui::Window::new("Export SVG")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let settings = &mut exporter.svg;
+
+            egui::Grid::new("grid_export_svg")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Samples per shape:");
+                    egui::DragValue::new(&mut settings.resolution)
+                        .range(16..=1024)
+                        .suffix("²")
+                        .ui(ui);
+                    ui.end_row();
+
+                    ui.label("Tolerance:");
+                    egui::DragValue::new(&mut settings.tolerance)
+                        .range(0.0..=10.0)
+                        .speed(0.01)
+                        .ui(ui);
+                    ui.end_row();
+
+                    ui.label(if cfg!(target_arch = "wasm32") {
+                        "File name:"
+                    } else {
+                        "Path:"
+                    });
+                    ui.text_edit_singleline(&mut settings.path);
+                    ui.end_row();
+                });
+
+            ui.separator();
+
+            if ui.button("Export").clicked() {
+                exporter.svg_requested = true;
+                exporter.status = None;
+            }
+
+            status_ui(ui, &exporter.status);
+        });
+    exporter.svg_window_open = open;
+}
+
+impl AnimationSettings {
+    fn frame_count(&self) -> usize {
+        let duration = (self.end - self.start).max(0.0);
+        ((duration * self.fps as f32).round() as usize).max(1)
+    }
+}
+
+fn resolution_ui(ui: &mut egui::Ui, width: &mut u32, height: &mut u32) {
+    ui.horizontal(|ui| {
+        egui::DragValue::new(width)
+            .range(1..=consts::MAX_EXPORT_RESOLUTION)
+            .ui(ui);
+        ui.label("×");
+        egui::DragValue::new(height)
+            .range(1..=consts::MAX_EXPORT_RESOLUTION)
+            .ui(ui);
+    });
+}
+
+fn progress_ui(ui: &mut egui::Ui, exporter: &Exporter) {
+    if let Some(job) = &exporter.job {
+        ui.spinner();
+        ui.label(format!(
+            "Rendering frame {}/{}",
+            job.next_frame.min(job.times.len()),
+            job.times.len()
+        ));
+    } else if exporter.encoding.is_some() {
+        ui.spinner();
+        ui.label("Encoding…");
+    }
+}
+
+pub fn status_ui(ui: &mut egui::Ui, status: &Option<Result<String, String>>) {
+    match status {
+        Some(Ok(message)) => {
+            ui.label(message);
+        }
+        Some(Err(error)) => {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        None => {}
+    }
+}
+
+/// Starts requested jobs, sets the clock for the current frame and captures it. Finished jobs are
+/// encoded in the background.
+pub fn drive_export(
+    mut commands: Commands,
+    mut exporter: ResMut<Exporter>,
+    mut clock: ResMut<Clock>,
+    mut images: ResMut<Assets<Image>>,
+    global_state: Res<GlobalState>,
+    viewport: Viewport,
+    shape_query: Query<(Entity, &ShapeState)>,
+) {
+    let Viewport { window, camera } = viewport;
+    if let Some(task) = &mut exporter.encoding
+        && let Some(status) = block_on(future::poll_once(task))
+    {
+        exporter.encoding = None;
+        exporter.status = Some(status);
+    }
+
+    if let Some(request) = exporter.request.take()
+        && !exporter.is_running()
+    {
+        let (camera, camera_transform) = *camera;
+        let selected = match global_state.selected_tab() {
+            SelectedTab::Shape(id) if request.area == ExportArea::SelectedShape => shape_query
+                .iter()
+                .find(|(_, shape_state)| shape_state.id == id),
+            _ => None,
+        };
+        let area = match request.area {
+            ExportArea::View => visible_area(&window, &global_state, camera, camera_transform),
+            ExportArea::SelectedShape => selected.map(|(_, shape_state)| {
+                let transforms = ShapeTransforms::new(shape_query.iter().map(|(_, state)| state));
+                shape_bounds(shape_state, &transforms.world(shape_state.id))
+            }),
+            ExportArea::Rectangle => Some(request.rect),
+        };
+        let Some(area) = area.filter(|area| !area.is_empty()) else {
+            exporter.status = Some(Err("The area to export is empty".to_owned()));
+            return;
+        };
+
+        // The image has the aspect ratio of the area and fits into the requested resolution
+        let scale =
+            (UVec2::new(request.width, request.height).as_vec2() / area.size()).min_element();
+        let size = (area.size() * scale).round().max(Vec2::ONE).as_uvec2();
+        let target_size = size * request.supersampling;
+        if target_size.max_element() > consts::MAX_EXPORT_RESOLUTION {
+            exporter.status = Some(Err(format!(
+                "The resolution including supersampling must not exceed {}",
+                consts::MAX_EXPORT_RESOLUTION
+            )));
+            return;
+        }
+
+        let background = if request.transparent {
+            Color::NONE
+        } else {
+            convert_color(global_state.background_color)
+        };
+        let target = images.add(Image::new_target_texture(
+            target_size.x,
+            target_size.y,
+            TextureFormat::bevy_default(),
+        ));
+        let mut camera = commands.spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Image(target.clone().into()),
+                clear_color: ClearColorConfig::Custom(background),
+                order: -1,
+                ..default()
+            },
+            Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed {
+                    width: area.width(),
+                    height: area.height(),
+                },
+                ..OrthographicProjection::default_2d()
+            }),
+            Msaa::Off,
+            Transform::from_translation(area.center().extend(camera_transform.translation().z)),
+        ));
+        global_state.post_process.insert_into(&mut camera);
+        // Overlapping shapes aren't exported with the selected shape
+        let isolated = selected.map(|(entity, _)| entity);
+        if isolated.is_some() {
+            camera.insert(RenderLayers::layer(consts::EXPORT_RENDER_LAYER));
+        }
+        let camera = camera.id();
+        if let Some(entity) = isolated {
+            commands
+                .entity(entity)
+                .insert(RenderLayers::from_layers(&[0, consts::EXPORT_RENDER_LAYER]));
+        }
+
+        let (times, delta) = match &request.output {
+            Output::Image { .. } => (vec![clock.elapsed], clock.delta),
+            Output::Animation { fps, .. } => (request.times, 1.0 / *fps as f32),
+        };
+        let frame_count = times.len();
+        exporter.job = Some(Job {
+            camera,
+            isolated,
+            target,
+            size,
+            times,
+            delta,
+            warmup: consts::EXPORT_WARMUP_FRAMES,
+            next_frame: 0,
+            frames: vec![None; frame_count],
+            error: None,
+            output: request.output,
+            clock: clock.clone(),
+        });
+    }
+
+    let Some(job) = &mut exporter.job else {
+        return;
+    };
+
+    if job.frames.iter().all(Option::is_some) {
+        let job = exporter.job.take().expect("Job exists");
+        *clock = job.clock;
+        commands.entity(job.camera).despawn();
+        if let Some(entity) = job.isolated
+            && let Ok(mut entity) = commands.get_entity(entity)
+        {
+            entity.try_remove::<RenderLayers>();
+        }
+        images.remove(&job.target);
+        match job.error {
+            Some(error) => exporter.status = Some(Err(error)),
+            None => {
+                let (frames, size, output) = (job.frames, job.size, job.output);
+                exporter.encoding = Some(AsyncComputeTaskPool::get().spawn(async move {
+                    let frames = frames
+                        .into_iter()
+                        .flatten()
+                        .map(|frame| downsample(frame, size))
+                        .collect();
+                    save_output(output, frames)
+                }));
+            }
+        }
+        return;
+    }
+
+    let Some(&time) = job.times.get(job.next_frame) else {
+        // All frames are requested, wait for the remaining captures
+        return;
+    };
+    clock.elapsed = time;
+    clock.delta = job.delta;
+    clock.frame = job.next_frame as u32;
+
+    if job.warmup > 0 {
+        job.warmup -= 1;
+        return;
+    }
+
+    let index = job.next_frame;
+    job.next_frame += 1;
+    commands
+        .spawn(Screenshot::image(job.target.clone()))
+        .observe(
+            move |captured: On<ScreenshotCaptured>, mut exporter: ResMut<Exporter>| {
+                let frame = captured
+                    .image
+                    .clone()
+                    .try_into_dynamic()
+                    .map(|image| image.to_rgba8());
+                let Some(job) = &mut exporter.job else {
+                    return;
+                };
+                job.frames[index] = Some(frame.unwrap_or_else(|error| {
+                    job.error = Some(format!("Failed to convert captured frame: {error}"));
+                    // Use an empty frame to finish the job anyway
+                    RgbaImage::new(1, 1)
+                }));
+            },
+        );
+}
+
+/// Traces the zero iso-contour of each shape's SDF on the CPU and saves them as SVG paths.
+pub fn export_svg(
+    mut exporter: ResMut<Exporter>,
+    mut evaluator: ShapeEvaluator,
+    window: Single<&Window, With<PrimaryWindow>>,
+    shape_query: Query<(&ShapeState, &SmudShape)>,
+) {
+    if !std::mem::take(&mut exporter.svg_requested) {
+        return;
+    }
+
+    let globals = evaluator.globals(&window);
+    let transforms = ShapeTransforms::new(shape_query.iter().map(|(shape_state, _)| shape_state));
+    let mut shapes: Vec<_> = shape_query
+        .iter()
+        .filter(|(shape_state, _)| shape_state.visible)
+        .map(|(shape_state, shape)| (shape_state, shape, transforms.world(shape_state.id)))
+        .collect();
+    shapes.sort_by(|(a, _, a_world), (b, _, b_world)| {
+        let (a_z, b_z) = (a_world.translation().z, b_world.translation().z);
+        a_z.total_cmp(&b_z).then(a.id.cmp(&b.id))
+    });
+
+    let result = shapes
+        .into_iter()
+        .map(|(shape_state, shape, world)| {
+            svg_shape_path(
+                &exporter.svg,
+                &mut evaluator,
+                globals,
+                shape_state,
+                shape,
+                &world,
+            )
+            .map_err(|error| format!("Shape {}: {error}", shape_state.id))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|paths| {
+            let bounds = paths
+                .iter()
+                .flatten()
+                .fold(Rect::EMPTY, |bounds, (_, path_bounds)| {
+                    bounds.union(*path_bounds)
+                });
+            if bounds.is_empty() {
+                return Err("There are no shapes to export".to_owned());
+            }
+
+            let mut svg = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\" \
+                 width=\"{:.0}\" height=\"{:.0}\">\n",
+                bounds.min.x,
+                bounds.min.y,
+                bounds.width(),
+                bounds.height(),
+                bounds.width().ceil(),
+                bounds.height().ceil(),
+            );
+            for (element, _) in paths.iter().flatten() {
+                svg.push_str(element);
+            }
+            svg.push_str("</svg>\n");
+
+            let path = PathBuf::from(&exporter.svg.path);
+            save_file(&path, svg.as_bytes())?;
+            Ok(format!("Exported SVG to {}", path.display()))
+        });
+    exporter.status = Some(result);
+}
+
+/// Returns the SVG path element of a shape and its bounds in SVG coordinates, or `None` if the
+/// shape is empty.
+fn svg_shape_path(
+    settings: &SvgSettings,
+    evaluator: &mut ShapeEvaluator,
+    globals: GlobalInputs,
+    shape_state: &ShapeState,
+    shape: &SmudShape,
+    world: &GlobalTransform,
+) -> Result<Option<(String, Rect)>, String> {
+    let sdf = evaluator.get(&shape.sdf, "sdf")?;
+    let scale = world.scale().x;
+    let inputs = ShapeInputs::new(shape_state.params, shape, world, 1.0, globals);
+
+    let mut error = None;
+    let area = Rect::from_center_size(Vec2::ZERO, Vec2::splat(shape_state.bounds_length));
+    let contours = trace::trace_contours(area, UVec2::splat(settings.resolution), |pos| {
+        sdf.sdf(pos, &inputs).unwrap_or_else(|e| {
+            error.get_or_insert(e);
+            f32::MAX
+        })
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    // SVG's y axis points down
+    let to_svg = |pos: Vec2| {
+        let world = world.transform_point(pos.extend(0.0));
+        Vec2::new(world.x, -world.y)
+    };
+
+    let mut data = String::new();
+    let mut bounds = Rect::EMPTY;
+    for contour in contours {
+        let polygon: Vec<_> = trace::simplify(&contour, settings.tolerance / scale)
+            .into_iter()
+            .map(to_svg)
+            .collect();
+        if polygon.len() < 3 {
+            continue;
+        }
+
+        data.push_str(&contour_path(&polygon));
+        bounds = polygon
+            .into_iter()
+            .fold(bounds, |bounds, point| bounds.union_point(point));
+    }
+    if data.is_empty() {
+        return Ok(None);
+    }
+
+    let [r, g, b, _] = shape_state.color.to_u8_array();
+    let blend_mode = match shape_state.blend_mode {
+        BlendMode::Alpha => "",
+        BlendMode::Additive => " style=\"mix-blend-mode:plus-lighter\"",
+    };
+    let element = format!(
+        "  <path d=\"{data}\" fill=\"#{r:02x}{g:02x}{b:02x}\" fill-opacity=\"{:.3}\" \
+         fill-rule=\"evenodd\"{blend_mode}/>\n",
+        shape_state.color.alpha
+    );
+    Ok(Some((element, bounds)))
+}
+
+/// Returns the path data of a closed curve through the points of the polygon.
+fn contour_path(polygon: &[Vec2]) -> String {
+    // The first segment starts at the first point
+    let start = polygon[0];
+    let mut data = format!("M{:.2} {:.2}", start.x, start.y);
+    for segment in trace::fit_beziers(polygon, consts::SVG_CORNER_ANGLE) {
+        let (c1, c2, end) = (segment.control1, segment.control2, segment.end);
+        data.push_str(&format!(
+            "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+            c1.x, c1.y, c2.x, c2.y, end.x, end.y
+        ));
+    }
+    data.push('Z');
+    data
+}
+
+/// Returns the area in world space that is visible in the window and not covered by the side
+/// panel.
+fn visible_area(
+    window: &Window,
+    global_state: &GlobalState,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Rect> {
+    let top_left = Vec2::new(global_state.side_panel_width, 0.0);
+    let bottom_right = window.size();
+    let a = camera
+        .viewport_to_world_2d(camera_transform, top_left)
+        .ok()?;
+    let b = camera
+        .viewport_to_world_2d(camera_transform, bottom_right)
+        .ok()?;
+    let area = Rect::from_corners(a, b);
+    (!area.is_empty()).then_some(area)
+}
+
+fn downsample(frame: RgbaImage, size: UVec2) -> RgbaImage {
+    if frame.dimensions() == (size.x, size.y) {
+        frame
+    } else {
+        imageops::resize(&frame, size.x, size.y, imageops::FilterType::Triangle)
+    }
+}
+
+fn save_output(output: Output, frames: Vec<RgbaImage>) -> Result<String, String> {
+    match output {
+        Output::Image { path } => {
+            let frame = frames.first().ok_or("No frame to save")?;
+            save_file(&path, &encode_png(frame)?)?;
+            Ok(format!("Exported image to {}", path.display()))
+        }
+        Output::Animation { format, fps, path } => {
+            let frame_count = frames.len();
+            match format {
+                AnimationFormat::PngSequence => {
+                    let stem = path.with_extension("");
+                    for (i, frame) in frames.iter().enumerate() {
+                        let frame_path = format!("{}_{i:05}.png", stem.display());
+                        save_file(Path::new(&frame_path), &encode_png(frame)?)?;
+                    }
+                }
+                AnimationFormat::Gif => save_file(&path, &encode_gif(frames, fps)?)?,
+                AnimationFormat::WebP => save_file(&path, &encode_webp(&frames, fps)?)?,
+            }
+            Ok(format!(
+                "Exported {frame_count} frames to {}",
+                path.display()
+            ))
+        }
+    }
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    image
+        .write_with_encoder(PngEncoder::new(&mut bytes))
+        .map_err(|error| error.to_string())?;
+    Ok(bytes)
+}
+
+fn encode_gif(frames: Vec<RgbaImage>, fps: u32) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|error| error.to_string())?;
+        let delay = Delay::from_numer_denom_ms(1000, fps);
+        encoder
+            .encode_frames(
+                frames
+                    .into_iter()
+                    .map(|frame| Frame::from_parts(frame, 0, 0, delay)),
+            )
+            .map_err(|error| error.to_string())?;
+    }
+    Ok(bytes)
+}
+
+/// The image crate can only encode still WebP images, so we encode each frame on its own and
+/// assemble the animation container ourselves.
+///
+/// See https://developers.google.com/speed/webp/docs/riff_container#animation
+fn encode_webp(frames: &[RgbaImage], fps: u32) -> Result<Vec<u8>, String> {
+    let first = frames.first().ok_or("No frames to encode")?;
+    let (width, height) = first.dimensions();
+    let duration = (1000.0 / fps as f32).round() as u32;
+
+    let mut chunks = Vec::new();
+
+    let mut vp8x = vec![0b0001_0010, 0, 0, 0]; // Alpha and animation flags
+    vp8x.extend_from_slice(&u24_le(width - 1));
+    vp8x.extend_from_slice(&u24_le(height - 1));
+    push_chunk(&mut chunks, b"VP8X", &vp8x);
+
+    let mut anim = vec![0, 0, 0, 0]; // Background color
+    anim.extend_from_slice(&0u16.to_le_bytes()); // Loop forever
+    push_chunk(&mut chunks, b"ANIM", &anim);
+
+    for frame in frames {
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still)
+            .encode(
+                frame.as_raw(),
+                width,
+                height,
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|error| error.to_string())?;
+        let bitstream = find_chunk(&still, b"VP8L").ok_or("Encoded WebP has no VP8L chunk")?;
+
+        let mut anmf = Vec::new();
+        anmf.extend_from_slice(&u24_le(0)); // Offset x
+        anmf.extend_from_slice(&u24_le(0)); // Offset y
+        anmf.extend_from_slice(&u24_le(width - 1));
+        anmf.extend_from_slice(&u24_le(height - 1));
+        anmf.extend_from_slice(&u24_le(duration));
+        anmf.push(0b0000_0010); // Don't blend, don't dispose
+        push_chunk(&mut anmf, b"VP8L", bitstream);
+        push_chunk(&mut chunks, b"ANMF", &anmf);
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    bytes.extend_from_slice(b"WEBP");
+    bytes.extend_from_slice(&chunks);
+    Ok(bytes)
+}
+
+fn u24_le(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
+
+fn push_chunk(bytes: &mut Vec<u8>, fourcc: &[u8; 4], payload: &[u8]) {
+    bytes.extend_from_slice(fourcc);
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        bytes.push(0);
+    }
+}
+
+/// Returns the payload of the first top-level chunk with the given FourCC in a RIFF file.
+fn find_chunk<'a>(riff: &'a [u8], fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut pos = 12;
+    while pos + 8 <= riff.len() {
+        let size = u32::from_le_bytes(riff[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let payload = riff.get(pos + 8..pos + 8 + size)?;
+        if &riff[pos..pos + 4] == fourcc {
+            return Some(payload);
+        }
+        pos += 8 + size + size % 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the points of the path data, i.e. the start and the segment ends.
+    fn path_points(data: &str) -> Vec<Vec2> {
+        data.trim_end_matches('Z')
+            .split(['M', 'C'])
+            .filter(|command| !command.is_empty())
+            .map(|command| {
+                let numbers: Vec<f32> = command
+                    .split(' ')
+                    .map(|number| number.parse().unwrap())
+                    .collect();
+                Vec2::new(numbers[numbers.len() - 2], numbers[numbers.len() - 1])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn square_path_round_trip() {
+        let half_size = 4.7;
+        let area = Rect::from_center_size(Vec2::ZERO, Vec2::splat(20.0));
+        let contours = trace::trace_contours(area, UVec2::splat(32), |pos| {
+            (pos.abs() - half_size).max_element()
+        });
+        assert_eq!(contours.len(), 1);
+        let polygon = trace::simplify(&contours[0], 0.1);
+
+        let points = path_points(&contour_path(&polygon));
+        assert_eq!(points.len(), polygon.len() + 1);
+        // Each segment ends at the next point and the last one at the start
+        assert!(points[0].distance(polygon[0]) < 0.01);
+        for (point, expected) in points[1..].iter().zip(polygon.iter().cycle().skip(1)) {
+            assert!(point.distance(*expected) < 0.01);
+        }
+        for point in points {
+            assert!((point.abs().max_element() - half_size).abs() < 0.1);
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use std::{collections::HashMap, f32::consts::PI, f32::consts::TAU};
+
+use bevy::prelude::*;
+
+use crate::state::{ShapeField, ShapeId};
+
+/// A user-editable math expression that drives a numeric shape field.
+#[derive(Clone)]
+pub struct Expression {
+    pub source: String,
+    parsed: Result<Expr, String>,
+    eval_error: Option<String>,
+}
+
+impl Expression {
+    pub fn new(source: String) -> Self {
+        let parsed = Expr::parse(&source);
+        Self {
+            source,
+            parsed,
+            eval_error: None,
+        }
+    }
+
+    /// Must be called after `source` was changed.
+    pub fn reparse(&mut self) {
+        self.parsed = Expr::parse(&self.source);
+        self.eval_error = None;
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        match &self.parsed {
+            Ok(_) => self.eval_error.as_deref(),
+            Err(error) => Some(error),
+        }
+    }
+
+    pub fn evaluate(&mut self, variables: &Variables) -> Option<f32> {
+        let expr = self.parsed.as_ref().ok()?;
+        match expr.eval(variables) {
+            // Would end up in transforms and shader inputs
+            Ok(value) if !value.is_finite() => {
+                self.eval_error = Some(format!("The result is {value}"));
+                None
+            }
+            Ok(value) => {
+                self.eval_error = None;
+                Some(value)
+            }
+            Err(error) => {
+                self.eval_error = Some(error);
+                None
+            }
+        }
+    }
+}
+
+/// The values that can be referenced by an expression.
+#[derive(Default)]
+pub struct Variables {
+    pub time: f32,
+    pub delta_time: f32,
+    pub frame: u32,
+    pub mouse: Vec2,
+    pub shapes: HashMap<ShapeId, HashMap<ShapeField, f32>>,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(f32),
+    Var(Var),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Var {
+    Time,
+    DeltaTime,
+    Frame,
+    MouseX,
+    MouseY,
+    Pi,
+    Tau,
+    Shape(ShapeId, ShapeField),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Abs,
+    Sign,
+    Sqrt,
+    Exp,
+    Ln,
+    Floor,
+    Ceil,
+    Round,
+    Fract,
+    Min,
+    Max,
+    Clamp,
+    Mix,
+    Step,
+    Smoothstep,
+}
+
+impl Function {
+    const ALL: [(&str, Function, usize); 22] = [
+        ("sin", Function::Sin, 1),
+        ("cos", Function::Cos, 1),
+        ("tan", Function::Tan, 1),
+        ("asin", Function::Asin, 1),
+        ("acos", Function::Acos, 1),
+        ("atan", Function::Atan, 1),
+        ("atan2", Function::Atan2, 2),
+        ("abs", Function::Abs, 1),
+        ("sign", Function::Sign, 1),
+        ("sqrt", Function::Sqrt, 1),
+        ("exp", Function::Exp, 1),
+        ("ln", Function::Ln, 1),
+        ("floor", Function::Floor, 1),
+        ("ceil", Function::Ceil, 1),
+        ("round", Function::Round, 1),
+        ("fract", Function::Fract, 1),
+        ("min", Function::Min, 2),
+        ("max", Function::Max, 2),
+        ("clamp", Function::Clamp, 3),
+        ("mix", Function::Mix, 3),
+        ("step", Function::Step, 2),
+        ("smoothstep", Function::Smoothstep, 3),
+    ];
+
+    fn apply(self, args: &[f32]) -> f32 {
+        match (self, args) {
+            (Function::Sin, &[x]) => x.sin(),
+            (Function::Cos, &[x]) => x.cos(),
+            (Function::Tan, &[x]) => x.tan(),
+            (Function::Asin, &[x]) => x.asin(),
+            (Function::Acos, &[x]) => x.acos(),
+            (Function::Atan, &[x]) => x.atan(),
+            (Function::Atan2, &[y, x]) => y.atan2(x),
+            (Function::Abs, &[x]) => x.abs(),
+            (Function::Sign, &[x]) => x.signum(),
+            (Function::Sqrt, &[x]) => x.sqrt(),
+            (Function::Exp, &[x]) => x.exp(),
+            (Function::Ln, &[x]) => x.ln(),
+            (Function::Floor, &[x]) => x.floor(),
+            (Function::Ceil, &[x]) => x.ceil(),
+            (Function::Round, &[x]) => x.round(),
+            (Function::Fract, &[x]) => x - x.floor(),
+            (Function::Min, &[a, b]) => a.min(b),
+            (Function::Max, &[a, b]) => a.max(b),
+            (Function::Clamp, &[x, lo, hi]) => x.max(lo).min(hi),
+            (Function::Mix, &[a, b, t]) => a + (b - a) * t,
+            (Function::Step, &[edge, x]) => {
+                if x < edge {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            (Function::Smoothstep, &[lo, hi, x]) => {
+                let t = ((x - lo) / (hi - lo)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+            _ => unreachable!("Arity is checked by the parser"),
+        }
+    }
+}
+
+impl Expr {
+    fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_sum()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected {token}")),
+        }
+    }
+
+    fn eval(&self, variables: &Variables) -> Result<f32, String> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Var(var) => match *var {
+                Var::Time => variables.time,
+                Var::DeltaTime => variables.delta_time,
+                Var::Frame => variables.frame as f32,
+                Var::MouseX => variables.mouse.x,
+                Var::MouseY => variables.mouse.y,
+                Var::Pi => PI,
+                Var::Tau => TAU,
+                Var::Shape(id, field) => *variables
+                    .shapes
+                    .get(&id)
+                    .ok_or_else(|| format!("Unknown shape shape_{id}"))?
+                    .get(&field)
+                    .unwrap_or(&0.0),
+            },
+            Expr::Neg(expr) => -expr.eval(variables)?,
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(variables)?;
+                let rhs = rhs.eval(variables)?;
+                match op {
+                    BinaryOp::Add => lhs + rhs,
+                    BinaryOp::Sub => lhs - rhs,
+                    BinaryOp::Mul => lhs * rhs,
+                    BinaryOp::Div => lhs / rhs,
+                    BinaryOp::Rem => lhs.rem_euclid(rhs),
+                    BinaryOp::Pow => lhs.powf(rhs),
+                }
+            }
+            Expr::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(variables))
+                    .collect::<Result<Vec<_>, _>>()?;
+                function.apply(&args)
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f32),
+    Ident(String),
+    Symbol(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number {value}"),
+            Token::Ident(name) => write!(f, "`{name}`"),
+            Token::Symbol(symbol) => write!(f, "`{symbol}`"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            let mut prev = c;
+            while let Some(&(i, c)) = chars.peek() {
+                let is_exponent_sign = (c == '-' || c == '+') && (prev == 'e' || prev == 'E');
+                if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || is_exponent_sign {
+                    end = i + c.len_utf8();
+                    prev = c;
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let literal = &source[start..end];
+            let value = literal
+                .parse()
+                .map_err(|_| format!("Invalid number `{literal}`"))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                // Dots are allowed for referencing other shapes like `shape_0.x`
+                if c.is_alphanumeric() || c == '_' || c == '.' {
+                    end = i + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Ident(source[start..end].to_owned()));
+        } else if "+-*/%^(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("Unexpected character `{c}`"));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "Unexpected end of expression".to_owned())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            token => Err(format!("Expected `{symbol}`, found {token}")),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_product()?;
+        loop {
+            let op = if self.eat('+') {
+                BinaryOp::Add
+            } else if self.eat('-') {
+                BinaryOp::Sub
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.parse_product()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = if self.eat('*') {
+                BinaryOp::Mul
+            } else if self.eat('/') {
+                BinaryOp::Div
+            } else if self.eat('%') {
+                BinaryOp::Rem
+            } else {
+                return Ok(lhs);
+            };
+            let rhs = self.parse_unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+        } else if self.eat('+') {
+            self.parse_unary()
+        } else {
+            self.parse_power()
+        }
+    }
+
+    fn parse_power(&mut self) -> Result<Expr, String> {
+        let base = self.parse_atom()?;
+        if self.eat('^') {
+            // Right associative and binds tighter than unary minus on the left
+            let exponent = self.parse_unary()?;
+            Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, String> {
+        match self.next()? {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Symbol('(') => {
+                let expr = self.parse_sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Token::Ident(name) if self.eat('(') => {
+                let (_, function, arity) = Function::ALL
+                    .iter()
+                    .find(|(n, _, _)| *n == name)
+                    .ok_or_else(|| format!("Unknown function `{name}`"))?;
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.parse_sum()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                if args.len() != *arity {
+                    return Err(format!(
+                        "`{name}` expects {arity} argument(s), found {}",
+                        args.len()
+                    ));
+                }
+                Ok(Expr::Call(*function, args))
+            }
+            Token::Ident(name) => parse_variable(&name).map(Expr::Var),
+            token => Err(format!("Unexpected {token}")),
+        }
+    }
+}
+
+fn parse_variable(name: &str) -> Result<Var, String> {
+    Ok(match name {
+        "t" | "time" => Var::Time,
+        "dt" => Var::DeltaTime,
+        "frame" => Var::Frame,
+        "mouse_x" => Var::MouseX,
+        "mouse_y" => Var::MouseY,
+        "pi" => Var::Pi,
+        "tau" => Var::Tau,
+        _ => {
+            let (shape, field) = name
+                .split_once('.')
+                .ok_or_else(|| format!("Unknown variable `{name}`"))?;
+            let id = shape
+                .strip_prefix("shape_")
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| format!("Unknown shape `{shape}`"))?;
+            let field = ShapeField::ALL
+                .into_iter()
+                .find(|f| f.name() == field)
+                .ok_or_else(|| format!("Unknown shape field `{field}`"))?;
+            Var::Shape(id, field)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str) -> Result<f32, String> {
+        let mut expression = Expression::new(source.to_owned());
+        let variables = Variables {
+            time: 2.0,
+            mouse: Vec2::new(3.0, -1.0),
+            shapes: HashMap::from([(1, HashMap::from([(ShapeField::PositionX, 5.0)]))]),
+            ..default()
+        };
+        expression
+            .evaluate(&variables)
+            .ok_or_else(|| expression.error().unwrap_or_default().to_owned())
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(evaluate("10 - 4 - 3"), Ok(3.0));
+        assert_eq!(evaluate("12 / 2 * 3"), Ok(18.0));
+        assert_eq!(evaluate("2 + 7 % 3"), Ok(3.0));
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(evaluate("-2 ^ 2"), Ok(-4.0));
+        assert_eq!(evaluate("2 ^ -1"), Ok(0.5));
+        assert_eq!(evaluate("--3"), Ok(3.0));
+        assert_eq!(evaluate("-7 % 3"), Ok(2.0));
+        assert_eq!(evaluate("1.5e2 + 1e-1"), Ok(150.1));
+    }
+
+    #[test]
+    fn variables_and_functions() {
+        assert_eq!(evaluate("t * mouse_x + mouse_y"), Ok(5.0));
+        assert_eq!(evaluate("shape_1.x * 2"), Ok(10.0));
+        assert_eq!(evaluate("max(1, min(4, 3)) + clamp(5, 0, 1)"), Ok(4.0));
+        assert_eq!(evaluate("mix(2, 4, 0.5)"), Ok(3.0));
+        assert_eq!(evaluate("sin(pi / 2)"), Ok(1.0));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source: &str| {
+            Expression::new(source.to_owned())
+                .error()
+                .map(str::to_owned)
+        };
+        assert_eq!(
+            error("1 +"),
+            Some("Unexpected end of expression".to_owned())
+        );
+        assert_eq!(
+            error("(1 + 2"),
+            Some("Unexpected end of expression".to_owned())
+        );
+        assert_eq!(error("1 2"), Some("Unexpected number 2".to_owned()));
+        assert_eq!(error("1 # 2"), Some("Unexpected character `#`".to_owned()));
+        assert_eq!(error("1..2"), Some("Invalid number `1..2`".to_owned()));
+        assert_eq!(error("foo(1)"), Some("Unknown function `foo`".to_owned()));
+        assert_eq!(error("foo"), Some("Unknown variable `foo`".to_owned()));
+        assert_eq!(
+            error("shape_a.x"),
+            Some("Unknown shape `shape_a`".to_owned())
+        );
+        assert_eq!(
+            error("shape_1.w"),
+            Some("Unknown shape field `w`".to_owned())
+        );
+        assert_eq!(
+            error("min(1)"),
+            Some("`min` expects 2 argument(s), found 1".to_owned())
+        );
+        assert_eq!(
+            error("max(1 2)"),
+            Some("Expected `,`, found number 2".to_owned())
+        );
+        assert_eq!(error("sqrt(t)"), None);
+    }
+
+    #[test]
+    fn evaluation_errors() {
+        assert_eq!(
+            evaluate("shape_2.x"),
+            Err("Unknown shape shape_2".to_owned())
+        );
+        assert_eq!(evaluate("1 / 0"), Err("The result is inf".to_owned()));
+        assert_eq!(evaluate("sqrt(-1)"), Err("The result is NaN".to_owned()));
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use bevy_egui::egui;
+
+use crate::consts;
+
+/// A file chosen by the user.
+pub struct LoadedFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A file that might not be loaded yet, because browsers only provide files asynchronously.
+#[derive(Clone, Default)]
+pub struct PendingFile(Arc<Mutex<Option<Result<LoadedFile, String>>>>);
+
+impl PendingFile {
+    fn set(&self, result: Result<LoadedFile, String>) {
+        if let Ok(mut file) = self.0.lock() {
+            *file = Some(result);
+        }
+    }
+
+    /// Returns the loaded file once it's available.
+    pub fn take(&self) -> Option<Result<LoadedFile, String>> {
+        self.0.lock().ok()?.take()
+    }
+}
+
+/// Shows a path input with a button for loading the file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_file_ui(ui: &mut egui::Ui, path: &mut String, _accept: &str) -> Option<PendingFile> {
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(path);
+        ui.button("Open").clicked().then(|| {
+            let pending = PendingFile::default();
+            let path = Path::new(path);
+            pending.set(
+                std::fs::read(path)
+                    .map(|bytes| LoadedFile {
+                        name: path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        bytes,
+                    })
+                    .map_err(|error| format!("{}: {error}", path.display())),
+            );
+            pending
+        })
+    })
+    .inner
+}
+
+/// Shows a button that lets the browser ask for a file, `accept` filters the file types.
+#[cfg(target_arch = "wasm32")]
+pub fn open_file_ui(ui: &mut egui::Ui, _path: &mut String, accept: &str) -> Option<PendingFile> {
+    ui.button("Choose file…").clicked().then(|| {
+        let pending = PendingFile::default();
+        if let Err(error) = pick_file(accept, &pending) {
+            pending.set(Err(error));
+        }
+        pending
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn pick_file(accept: &str, pending: &PendingFile) -> Result<(), String> {
+    use wasm_bindgen::{JsCast, closure::Closure};
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("There is no document")?;
+    let input: web_sys::HtmlInputElement = document
+        .create_element("input")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "Failed to create input element")?;
+    input.set_type("file");
+    input.set_accept(accept);
+
+    let on_change = {
+        let input = input.clone();
+        let pending = pending.clone();
+        Closure::once_into_js(move || {
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            let reader = match web_sys::FileReader::new() {
+                Ok(reader) => reader,
+                Err(error) => return pending.set(Err(js_error(error))),
+            };
+            let on_load = {
+                let reader = reader.clone();
+                let pending = pending.clone();
+                let name = file.name();
+                Closure::once_into_js(move || {
+                    pending.set(
+                        reader
+                            .result()
+                            .map(|buffer| LoadedFile {
+                                name,
+                                bytes: js_sys::Uint8Array::new(&buffer).to_vec(),
+                            })
+                            .map_err(js_error),
+                    );
+                })
+            };
+            reader.set_onload(Some(on_load.unchecked_ref()));
+            if let Err(error) = reader.read_as_array_buffer(&file) {
+                pending.set(Err(js_error(error)));
+            }
+        })
+    };
+    input.set_onchange(Some(on_change.unchecked_ref()));
+    input.click();
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(error: wasm_bindgen::JsValue) -> String {
+    format!("{error:?}")
+}
+
+/// Writes the file, creating missing directories.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent).map_err(|error| format!("{}: {error}", path.display()))?;
+    }
+    std::fs::write(path, bytes).map_err(|error| format!("{}: {error}", path.display()))
+}
+
+/// There is no file system, so we let the browser download the file instead.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    use wasm_bindgen::{JsCast, closure::Closure};
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or("The file name is empty")?;
+
+    let window = web_sys::window().ok_or("There is no window")?;
+    let document = window.document().ok_or("There is no document")?;
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "Failed to create anchor element")?;
+    anchor.set_href(&url);
+    anchor.set_download(&file_name);
+    anchor.click();
+
+    // Revoking the URL right away cancels the download in some browsers
+    let revoke = Closure::once_into_js(move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    });
+    window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            revoke.unchecked_ref(),
+            consts::REVOKE_DOWNLOAD_URL_DELAY_MS,
+        )
+        .map_err(js_error)?;
+    Ok(())
+}
+
+/// Returns the stored value of a preference, see [`store_preference`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_preference(key: &str) -> Option<String> {
+    let preferences = std::fs::read_to_string(consts::PREFERENCES_PATH).ok()?;
+    preferences.lines().find_map(|line| {
+        let (line_key, value) = line.split_once('=')?;
+        (line_key == key).then(|| value.to_owned())
+    })
+}
+
+/// Stores the value of a preference in a file in the working directory, with one `key=value`
+/// line per preference.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store_preference(key: &str, value: &str) -> Result<(), String> {
+    let preferences = std::fs::read_to_string(consts::PREFERENCES_PATH).unwrap_or_default();
+    let mut lines: Vec<_> = preferences
+        .lines()
+        .filter(|line| {
+            line.split_once('=')
+                .is_none_or(|(line_key, _)| line_key != key)
+        })
+        .map(str::to_owned)
+        .collect();
+    lines.push(format!("{key}={value}"));
+    save_file(
+        Path::new(consts::PREFERENCES_PATH),
+        (lines.join("\n") + "\n").as_bytes(),
+    )
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_preference(key: &str) -> Option<String> {
+    local_storage().ok()?.get_item(key).ok()?
+}
+
+/// Stores the value of a preference in the local storage of the browser.
+#[cfg(target_arch = "wasm32")]
+pub fn store_preference(key: &str, value: &str) -> Result<(), String> {
+    local_storage()?.set_item(key, value).map_err(js_error)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .ok_or("There is no window")?
+        .local_storage()
+        .map_err(js_error)?
+        .ok_or_else(|| "There is no local storage".to_owned())
+}
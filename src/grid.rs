@@ -0,0 +1,180 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{ShapeCamera, consts, state::GlobalState};
+
+/// Which reference lines are shown behind the UI.
+pub struct GridSettings {
+    pub grid: bool,
+    pub axes: bool,
+    pub rulers: bool,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        Self {
+            grid: true,
+            axes: true,
+            rulers: false,
+        }
+    }
+}
+
+/// Shows a grid whose spacing adapts to the zoom, the world axes and rulers along the edges of
+/// the area next to the side panel.
+pub fn grid_overlay(
+    mut contexts: EguiContexts,
+    global_state: Res<GlobalState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform, &Projection), With<ShapeCamera>>,
+) -> Result {
+    let settings = &global_state.grid;
+    if !settings.grid && !settings.axes && !settings.rulers {
+        return Ok(());
+    }
+    let (camera, camera_transform, projection) = *camera;
+    let Projection::Orthographic(projection) = projection else {
+        return Ok(());
+    };
+
+    let ctx = contexts.ctx_mut()?;
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("grid"),
+    ));
+    let visuals = ctx.style().visuals.clone();
+
+    let viewport = Rect::new(
+        global_state.side_panel_width,
+        0.0,
+        window.width(),
+        window.height(),
+    );
+    if viewport.is_empty() {
+        return Ok(());
+    }
+    let to_viewport = |world: Vec2| {
+        camera
+            .world_to_viewport(camera_transform, world.extend(0.0))
+            .ok()
+    };
+    let (Ok(a), Ok(b)) = (
+        camera.viewport_to_world_2d(camera_transform, viewport.min),
+        camera.viewport_to_world_2d(camera_transform, viewport.max),
+    ) else {
+        return Ok(());
+    };
+    let area = Rect::from_corners(a, b);
+
+    // Minor lines are a power of ten apart, every tenth line is a major line
+    let minor = grid_step(projection.scale);
+    let major = minor * 10.0;
+    let lines = |step: f32, min: f32, max: f32| {
+        let (first, last) = ((min / step).ceil() as i64, (max / step).floor() as i64);
+        (first..=last).map(move |index| (index, index as f32 * step))
+    };
+    let line_color = visuals.weak_text_color();
+
+    if settings.grid {
+        // Minor lines fade in while zooming in, so they don't pop up
+        let fade = (minor / projection.scale / consts::GRID_MIN_SPACING - 1.0) / 2.0;
+        for (step, alpha) in [(minor, 0.1 * fade.clamp(0.0, 1.0)), (major, 0.25)] {
+            let stroke = egui::Stroke::new(1.0, line_color.gamma_multiply(alpha));
+            for (_, x) in lines(step, area.min.x, area.max.x) {
+                if let Some(x) = to_viewport(Vec2::new(x, 0.0)).map(|p| p.x) {
+                    painter.vline(x, viewport.min.y..=viewport.max.y, stroke);
+                }
+            }
+            for (_, y) in lines(step, area.min.y, area.max.y) {
+                if let Some(y) = to_viewport(Vec2::new(0.0, y)).map(|p| p.y) {
+                    painter.hline(viewport.min.x..=viewport.max.x, y, stroke);
+                }
+            }
+        }
+    }
+
+    if settings.axes
+        && let Some(origin) = to_viewport(Vec2::ZERO)
+    {
+        let (x_color, y_color) = (consts::GRID_X_AXIS_COLOR, consts::GRID_Y_AXIS_COLOR);
+        if (viewport.min.y..=viewport.max.y).contains(&origin.y) {
+            painter.hline(
+                viewport.min.x..=viewport.max.x,
+                origin.y,
+                egui::Stroke::new(1.5, x_color),
+            );
+        }
+        if (viewport.min.x..=viewport.max.x).contains(&origin.x) {
+            painter.vline(
+                origin.x,
+                viewport.min.y..=viewport.max.y,
+                egui::Stroke::new(1.5, y_color),
+            );
+        }
+    }
+
+    if settings.rulers {
+        let size = consts::RULER_SIZE;
+        let background = visuals.extreme_bg_color.gamma_multiply(0.8);
+        let stroke = egui::Stroke::new(1.0, visuals.text_color());
+        let font = egui::FontId::monospace(9.0);
+        let decimals = (-major.log10()).ceil().max(0.0) as usize;
+
+        let top = egui::Rect::from_min_max(
+            egui::pos2(viewport.min.x, viewport.min.y),
+            egui::pos2(viewport.max.x, viewport.min.y + size),
+        );
+        let left = egui::Rect::from_min_max(
+            egui::pos2(viewport.min.x, viewport.min.y + size),
+            egui::pos2(viewport.min.x + size, viewport.max.y),
+        );
+        painter.rect_filled(top, 0.0, background);
+        painter.rect_filled(left, 0.0, background);
+
+        for (index, x) in lines(minor, area.min.x, area.max.x) {
+            let Some(position) = to_viewport(Vec2::new(x, 0.0)) else {
+                continue;
+            };
+            let is_major = index % 10 == 0;
+            let length = if is_major { size } else { size / 3.0 };
+            painter.vline(position.x, top.max.y - length..=top.max.y, stroke);
+            if is_major {
+                painter.text(
+                    egui::pos2(position.x + 2.0, top.min.y),
+                    egui::Align2::LEFT_TOP,
+                    format!("{x:.decimals$}"),
+                    font.clone(),
+                    visuals.text_color(),
+                );
+            }
+        }
+        for (index, y) in lines(minor, area.min.y, area.max.y) {
+            let Some(position) = to_viewport(Vec2::new(0.0, y)) else {
+                continue;
+            };
+            if position.y < left.min.y {
+                continue;
+            }
+            let is_major = index % 10 == 0;
+            let length = if is_major { size } else { size / 3.0 };
+            painter.hline(left.max.x - length..=left.max.x, position.y, stroke);
+            if is_major {
+                painter.text(
+                    egui::pos2(left.min.x + 1.0, position.y + 2.0),
+                    egui::Align2::LEFT_TOP,
+                    format!("{y:.decimals$}"),
+                    font.clone(),
+                    visuals.text_color(),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the smallest power of ten that is at least [`consts::GRID_MIN_SPACING`] logical
+/// pixels long, `scale` is the size of a logical pixel in world space.
+fn grid_step(scale: f32) -> f32 {
+    10.0_f32.powf((consts::GRID_MIN_SPACING * scale).log10().ceil())
+}
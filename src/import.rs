@@ -0,0 +1,480 @@
+use std::sync::Arc;
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
+use bevy_egui::egui::{self, Widget};
+use usvg::tiny_skia_path::{self, PathSegment};
+
+use crate::{
+    consts,
+    distance_grid::{DistanceGrid, Mask},
+    export::status_ui,
+    file::{PendingFile, open_file_ui},
+    outline::{CurveMode, FillRule, Outline, OutlineBuilder, Segment},
+    shape::{new_shape_state, spawn_shape},
+    state::GlobalState,
+    templates::Templates,
+};
+
+/// Converts files into shapes with generated SDF code.
+#[derive(Resource, Default)]
+pub struct Importer {
+    pub svg_window_open: bool,
+    pub svg: SvgImportSettings,
+    svg_file: Option<PendingFile>,
+    /// Name and content of the loaded SVG file.
+    svg_source: Option<(String, Vec<u8>)>,
+    /// The outline of the loaded SVG file with the current settings.
+    svg_outline: Option<Result<Outline, String>>,
+    status: Option<Result<String, String>>,
+    pub image_window_open: bool,
+    pub image: ImageImportSettings,
+    image_file: Option<PendingFile>,
+    /// Name and content of the loaded image file.
+    image_source: Option<(String, Arc<[u8]>)>,
+    /// The distance grid of the loaded image with the current settings.
+    image_grid: Option<Result<DistanceGrid, String>>,
+    /// Computes the distance grid in the background after the settings changed.
+    image_task: Option<Task<Result<DistanceGrid, String>>>,
+    image_status: Option<Result<String, String>>,
+}
+
+pub struct SvgImportSettings {
+    pub path: String,
+    pub curves: CurveMode,
+    /// Maximum deviation of approximated curves in world space.
+    pub tolerance: f32,
+    /// Length of the larger side of the shape in world space.
+    pub size: f32,
+}
+
+impl Default for SvgImportSettings {
+    fn default() -> Self {
+        Self {
+            path: consts::DEFAULT_SVG_IMPORT_PATH.to_owned(),
+            curves: CurveMode::Bezier,
+            tolerance: 0.25,
+            size: 300.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ImageImportSettings {
+    pub path: String,
+    pub channel: ImageChannel,
+    /// Pixels whose channel value exceeds the threshold are inside of the shape.
+    pub threshold: f32,
+    pub invert: bool,
+    /// Number of distance samples along the larger side of the image.
+    pub resolution: u32,
+    /// Length of the larger side of the shape in world space.
+    pub size: f32,
+}
+
+impl Default for ImageImportSettings {
+    fn default() -> Self {
+        Self {
+            path: consts::DEFAULT_IMAGE_IMPORT_PATH.to_owned(),
+            channel: ImageChannel::Alpha,
+            threshold: 0.5,
+            invert: false,
+            resolution: 64,
+            size: 300.0,
+        }
+    }
+}
+
+/// The channel of the image that is thresholded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageChannel {
+    Alpha,
+    Luminance,
+}
+
+pub fn import_windows(
+    ctx: &egui::Context,
+    importer: &mut Importer,
+    commands: &mut Commands,
+    templates: &Templates,
+    global_state: &mut GlobalState,
+    shaders: &mut Assets<Shader>,
+) {
+    svg_window(ctx, importer, commands, templates, global_state, shaders);
+    image_window(ctx, importer, commands, templates, global_state, shaders);
+}
+
+fn svg_window(
+    ctx: &egui::Context,
+    importer: &mut Importer,
+    commands: &mut Commands,
+    templates: &Templates,
+    global_state: &mut GlobalState,
+    shaders: &mut Assets<Shader>,
+) {
+    let mut changed = false;
+    if let Some(file) = importer.svg_file.as_ref().and_then(PendingFile::take) {
+        importer.svg_file = None;
+        match file {
+            Ok(file) => {
+                importer.svg_source = Some((file.name, file.bytes));
+                importer.status = None;
+                changed = true;
+            }
+            Err(error) => importer.status = Some(Err(error)),
+        }
+    }
+
+    let mut open = importer.svg_window_open;
+    egui::Window::new("Import SVG")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let settings = &mut importer.svg;
+
+            egui::Grid::new("grid_import_svg")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("File:");
+                    if let Some(file) = open_file_ui(ui, &mut settings.path, ".svg") {
+                        importer.svg_file = Some(file);
+                    }
+                    ui.end_row();
+
+                    ui.label("Curves:");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .selectable_value(&mut settings.curves, CurveMode::Bezier, "Bézier")
+                            .changed();
+                        changed |= ui
+                            .selectable_value(&mut settings.curves, CurveMode::Flatten, "Flatten")
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Tolerance:");
+                    changed |= egui::DragValue::new(&mut settings.tolerance)
+                        .range(0.01..=10.0)
+                        .speed(0.01)
+                        .ui(ui)
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Size:");
+                    changed |= egui::DragValue::new(&mut settings.size)
+                        .range(1.0..=f32::MAX)
+                        .ui(ui)
+                        .changed();
+                    ui.end_row();
+                });
+
+            if changed && let Some((_, bytes)) = &importer.svg_source {
+                importer.svg_outline = Some(svg_outline(bytes, settings));
+            }
+
+            ui.separator();
+
+            let outline = match (&importer.svg_source, &importer.svg_outline) {
+                (Some((name, _)), Some(Ok(outline))) => {
+                    outline_info_ui(ui, name, outline);
+                    Some((name, outline))
+                }
+                (_, Some(Err(error))) => {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                    None
+                }
+                _ => None,
+            };
+
+            let clicked = ui
+                .add_enabled(
+                    outline.is_some_and(|(_, outline)| !outline.is_empty()),
+                    egui::Button::new("Create shape"),
+                )
+                .clicked();
+            if clicked && let Some((name, outline)) = outline {
+                let mut shape_state = new_shape_state(templates, global_state);
+                shape_state.sdf_code = outline.sdf_code(
+                    consts::SVG_SDF_IMPORT_PATH,
+                    &format!("Imported from {name}"),
+                );
+                shape_state.bounds_length = settings.size * consts::IMPORTED_SHAPE_BOUNDS_FACTOR;
+                spawn_shape(commands, global_state, shaders, shape_state);
+                importer.status = Some(Ok(format!("Created shape from {name}")));
+            }
+
+            status_ui(ui, &importer.status);
+        });
+    importer.svg_window_open = open;
+}
+
+fn image_window(
+    ctx: &egui::Context,
+    importer: &mut Importer,
+    commands: &mut Commands,
+    templates: &Templates,
+    global_state: &mut GlobalState,
+    shaders: &mut Assets<Shader>,
+) {
+    let mut changed = false;
+    if let Some(file) = importer.image_file.as_ref().and_then(PendingFile::take) {
+        importer.image_file = None;
+        match file {
+            Ok(file) => {
+                importer.image_source = Some((file.name, file.bytes.into()));
+                importer.image_status = None;
+                changed = true;
+            }
+            Err(error) => importer.image_status = Some(Err(error)),
+        }
+    }
+
+    if let Some(task) = &mut importer.image_task
+        && let Some(grid) = block_on(future::poll_once(task))
+    {
+        importer.image_task = None;
+        importer.image_grid = Some(grid);
+    }
+
+    let mut open = importer.image_window_open;
+    egui::Window::new("Import image")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let settings = &mut importer.image;
+
+            egui::Grid::new("grid_import_image")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("File:");
+                    if let Some(file) = open_file_ui(ui, &mut settings.path, ".png") {
+                        importer.image_file = Some(file);
+                    }
+                    ui.end_row();
+
+                    ui.label("Channel:");
+                    ui.horizontal(|ui| {
+                        for channel in [ImageChannel::Alpha, ImageChannel::Luminance] {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut settings.channel,
+                                    channel,
+                                    format!("{channel:?}"),
+                                )
+                                .changed();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Threshold:");
+                    ui.horizontal(|ui| {
+                        changed |= egui::Slider::new(&mut settings.threshold, 0.0..=1.0)
+                            .ui(ui)
+                            .changed();
+                        changed |= ui.checkbox(&mut settings.invert, "Invert").changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Resolution:");
+                    changed |= egui::Slider::new(&mut settings.resolution, 4..=256)
+                        .ui(ui)
+                        .on_hover_text("Number of distance samples along the larger side")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Size:");
+                    changed |= egui::DragValue::new(&mut settings.size)
+                        .range(1.0..=f32::MAX)
+                        .ui(ui)
+                        .changed();
+                    ui.end_row();
+                });
+
+            // Replacing the task cancels the previous one, e.g. while dragging a slider
+            if changed && let Some((_, bytes)) = &importer.image_source {
+                let bytes = bytes.clone();
+                let settings = settings.clone();
+                importer.image_task = Some(
+                    AsyncComputeTaskPool::get()
+                        .spawn(async move { image_distance_grid(&bytes, &settings) }),
+                );
+            }
+
+            ui.separator();
+
+            let computing = importer.image_task.is_some();
+            let grid = match (&importer.image_source, &importer.image_grid) {
+                _ if computing => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Computing distances…");
+                    });
+                    None
+                }
+                (Some((name, _)), Some(Ok(grid))) => {
+                    ui.label(format!(
+                        "{name}: {} × {} distance samples",
+                        grid.size.x, grid.size.y
+                    ));
+                    if grid.distances.len() > consts::DISTANCE_GRID_SAMPLE_WARNING {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            "The samples are embedded into the shader and might make it slow \
+                             to compile. Consider a lower resolution.",
+                        );
+                    }
+                    Some((name, grid))
+                }
+                (_, Some(Err(error))) => {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                    None
+                }
+                _ => None,
+            };
+
+            let clicked = ui
+                .add_enabled(grid.is_some(), egui::Button::new("Create shape"))
+                .clicked();
+            if clicked && let Some((name, grid)) = grid {
+                let mut shape_state = new_shape_state(templates, global_state);
+                shape_state.sdf_code = grid.sdf_code(
+                    consts::IMAGE_SDF_IMPORT_PATH,
+                    &format!("Imported from {name}"),
+                );
+                shape_state.bounds_length = settings.size * consts::IMPORTED_SHAPE_BOUNDS_FACTOR;
+                spawn_shape(commands, global_state, shaders, shape_state);
+                importer.image_status = Some(Ok(format!("Created shape from {name}")));
+            }
+
+            status_ui(ui, &importer.image_status);
+        });
+    importer.image_window_open = open;
+}
+
+/// Shows the number of segments and warns if the shader will be slow.
+pub fn outline_info_ui(ui: &mut egui::Ui, name: &str, outline: &Outline) {
+    let curves = outline
+        .segments
+        .iter()
+        .filter(|segment| matches!(segment, Segment::Quadratic(..)))
+        .count();
+    ui.label(format!(
+        "{name}: {} lines and {curves} curves",
+        outline.segments.len() - curves,
+    ));
+    if outline.segments.len() > consts::OUTLINE_SEGMENT_WARNING {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "The SDF evaluates every segment for every pixel and might render slowly. \
+             Consider flattening with a larger tolerance or simplifying the file.",
+        );
+    }
+}
+
+fn svg_outline(bytes: &[u8], settings: &SvgImportSettings) -> Result<Outline, String> {
+    let tree = usvg::Tree::from_data(bytes, &usvg::Options::default())
+        .map_err(|error| error.to_string())?;
+
+    // The tolerance applies to the fitted outline
+    let bounds = tree.root().abs_bounding_box();
+    let scale = settings.size / bounds.width().max(bounds.height()).max(f32::EPSILON);
+    let mut builder = OutlineBuilder::new(settings.curves, settings.tolerance / scale);
+    add_svg_group(&mut builder, tree.root());
+
+    let mut outline = builder.build();
+    if outline.is_empty() {
+        return Err("The file contains no paths".to_owned());
+    }
+    // SVG's y axis points down
+    outline.transform(|point| Vec2::new(point.x, -point.y));
+    outline.fit(settings.size);
+    Ok(outline)
+}
+
+/// Adds every fill and stroke as a separate path, so overlapping paths don't cancel each other.
+fn add_svg_group(builder: &mut OutlineBuilder, group: &usvg::Group) {
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => add_svg_group(builder, group),
+            usvg::Node::Path(path) if path.is_visible() => {
+                let transform = path.abs_transform();
+                if let Some(fill) = path.fill() {
+                    builder.begin_path(match fill.rule() {
+                        usvg::FillRule::NonZero => FillRule::NonZero,
+                        usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+                    });
+                    add_svg_path(builder, path.data(), transform);
+                }
+                // Strokes are converted into filled paths, whose windings are nonzero
+                if let Some(stroke) = path.stroke()
+                    && let Some(data) = path.data().stroke(&stroke.to_tiny_skia(), 1.0)
+                {
+                    builder.begin_path(FillRule::NonZero);
+                    add_svg_path(builder, &data, transform);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn add_svg_path(
+    builder: &mut OutlineBuilder,
+    path: &tiny_skia_path::Path,
+    transform: tiny_skia_path::Transform,
+) {
+    let Some(path) = path.clone().transform(transform) else {
+        return;
+    };
+    let vec = |point: tiny_skia_path::Point| Vec2::new(point.x, point.y);
+    for segment in path.segments() {
+        match segment {
+            PathSegment::MoveTo(point) => builder.move_to(vec(point)),
+            PathSegment::LineTo(point) => builder.line_to(vec(point)),
+            PathSegment::QuadTo(control, point) => builder.quad_to(vec(control), vec(point)),
+            PathSegment::CubicTo(control1, control2, point) => {
+                builder.cubic_to(vec(control1), vec(control2), vec(point))
+            }
+            PathSegment::Close => builder.close(),
+        }
+    }
+}
+
+fn image_distance_grid(
+    bytes: &[u8],
+    settings: &ImageImportSettings,
+) -> Result<DistanceGrid, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|error| error.to_string())?
+        .into_luma_alpha8();
+    let threshold = (settings.threshold * 255.0).round() as u8;
+    let mask = Mask {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        inside: image
+            .pixels()
+            .map(|pixel| {
+                let [luminance, alpha] = pixel.0;
+                let value = match settings.channel {
+                    ImageChannel::Alpha => alpha,
+                    ImageChannel::Luminance => luminance,
+                };
+                (value > threshold) != settings.invert
+            })
+            .collect(),
+    };
+    if mask.is_empty() {
+        return Err("No pixels are inside of the shape with this threshold".to_owned());
+    }
+    Ok(DistanceGrid::from_mask(
+        &mask,
+        settings.resolution,
+        settings.size,
+    ))
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_smud::SmudShape;
+
+use crate::{
+    consts,
+    shape::{ShapeTransforms, local_transform},
+    state::{GlobalState, SelectedTab, ShapeId, ShapeOrder, ShapeState},
+};
+
+/// Shows the shapes sorted by draw order with the front shape on top, shapes can be dragged onto
+/// another row to take its place.
+pub fn layers(
+    ui: &mut egui::Ui,
+    global_state: &mut GlobalState,
+    shape_query: &mut Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let transforms =
+        ShapeTransforms::new(shape_query.iter().map(|(_, _, _, shape_state)| shape_state));
+    let order = transforms.draw_order();
+    let rows: HashMap<_, _> = shape_query
+        .iter()
+        .map(|(_, _, _, shape_state)| (shape_state.id, (shape_state.label(), shape_state.visible)))
+        .collect();
+    let mut new_order = None;
+
+    ui.horizontal(|ui| {
+        ui.label("Outliner order:");
+        ui.selectable_value(&mut global_state.shape_order, ShapeOrder::Id, "Id");
+        ui.selectable_value(&mut global_state.shape_order, ShapeOrder::Layer, "Layer");
+    });
+
+    ui.horizontal(|ui| {
+        ui.add_enabled_ui(!global_state.selection().is_empty(), |ui| {
+            let (selected, others): (Vec<_>, Vec<_>) = order
+                .iter()
+                .copied()
+                .partition(|&id| global_state.is_selected(id));
+            if ui.button("Bring to front").clicked() {
+                new_order = Some([others.clone(), selected.clone()].concat());
+            }
+            if ui.button("Send to back").clicked() {
+                new_order = Some([selected, others].concat());
+            }
+        });
+    });
+
+    egui::ScrollArea::vertical()
+        .id_salt("scroll_layers")
+        .max_height(consts::LAYERS_MAX_HEIGHT)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            for &id in order.iter().rev() {
+                let Some((name, visible)) = rows.get(&id) else {
+                    continue;
+                };
+                let mut text = egui::RichText::new(name);
+                if !visible {
+                    text = text.weak();
+                }
+                let label = ui.dnd_drag_source(egui::Id::new(("layers", id)), id, |ui| {
+                    ui.selectable_label(global_state.is_selected(id), text)
+                        .on_hover_text("Drag onto another shape to move it to its layer")
+                });
+                if label.inner.clicked() {
+                    if ui.input(|i| i.modifiers.shift || i.modifiers.command) {
+                        global_state.toggle_selection(id);
+                    } else {
+                        global_state.select_tab(SelectedTab::Shape(id));
+                    }
+                }
+                if label.response.dnd_hover_payload::<ShapeId>().is_some() {
+                    ui.painter().rect_stroke(
+                        label.response.rect,
+                        2.0,
+                        ui.visuals().selection.stroke,
+                        egui::StrokeKind::Outside,
+                    );
+                }
+                if let Some(dragged) = label.response.dnd_release_payload::<ShapeId>() {
+                    new_order = Some(move_layer(&order, *dragged, id));
+                }
+            }
+        });
+
+    if let Some(new_order) = new_order
+        && new_order != order
+    {
+        restack(&new_order, &transforms, shape_query);
+    }
+}
+
+/// Moves the shape to the position of `target` in the draw order, the shapes in between move one
+/// layer towards the old position.
+fn move_layer(order: &[ShapeId], shape: ShapeId, target: ShapeId) -> Vec<ShapeId> {
+    let Some(index) = order.iter().position(|&id| id == target) else {
+        return order.to_vec();
+    };
+    let mut order: Vec<_> = order.iter().copied().filter(|&id| id != shape).collect();
+    order.insert(index.min(order.len()), shape);
+    order
+}
+
+/// Rewrites the z values of the shapes, so they are drawn in the given order from back to front
+/// with evenly spaced depths.
+fn restack(
+    order: &[ShapeId],
+    transforms: &ShapeTransforms,
+    shape_query: &mut Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let depths: HashMap<_, _> = order
+        .iter()
+        .enumerate()
+        .map(|(layer, &id)| (id, layer as f32 * consts::LAYER_Z_STEP))
+        .collect();
+
+    for (_, mut transform, _, mut shape_state) in shape_query.iter_mut() {
+        let Some(&depth) = depths.get(&shape_state.id) else {
+            continue;
+        };
+        // Shapes are only rotated around the z axis, so the depth of a child only depends on the
+        // depth and scale of its parent
+        let parent_world = transforms.parent_world(shape_state.id);
+        let parent_depth = shape_state
+            .parent
+            .and_then(|parent| depths.get(&parent).copied())
+            .unwrap_or(parent_world.translation().z);
+        let parent_scale = parent_world.scale().z;
+        if parent_scale == 0.0 {
+            continue;
+        }
+        let z = (depth - parent_depth) / parent_scale;
+        if z != shape_state.position.z {
+            shape_state.position.z = z;
+            *transform = local_transform(&shape_state);
+        }
+    }
+}
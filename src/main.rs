@@ -1,24 +1,53 @@
+mod background;
+mod camera;
+mod clock;
 mod consts;
+mod cpu_shader;
+mod cursor;
+mod distance_grid;
+mod distance_view;
+mod draw;
+mod export;
+mod expr;
+mod file;
+mod grid;
+mod import;
+mod layers;
+mod outline;
+mod outliner;
+mod performance;
+mod picking;
+mod post_process;
+mod probe;
+mod quality;
+mod selection;
 mod shader;
 mod shape;
+mod snap;
 mod state;
+mod stress_test;
 mod templates;
+mod text;
+mod trace;
 mod util;
 
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeSet, HashMap},
     f32::consts::TAU,
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use bevy::{
-    picking::hover::PickingInteraction,
+    camera::visibility::RenderLayers,
+    core_pipeline::tonemapping::Tonemapping,
+    ecs::system::SystemParam,
     prelude::*,
     render::view::screenshot::{Screenshot, save_to_disk},
+    transform::TransformSystems,
     window::default_event_handling::KeyEventException,
 };
 use bevy_egui::{
-    EguiContexts, EguiPlugin, EguiPrimaryContextPass,
+    EguiContexts, EguiGlobalSettings, EguiPlugin, EguiPrimaryContextPass,
     egui::{self, Widget, special_emojis},
     input::EguiWantsInput,
 };
@@ -27,10 +56,37 @@ use bevy_smud::prelude::*;
 use git_version::*;
 
 use crate::{
-    shape::{add_shape, clone_shape, update_shape},
-    state::{GlobalState, SelectedTab, ShaderKind, ShapeState},
+    background::{
+        ReferenceImage, background_ui, spawn_background, update_background, update_reference_image,
+    },
+    camera::{CameraTool, apply_camera_requests, camera_ui},
+    clock::{Clock, ClockPlugin, advance_clock},
+    cpu_shader::CpuShaders,
+    cursor::{Cursor, update_cursor},
+    distance_view::{distance_view_ui, update_distance_view},
+    draw::{DrawTool, draw_tool_input, draw_tool_overlay, drawing_ui},
+    export::{Exporter, drive_export, export_svg, export_windows},
+    expr::{Expression, Variables},
+    file::{load_preference, store_preference},
+    grid::grid_overlay,
+    import::{Importer, import_windows},
+    layers::layers,
+    outliner::{delete_selected, outliner, sync_hierarchy, sync_visibility},
+    performance::{Performance, PerformancePlugin},
+    picking::ShapePickingPlugin,
+    post_process::{apply_post_process, post_process_ui},
+    probe::{ProbeTool, probe_overlay, probe_ui, update_probe},
+    quality::{apply_render_quality, quality_ui, spawn_display_camera},
+    selection::{SelectionTool, group_settings, move_input, selection_input, selection_overlay},
+    shader::add_globals_shader,
+    shape::{ShapeTransforms, add_shape, clone_shape, update_shape},
+    snap::{SnapSteps, snapped_drag_value, snapping_ui},
+    state::{
+        GlobalState, InputScheme, PickingMode, SelectedTab, ShaderKind, ShapeField, ShapeState,
+    },
     templates::Templates,
-    util::convert_color,
+    text::{TextEditor, text_ui},
+    util::{color_edit_button, convert_color},
 };
 
 fn main() {
@@ -55,15 +111,70 @@ fn main() {
             }),
         )
         .add_plugins(SmudPlugin)
-        .add_plugins(SmudPickingPlugin)
+        .add_plugins(ShapePickingPlugin)
         .add_plugins(EguiPlugin::default())
+        // The UI belongs to the display camera, see `spawn_display_camera`
+        .insert_resource(EguiGlobalSettings {
+            auto_create_primary_context: false,
+            ..default()
+        })
         .add_plugins(PanCamPlugin)
+        .add_plugins(ClockPlugin)
+        .add_plugins(PerformancePlugin)
         .insert_resource(Templates::default())
         .insert_resource(GlobalState::default())
+        .insert_resource(Cursor::default())
+        .insert_resource(Exporter::default())
+        .insert_resource(Importer::default())
+        .insert_resource(DrawTool::default())
+        .insert_resource(SelectionTool::default())
+        .insert_resource(CameraTool::default())
+        .insert_resource(ProbeTool::default())
+        .insert_resource(ReferenceImage::default())
+        .insert_resource(TextEditor::default())
+        .insert_resource(CpuShaders::default())
         .add_systems(Startup, setup)
-        .add_systems(Update, update)
-        .add_systems(Update, screenshot)
-        .add_systems(EguiPrimaryContextPass, gui)
+        .add_systems(
+            Update,
+            (
+                update,
+                apply_camera_requests,
+                (selection_input, move_input).chain(),
+                draw_tool_input,
+                sync_hierarchy,
+                sync_visibility,
+                update_distance_view,
+                update_probe,
+                apply_post_process,
+                apply_render_quality,
+                update_reference_image,
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            update_background.before(TransformSystems::Propagate),
+        )
+        .add_systems(
+            Update,
+            (
+                update_cursor,
+                drive_export,
+                advance_clock,
+                evaluate_expressions,
+            )
+                .chain(),
+        )
+        .add_systems(Update, (screenshot, export_svg))
+        .add_systems(
+            EguiPrimaryContextPass,
+            (
+                gui,
+                grid_overlay,
+                draw_tool_overlay,
+                selection_overlay,
+                probe_overlay,
+            ),
+        )
         .run();
 }
 
@@ -77,32 +188,48 @@ fn setup(
     mut clear_color: ResMut<ClearColor>,
     mut shaders: ResMut<Assets<Shader>>,
 ) {
+    // Initialize shaders that can be imported by shapes
+    add_globals_shader(&mut shaders);
+
     // Initialize background
     clear_color.0 = convert_color(global_state.background_color);
 
+    // Initialize preferences
+    if let Some(input_scheme) = load_preference(consts::INPUT_SCHEME_PREFERENCE)
+        .and_then(|name| InputScheme::from_name(&name))
+    {
+        global_state.input_scheme = input_scheme;
+    }
+
     // Initialize camera
     commands.spawn((
         ShapeCamera,
         Camera2d,
         PanCam {
-            grab_buttons: vec![MouseButton::Left],
+            grab_buttons: global_state.input_scheme.pan_buttons(false),
             ..default()
         },
         Msaa::Off,
+        Tonemapping::None,
         Transform::from_translation(consts::DEFAULT_CAMERA_POSITION.extend(0.0)),
+        RenderLayers::from_layers(&[0, consts::BACKGROUND_RENDER_LAYER]),
     ));
+    spawn_display_camera(&mut commands);
+
+    // Initialize background shape and reference image
+    spawn_background(&mut commands, &mut global_state, &mut shaders);
 
     // Initialize shape
     add_shape(&mut commands, &templates, &mut global_state, &mut shaders);
 }
 
 fn update(
-    mut global_state: ResMut<GlobalState>,
+    global_state: Res<GlobalState>,
     mut clear_color: ResMut<ClearColor>,
     egui_wants_input: Res<EguiWantsInput>,
-    input: Res<ButtonInput<MouseButton>>,
+    draw_tool: Res<DrawTool>,
+    keys: Res<ButtonInput<KeyCode>>,
     mut pan_cam: Single<&mut PanCam>,
-    picking_query: Query<(&ShapeState, &PickingInteraction), Changed<PickingInteraction>>,
 ) {
     // Update background
     clear_color.0 = convert_color(global_state.background_color);
@@ -110,14 +237,75 @@ fn update(
     // Deactivate panning and zooming if mouse is over UI
     pan_cam.enabled = !egui_wants_input.is_pointer_over_area();
 
-    // Pick shape
-    if input.just_pressed(MouseButton::Right) {
-        for (shape_state, &interaction) in picking_query {
-            if interaction == PickingInteraction::Pressed {
-                global_state.select_tab(SelectedTab::Shape(shape_state.id));
-                break;
-            }
+    // The left button is used for drawing, so panning moves to the middle button
+    let grab_buttons = if draw_tool.shape.is_some() {
+        vec![MouseButton::Middle]
+    } else {
+        global_state
+            .input_scheme
+            .pan_buttons(keys.pressed(KeyCode::Space))
+    };
+    if pan_cam.grab_buttons != grab_buttons {
+        pan_cam.grab_buttons = grab_buttons;
+    }
+}
+
+fn evaluate_expressions(
+    mut global_state: ResMut<GlobalState>,
+    mut shaders: ResMut<Assets<Shader>>,
+    clock: Res<Clock>,
+    cursor: Res<Cursor>,
+    mut shape_query: Query<(&mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    if shape_query
+        .iter()
+        .all(|(_, _, shape_state)| shape_state.expressions.is_empty())
+    {
+        return;
+    }
+
+    // Other shapes are referenced with their values from before this evaluation
+    let variables = Variables {
+        time: clock.elapsed,
+        delta_time: clock.delta,
+        frame: clock.frame,
+        mouse: cursor.last_world_position,
+        shapes: shape_query
+            .iter()
+            .map(|(_, _, shape_state)| {
+                let fields = ShapeField::ALL
+                    .into_iter()
+                    .map(|field| (field, field.get(shape_state)))
+                    .collect();
+                (shape_state.id, fields)
+            })
+            .collect(),
+    };
+
+    for (mut transform, mut shape, mut shape_state) in &mut shape_query {
+        if shape_state.expressions.is_empty() {
+            continue;
+        }
+
+        let values: Vec<_> = shape_state
+            .expressions
+            .iter_mut()
+            .filter_map(|(&field, expression)| {
+                expression.evaluate(&variables).map(|value| (field, value))
+            })
+            .collect();
+        for (field, value) in values {
+            field.set(&mut shape_state, value);
         }
+
+        update_shape(
+            &mut global_state,
+            &mut shaders,
+            &mut transform,
+            &mut shape,
+            &shape_state,
+            false,
+        );
     }
 }
 
@@ -134,18 +322,46 @@ fn screenshot(mut commands: Commands, input: Res<ButtonInput<KeyCode>>) {
     }
 }
 
+/// The resources of the tools and windows that are changed in the GUI.
+#[derive(SystemParam)]
+struct Tools<'w> {
+    exporter: ResMut<'w, Exporter>,
+    importer: ResMut<'w, Importer>,
+    draw_tool: ResMut<'w, DrawTool>,
+    selection_tool: ResMut<'w, SelectionTool>,
+    camera_tool: ResMut<'w, CameraTool>,
+    probe_tool: ResMut<'w, ProbeTool>,
+    reference_image: ResMut<'w, ReferenceImage>,
+    performance: ResMut<'w, Performance>,
+    cursor: Res<'w, Cursor>,
+    text_editor: ResMut<'w, TextEditor>,
+}
+
 fn gui(
     mut commands: Commands,
     mut contexts: EguiContexts,
     templates: Res<Templates>,
     mut global_state: ResMut<GlobalState>,
+    tools: Tools,
     mut shaders: ResMut<Assets<Shader>>,
     mut shape_query: Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
 ) -> Result {
+    let Tools {
+        mut exporter,
+        mut importer,
+        mut draw_tool,
+        mut selection_tool,
+        mut camera_tool,
+        mut probe_tool,
+        mut reference_image,
+        mut performance,
+        cursor,
+        mut text_editor,
+    } = tools;
     let ctx = contexts.ctx_mut()?;
 
     // Build UI
-    egui::SidePanel::left("side_panel")
+    let side_panel = egui::SidePanel::left("side_panel")
         .default_width(consts::SIDE_PANEL_WIDTH)
         .show(ctx, |ui| {
             // UI with info about this demo
@@ -160,15 +376,54 @@ fn gui(
                 &templates,
                 &mut global_state,
                 &mut shaders,
-                &shape_query,
+                &mut shape_query,
             );
 
+            // UI for selecting shapes and changing their hierarchy
+            outliner(ui, &mut commands, &mut global_state, &mut shape_query);
+
+            // UI for changing the draw order of shapes
+            egui::CollapsingHeader::new("Layers").show(ui, |ui| {
+                layers(ui, &mut global_state, &mut shape_query);
+            });
+
+            // UI for moving the camera
+            egui::CollapsingHeader::new("Camera").show(ui, |ui| {
+                camera_ui(ui, &mut camera_tool, &global_state, &shape_query);
+            });
+
+            // UI for inspecting shader outputs under the cursor
+            egui::CollapsingHeader::new("Probe").show(ui, |ui| {
+                probe_ui(ui, &mut probe_tool);
+            });
+
             ui.separator();
 
-            match global_state.selected_tab {
+            if global_state.selection().len() > 1 {
+                // UI for changing all selected shapes
+                group_settings(
+                    ui,
+                    &mut global_state,
+                    &mut selection_tool,
+                    &mut shaders,
+                    &mut shape_query,
+                );
+
+                ui.separator();
+            }
+
+            match global_state.selected_tab() {
                 SelectedTab::Global => {
                     // UI for changing global settings
-                    global_settings(ui, &mut global_state);
+                    global_settings(
+                        ui,
+                        &mut global_state,
+                        &mut reference_image,
+                        &cursor,
+                        &mut exporter,
+                        &mut importer,
+                        &mut performance,
+                    );
                 }
                 SelectedTab::Shape(id) => {
                     // UI for changing the selected shape
@@ -177,12 +432,21 @@ fn gui(
                         .find(|(_, _, _, shape_state)| shape_state.id == id)
                     {
                         // UI for changing non-shader shape settings
-                        shape_settings(ui, &mut shape_state);
+                        let snap_steps =
+                            global_state.snapping.steps(ctx.input(|i| i.modifiers.alt));
+                        let shader_changed = shape_settings(
+                            ui,
+                            &mut shape_state,
+                            snap_steps,
+                            &mut draw_tool,
+                            &mut text_editor,
+                        );
 
                         ui.separator();
 
                         // UI for editing the shader shape
-                        let compile_shader = shader_editor(ui, &templates, &mut shape_state);
+                        let compile_shader =
+                            shader_editor(ui, &templates, &mut shape_state) || shader_changed;
 
                         // Apply changes
                         update_shape(
@@ -197,6 +461,21 @@ fn gui(
                 }
             };
         });
+    global_state.side_panel_width = side_panel.response.rect.right();
+
+    // UI for exporting
+    let selected_shape = matches!(global_state.selected_tab(), SelectedTab::Shape(_));
+    export_windows(ctx, &mut exporter, selected_shape);
+
+    // UI for importing
+    import_windows(
+        ctx,
+        &mut importer,
+        &mut commands,
+        &templates,
+        &mut global_state,
+        &mut shaders,
+    );
 
     Ok(())
 }
@@ -222,14 +501,15 @@ fn tab_bar(
     templates: &Templates,
     global_state: &mut GlobalState,
     shaders: &mut Assets<Shader>,
-    shape_query: &Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+    shape_query: &mut Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
 ) {
     ui.horizontal(|ui| {
-        ui.selectable_value(
-            &mut global_state.selected_tab,
-            SelectedTab::Global,
-            "Global",
-        );
+        if ui
+            .selectable_label(global_state.selected_tab() == SelectedTab::Global, "Global")
+            .clicked()
+        {
+            global_state.select_tab(SelectedTab::Global);
+        }
 
         ui.separator();
 
@@ -242,98 +522,260 @@ fn tab_bar(
             .map(|(_, _, _, shape_state)| shape_state.id)
             .collect();
 
-        let selected_shape = match global_state.selected_tab {
+        let selected_shape = match global_state.selected_tab() {
             SelectedTab::Shape(id) => Some(id),
             _ => None,
         };
 
         ui.add_enabled_ui(selected_shape.is_some(), |ui| {
-            if ui.button("Copy").clicked()
-                && let Some(id) = selected_shape
-                && let Some((transform, shape, shape_state)) =
-                    shape_query
-                        .iter()
-                        .find_map(|(_, transform, shape, shape_state)| {
-                            (shape_state.id == id).then_some((transform, shape, shape_state))
-                        })
-            {
-                clone_shape(commands, global_state, transform, shape, shape_state);
+            if ui.button("Copy").clicked() {
+                let transforms = ShapeTransforms::new(
+                    shape_query.iter().map(|(_, _, _, shape_state)| shape_state),
+                );
+                // Parents are copied before their children, so the copies are attached to each
+                // other
+                let mut selected: Vec<_> = shape_query
+                    .iter()
+                    .filter(|(_, _, _, shape_state)| global_state.is_selected(shape_state.id))
+                    .collect();
+                selected.sort_by_key(|(_, _, _, shape_state)| {
+                    transforms.ancestors(shape_state.id).count()
+                });
+                let entities: HashMap<_, _> = shape_query
+                    .iter()
+                    .map(|(entity, _, _, shape_state)| (shape_state.id, entity))
+                    .collect();
+                let mut copies = HashMap::new();
+                for (_, transform, shape, shape_state) in selected {
+                    let parent = shape_state.parent.and_then(|parent| {
+                        copies
+                            .get(&parent)
+                            .copied()
+                            .or_else(|| Some((parent, *entities.get(&parent)?)))
+                    });
+                    let copy = clone_shape(
+                        commands,
+                        global_state,
+                        transform,
+                        shape,
+                        shape_state,
+                        parent,
+                    );
+                    copies.insert(shape_state.id, copy);
+                }
+                // The copy of the shown shape is shown afterwards
+                if let Some(&(shown, _)) = selected_shape.and_then(|id| copies.get(&id)) {
+                    global_state.select_tab(SelectedTab::Shape(shown));
+                    global_state.extend_selection(copies.into_values().map(|(id, _)| id));
+                }
             }
 
             if ui.button("Delete").clicked()
                 && let Some(id) = selected_shape
-                && let Some(entity) = shape_query.iter().find_map(|(entity, _, _, shape_state)| {
-                    (shape_state.id == id).then_some(entity)
-                })
             {
                 let neighbor_id = shapes
                     .range(0..id)
-                    .next_back()
-                    .copied()
-                    .or_else(|| shapes.range(id + 1..).next().copied());
+                    .rev()
+                    .chain(shapes.range(id + 1..))
+                    .find(|&&neighbor| !global_state.is_selected(neighbor))
+                    .copied();
+                delete_selected(commands, global_state, shape_query);
                 global_state
                     .select_tab(neighbor_id.map_or(SelectedTab::Global, SelectedTab::Shape));
-                commands.entity(entity).despawn();
             }
         });
-
-        egui::ScrollArea::horizontal()
-            .id_salt("scroll_tab")
-            .show(ui, |ui| {
-                for id in shapes {
-                    let selector = ui.selectable_value(
-                        &mut global_state.selected_tab,
-                        SelectedTab::Shape(id),
-                        format!("shape_{id}"),
-                    );
-                    if global_state.check_scroll_to(id) {
-                        selector.scroll_to_me(None);
-                    }
-                }
-            });
     });
 }
 
-fn global_settings(ui: &mut egui::Ui, global_state: &mut GlobalState) {
+fn global_settings(
+    ui: &mut egui::Ui,
+    global_state: &mut GlobalState,
+    reference_image: &mut ReferenceImage,
+    cursor: &Cursor,
+    exporter: &mut Exporter,
+    importer: &mut Importer,
+    performance: &mut Performance,
+) {
     egui::Grid::new("grid_global")
         .num_columns(2)
         .spacing([40.0, 4.0])
         .striped(true)
         .show(ui, |ui| {
-            ui.label("Background color:");
+            background_ui(ui, global_state, reference_image);
+
+            ui.label("Overlay:");
             ui.horizontal(|ui| {
-                if ui.button("⟲").clicked() {
-                    global_state.background_color = consts::DEFAULT_BACKGROUND_COLOR;
-                };
-                ui.color_edit_button_srgba(&mut global_state.background_color);
+                ui.checkbox(&mut global_state.grid.grid, "Grid");
+                ui.checkbox(&mut global_state.grid.axes, "Axes");
+                ui.checkbox(&mut global_state.grid.rulers, "Rulers");
             });
             ui.end_row();
+
+            ui.label("Cursor:");
+            match cursor.world_position {
+                Some(position) => ui.label(format!("x: {:.1}, y: {:.1}", position.x, position.y)),
+                None => ui.weak("outside of the window"),
+            };
+            ui.end_row();
+
+            snapping_ui(ui, &mut global_state.snapping);
+
+            distance_view_ui(ui, &mut global_state.distance_view);
+
+            post_process_ui(ui, &mut global_state.post_process);
+
+            quality_ui(ui, &mut global_state.quality);
+
+            ui.label("Viewport input:");
+            let input_scheme = global_state.input_scheme;
+            egui::ComboBox::from_id_salt("input_scheme")
+                .selected_text(input_scheme.to_string())
+                .show_ui(ui, |ui| {
+                    for input_scheme in InputScheme::ALL {
+                        ui.selectable_value(
+                            &mut global_state.input_scheme,
+                            input_scheme,
+                            input_scheme.to_string(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "With left click selecting, the middle button or space with the left button \
+                     pans the view",
+                );
+            if global_state.input_scheme != input_scheme
+                && let Err(error) = store_preference(
+                    consts::INPUT_SCHEME_PREFERENCE,
+                    global_state.input_scheme.name(),
+                )
+            {
+                warn!("Failed to store input scheme: {error}");
+            }
+            ui.end_row();
+
+            ui.label("Picking:");
+            ui.horizontal(|ui| {
+                ui.selectable_value(
+                    &mut global_state.picking_mode,
+                    PickingMode::Bounds,
+                    "Bounds",
+                )
+                .on_hover_text("Shapes are picked anywhere within their bounds");
+                ui.selectable_value(&mut global_state.picking_mode, PickingMode::Sdf, "Precise")
+                    .on_hover_text("Shapes are picked within the threshold of their area");
+                ui.add_enabled(
+                    global_state.picking_mode == PickingMode::Sdf,
+                    egui::DragValue::new(&mut global_state.picking_threshold)
+                        .range(0.0..=f32::MAX)
+                        .speed(0.1)
+                        .suffix(" px"),
+                )
+                .on_hover_text("Maximum distance outside of the shape");
+            });
+            ui.end_row();
+
+            ui.label("Import:");
+            ui.horizontal(|ui| {
+                if ui.button("SVG…").clicked() {
+                    importer.svg_window_open = true;
+                }
+                if ui.button("Image…").clicked() {
+                    importer.image_window_open = true;
+                }
+            });
+            ui.end_row();
+
+            ui.label("Export:");
+            ui.horizontal(|ui| {
+                if ui.button("Image…").clicked() {
+                    exporter.image_window_open = true;
+                }
+                if ui.button("Animation…").clicked() {
+                    exporter.animation_window_open = true;
+                }
+                if ui.button("SVG…").clicked() {
+                    exporter.svg_window_open = true;
+                }
+            });
+            ui.end_row();
+
+            ui.label("Diagnostics:");
+            if ui.button("Performance…").clicked() {
+                performance.window_open = true;
+            }
+            ui.end_row();
         });
 }
 
-fn shape_settings(ui: &mut egui::Ui, shape_state: &mut ShapeState) {
+/// Returns whether the SDF code was generated from the text of the shape or the fill was switched
+/// to the distance view, so the shaders need to be compiled.
+fn shape_settings(
+    ui: &mut egui::Ui,
+    shape_state: &mut ShapeState,
+    snap_steps: SnapSteps,
+    draw_tool: &mut DrawTool,
+    text_editor: &mut TextEditor,
+) -> bool {
+    let mut sdf_changed = false;
+    let mut fill_changed = false;
     egui::Grid::new("grid_shape")
         .num_columns(2)
         .spacing([40.0, 4.0])
         .striped(true)
         .show(ui, |ui| {
+            ui.label("Name:");
+            ui.add(
+                egui::TextEdit::singleline(&mut shape_state.name)
+                    .hint_text(format!("shape_{}", shape_state.id)),
+            );
+            ui.end_row();
+
+            ui.label("Visibility:");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut shape_state.visible, "Visible");
+                ui.checkbox(&mut shape_state.locked, "Locked")
+                    .on_hover_text("Locked shapes can't be picked or edited in the viewport");
+            });
+            ui.end_row();
+
             ui.label("Position:");
             ui.horizontal(|ui| {
                 if ui.button("⟲").clicked() {
                     shape_state.position = consts::DEFAULT_SHAPE_POSITION;
+                    remove_expressions(
+                        shape_state,
+                        &[
+                            ShapeField::PositionX,
+                            ShapeField::PositionY,
+                            ShapeField::PositionZ,
+                        ],
+                    );
                 };
                 ui.label("x");
-                egui::DragValue::new(&mut shape_state.position.x)
-                    .speed(5.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::PositionX, |ui, shape_state| {
+                    snapped_drag_value(
+                        ui,
+                        &mut shape_state.position.x,
+                        snap_steps.position,
+                        |value| egui::DragValue::new(value).speed(5.0),
+                    );
+                });
                 ui.label("y");
-                egui::DragValue::new(&mut shape_state.position.y)
-                    .speed(5.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::PositionY, |ui, shape_state| {
+                    snapped_drag_value(
+                        ui,
+                        &mut shape_state.position.y,
+                        snap_steps.position,
+                        |value| egui::DragValue::new(value).speed(5.0),
+                    );
+                });
                 ui.label("z");
-                egui::DragValue::new(&mut shape_state.position.z)
-                    .speed(1.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::PositionZ, |ui, shape_state| {
+                    egui::DragValue::new(&mut shape_state.position.z)
+                        .speed(1.0)
+                        .ui(ui);
+                });
             });
             ui.end_row();
 
@@ -341,12 +783,20 @@ fn shape_settings(ui: &mut egui::Ui, shape_state: &mut ShapeState) {
             ui.horizontal(|ui| {
                 if ui.button("⟲").clicked() {
                     shape_state.rotation = consts::DEFAULT_SHAPE_ROTATION;
+                    remove_expressions(shape_state, &[ShapeField::Rotation]);
                 };
-                ui.add(
-                    egui::DragValue::new(&mut shape_state.rotation)
-                        .min_decimals(2)
-                        .speed(TAU / 50.0),
-                );
+                expression_field(ui, shape_state, ShapeField::Rotation, |ui, shape_state| {
+                    snapped_drag_value(
+                        ui,
+                        &mut shape_state.rotation,
+                        snap_steps.rotation,
+                        |value| {
+                            egui::DragValue::new(value)
+                                .min_decimals(2)
+                                .speed(TAU / 50.0)
+                        },
+                    );
+                });
             });
             ui.end_row();
 
@@ -354,12 +804,13 @@ fn shape_settings(ui: &mut egui::Ui, shape_state: &mut ShapeState) {
             ui.horizontal(|ui| {
                 if ui.button("⟲").clicked() {
                     shape_state.scale = consts::DEFAULT_SHAPE_SCALE;
+                    remove_expressions(shape_state, &[ShapeField::Scale]);
                 };
-                ui.add(
-                    egui::DragValue::new(&mut shape_state.scale)
-                        .min_decimals(1)
-                        .speed(1.0 / 5.0),
-                );
+                expression_field(ui, shape_state, ShapeField::Scale, |ui, shape_state| {
+                    snapped_drag_value(ui, &mut shape_state.scale, snap_steps.scale, |value| {
+                        egui::DragValue::new(value).min_decimals(1).speed(1.0 / 5.0)
+                    });
+                });
             });
             ui.end_row();
 
@@ -367,8 +818,26 @@ fn shape_settings(ui: &mut egui::Ui, shape_state: &mut ShapeState) {
             ui.horizontal(|ui| {
                 if ui.button("⟲").clicked() {
                     shape_state.color = consts::DEFAULT_SHAPE_COLOR;
+                    remove_expressions(
+                        shape_state,
+                        &[
+                            ShapeField::ColorR,
+                            ShapeField::ColorG,
+                            ShapeField::ColorB,
+                            ShapeField::ColorA,
+                        ],
+                    );
                 };
-                ui.color_edit_button_srgba(&mut shape_state.color);
+                color_edit_button(ui, &mut shape_state.color);
+                for (label, field) in [
+                    ("r", ShapeField::ColorR),
+                    ("g", ShapeField::ColorG),
+                    ("b", ShapeField::ColorB),
+                    ("a", ShapeField::ColorA),
+                ] {
+                    ui.label(label);
+                    expression_field(ui, shape_state, field, |_, _| {});
+                }
             });
             ui.end_row();
 
@@ -385,23 +854,40 @@ fn shape_settings(ui: &mut egui::Ui, shape_state: &mut ShapeState) {
             ui.horizontal(|ui| {
                 if ui.button("⟲").clicked() {
                     shape_state.params = consts::DEFAULT_SHAPE_PARAMS;
+                    remove_expressions(
+                        shape_state,
+                        &[
+                            ShapeField::ParamsX,
+                            ShapeField::ParamsY,
+                            ShapeField::ParamsZ,
+                            ShapeField::ParamsW,
+                        ],
+                    );
                 };
                 ui.label("x");
-                egui::DragValue::new(&mut shape_state.params.x)
-                    .speed(1.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::ParamsX, |ui, shape_state| {
+                    egui::DragValue::new(&mut shape_state.params.x)
+                        .speed(1.0)
+                        .ui(ui);
+                });
                 ui.label("y");
-                egui::DragValue::new(&mut shape_state.params.y)
-                    .speed(1.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::ParamsY, |ui, shape_state| {
+                    egui::DragValue::new(&mut shape_state.params.y)
+                        .speed(1.0)
+                        .ui(ui);
+                });
                 ui.label("z");
-                egui::DragValue::new(&mut shape_state.params.z)
-                    .speed(1.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::ParamsZ, |ui, shape_state| {
+                    egui::DragValue::new(&mut shape_state.params.z)
+                        .speed(1.0)
+                        .ui(ui);
+                });
                 ui.label("w");
-                egui::DragValue::new(&mut shape_state.params.w)
-                    .speed(1.0)
-                    .ui(ui);
+                expression_field(ui, shape_state, ShapeField::ParamsW, |ui, shape_state| {
+                    egui::DragValue::new(&mut shape_state.params.w)
+                        .speed(1.0)
+                        .ui(ui);
+                });
             });
             ui.end_row();
 
@@ -423,7 +909,78 @@ fn shape_settings(ui: &mut egui::Ui, shape_state: &mut ShapeState) {
                     });
             });
             ui.end_row();
+
+            ui.label("Distance view:");
+            if ui
+                .checkbox(&mut shape_state.show_distance, "Show distance")
+                .on_hover_text("Replace the fill with contour lines of the raw distance")
+                .changed()
+            {
+                fill_changed = true;
+            }
+            ui.end_row();
+
+            ui.label("Outline:");
+            drawing_ui(ui, shape_state, draw_tool);
+            ui.end_row();
+
+            ui.label("Text:");
+            sdf_changed = text_ui(ui, shape_state, text_editor);
+            if shape_state.text.is_some() && draw_tool.is_drawing(shape_state.id) {
+                // The text replaces the drawing
+                *draw_tool = DrawTool::default();
+            }
+            ui.end_row();
         });
+    sdf_changed || fill_changed
+}
+
+/// Shows a toggle for driving `field` by an expression, followed by either the expression editor
+/// or the regular UI for the value.
+fn expression_field(
+    ui: &mut egui::Ui,
+    shape_state: &mut ShapeState,
+    field: ShapeField,
+    value_ui: impl FnOnce(&mut egui::Ui, &mut ShapeState),
+) {
+    let mut enabled = shape_state.expressions.contains_key(&field);
+    let toggle = ui
+        .toggle_value(&mut enabled, "fx")
+        .on_hover_text("Drive this value by an expression");
+    if toggle.changed() {
+        if enabled {
+            let source = field.get(shape_state).to_string();
+            shape_state
+                .expressions
+                .insert(field, Expression::new(source));
+        } else {
+            shape_state.expressions.remove(&field);
+        }
+    }
+
+    match shape_state.expressions.get_mut(&field) {
+        Some(expression) => {
+            let editor = egui::TextEdit::singleline(&mut expression.source)
+                .id_salt(("expression", field))
+                .font(egui::TextStyle::Monospace)
+                .desired_width(80.0)
+                .ui(ui)
+                .on_hover_text(consts::EXPRESSION_HELP);
+            if editor.changed() {
+                expression.reparse();
+            }
+            if let Some(error) = expression.error() {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        }
+        None => value_ui(ui, shape_state),
+    }
+}
+
+fn remove_expressions(shape_state: &mut ShapeState, fields: &[ShapeField]) {
+    shape_state
+        .expressions
+        .retain(|field, _| !fields.contains(field));
 }
 
 fn shader_editor(ui: &mut egui::Ui, templates: &Templates, shape_state: &mut ShapeState) -> bool {
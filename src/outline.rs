@@ -0,0 +1,623 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+
+/// A closed outline made of line and quadratic Bézier segments that can be turned into SDF code.
+///
+/// The segments are grouped into paths, each path is filled with its own fill rule and the
+/// filled paths are united.
+#[derive(Clone, Default)]
+pub struct Outline {
+    pub segments: Vec<Segment>,
+    /// Index of the first segment of each path and its fill rule, in the order of the segments.
+    pub paths: Vec<(usize, FillRule)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Segment {
+    Line(Vec2, Vec2),
+    Quadratic(Vec2, Vec2, Vec2),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+}
+
+/// How curves are added to an [`Outline`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveMode {
+    /// Quadratic Béziers are kept, cubic Béziers are approximated by quadratic ones.
+    Bezier,
+    /// All curves are approximated by lines.
+    Flatten,
+}
+
+impl Segment {
+    fn transform(self, f: impl Fn(Vec2) -> Vec2) -> Self {
+        match self {
+            Segment::Line(a, b) => Segment::Line(f(a), f(b)),
+            Segment::Quadratic(a, b, c) => Segment::Quadratic(f(a), f(b), f(c)),
+        }
+    }
+}
+
+impl Outline {
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.segments
+            .iter()
+            .map(|segment| match *segment {
+                Segment::Line(a, b) => Rect::from_corners(a, b),
+                Segment::Quadratic(a, b, c) => {
+                    // Include the extrema where the derivative of an axis is zero
+                    let t = ((a - b) / (a - 2.0 * b + c)).clamp(Vec2::ZERO, Vec2::ONE);
+                    let point = |t: f32| a.lerp(b, t).lerp(b.lerp(c, t), t);
+                    let extrema = Vec2::new(point(t.x).x, point(t.y).y);
+                    Rect::from_corners(a, c).union_point(if extrema.is_finite() {
+                        extrema
+                    } else {
+                        a
+                    })
+                }
+            })
+            .fold(Rect::EMPTY, |bounds, rect| bounds.union(rect))
+    }
+
+    pub fn transform(&mut self, f: impl Fn(Vec2) -> Vec2) {
+        for segment in &mut self.segments {
+            *segment = segment.transform(&f);
+        }
+    }
+
+    /// Centers the outline at the origin and scales it so its larger side has the given size.
+    pub fn fit(&mut self, size: f32) {
+        let bounds = self.bounds();
+        if bounds.is_empty() {
+            return;
+        }
+        let scale = size / bounds.size().max_element().max(f32::EPSILON);
+        let center = bounds.center();
+        self.transform(|point| (point - center) * scale);
+    }
+
+    /// Returns the segments of each non-empty path with its fill rule.
+    fn path_segments(&self) -> impl Iterator<Item = (&[Segment], FillRule)> {
+        let mut paths = self.paths.clone();
+        if paths.first().is_none_or(|&(start, _)| start > 0) {
+            paths.insert(0, (0, FillRule::default()));
+        }
+        let ends: Vec<_> = paths
+            .iter()
+            .skip(1)
+            .map(|&(start, _)| start)
+            .chain([self.segments.len()])
+            .collect();
+        paths
+            .into_iter()
+            .zip(ends)
+            .map(|((start, fill_rule), end)| (&self.segments[start..end], fill_rule))
+            .filter(|(segments, _)| !segments.is_empty())
+    }
+
+    /// Returns the WGSL code of an SDF with exact distances to the segments. The sign of each path
+    /// is determined by its winding number and the paths are united by the minimum of their
+    /// signed distances.
+    pub fn sdf_code(&self, import_path: &str, comment: &str) -> String {
+        let mut lines = Vec::new();
+        let mut quadratics = Vec::new();
+        let mut paths = Vec::new();
+        for (segments, fill_rule) in self.path_segments() {
+            let (line_start, quadratic_start) = (lines.len(), quadratics.len());
+            for segment in segments {
+                match *segment {
+                    Segment::Line(a, b) => lines.push((a, b)),
+                    Segment::Quadratic(a, b, c) => quadratics.push((a, b, c)),
+                }
+            }
+            paths.push((
+                [line_start, lines.len(), quadratic_start, quadratics.len()],
+                fill_rule,
+            ));
+        }
+
+        let mut code = format!("#define_import_path {import_path}\n\n#import smud\n\n");
+        for line in comment.lines() {
+            let _ = writeln!(code, "// {line}");
+        }
+        code.push('\n');
+        if paths.is_empty() {
+            code.push_str("fn sdf(input: smud::SdfInput) -> f32 {\n    return 1e20;\n}\n");
+            return code;
+        }
+
+        if !lines.is_empty() {
+            let _ = writeln!(code, "const LINES = array<vec4<f32>, {}>(", lines.len());
+            for (a, b) in &lines {
+                let _ = writeln!(
+                    code,
+                    "    vec4({:?}, {:?}, {:?}, {:?}),",
+                    a.x, a.y, b.x, b.y
+                );
+            }
+            code.push_str(");\n\n");
+        }
+        if !quadratics.is_empty() {
+            let _ = writeln!(
+                code,
+                "const QUADRATICS = array<vec2<f32>, {}>(",
+                3 * quadratics.len()
+            );
+            for (a, b, c) in &quadratics {
+                let _ = writeln!(
+                    code,
+                    "    vec2({:?}, {:?}), vec2({:?}, {:?}), vec2({:?}, {:?}),",
+                    a.x, a.y, b.x, b.y, c.x, c.y
+                );
+            }
+            code.push_str(");\n\n");
+        }
+
+        code.push_str(
+            "// Ranges of the lines and curves of each path and whether it's filled even-odd\n",
+        );
+        let n = paths.len();
+        let _ = writeln!(code, "const PATHS = array<vec4<u32>, {n}>(");
+        for ([a, b, c, d], _) in &paths {
+            let _ = writeln!(code, "    vec4({a}u, {b}u, {c}u, {d}u),");
+        }
+        code.push_str(");\n\n");
+        let _ = writeln!(code, "const EVEN_ODD = array<bool, {n}>(");
+        for (_, fill_rule) in &paths {
+            let _ = writeln!(code, "    {},", *fill_rule == FillRule::EvenOdd);
+        }
+        code.push_str(");\n\n");
+
+        let _ = write!(
+            code,
+            "fn sdf(input: smud::SdfInput) -> f32 {{\n    \
+                 let p = input.pos;\n    \
+                 var result = 1e20;\n    \
+                 for (var i = 0; i < {n}; i++) {{\n        \
+                     let range = PATHS[i];\n        \
+                     var distance = 1e20;\n        \
+                     var winding = 0;\n"
+        );
+        if !lines.is_empty() {
+            code.push_str(
+                "        for (var j = range.x; j < range.y; j++) {\n            \
+                             let a = LINES[j].xy;\n            \
+                             let b = LINES[j].zw;\n            \
+                             distance = min(distance, line_distance(p, a, b));\n            \
+                             winding += line_winding(p, a, b);\n        \
+                         }\n",
+            );
+        }
+        if !quadratics.is_empty() {
+            code.push_str(
+                "        for (var j = range.z; j < range.w; j++) {\n            \
+                             let a = QUADRATICS[3u * j];\n            \
+                             let b = QUADRATICS[3u * j + 1u];\n            \
+                             let c = QUADRATICS[3u * j + 2u];\n            \
+                             distance = min(distance, quadratic_distance(p, a, b, c));\n            \
+                             winding += line_winding(p, a, c) + quadratic_region_winding(p, a, b, c);\n        \
+                         }\n",
+            );
+        }
+        code.push_str(
+            "        let inside = select(winding != 0, winding % 2 != 0, EVEN_ODD[i]);\n        \
+                     result = min(result, select(distance, -distance, inside));\n    \
+                 }\n    \
+                 return result;\n\
+             }\n",
+        );
+
+        code.push_str(LINE_FUNCTIONS);
+        if !quadratics.is_empty() {
+            code.push_str(QUADRATIC_FUNCTIONS);
+        }
+        code
+    }
+}
+
+/// WGSL functions for the distance and winding number of line segments.
+pub const LINE_FUNCTIONS: &str = "
+fn line_distance(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+fn cross2(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+// Contribution to the winding number of a ray from p in +x direction
+fn line_winding(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> i32 {
+    if a.y <= p.y {
+        if b.y > p.y && cross2(b - a, p - a) > 0.0 {
+            return 1;
+        }
+    } else if b.y <= p.y && cross2(b - a, p - a) < 0.0 {
+        return -1;
+    }
+    return 0;
+}
+";
+
+/// WGSL functions for the distance and winding number of quadratic Bézier segments, requires
+/// [`LINE_FUNCTIONS`].
+pub const QUADRATIC_FUNCTIONS: &str = "
+// Source: https://iquilezles.org/articles/distfunctions2d/
+fn quadratic_distance(pos: vec2<f32>, A: vec2<f32>, B: vec2<f32>, C: vec2<f32>) -> f32 {
+    let a = B - A;
+    let b = A - 2.0 * B + C;
+    if dot(b, b) < 1e-8 {
+        // The curve is a straight line
+        return line_distance(pos, A, C);
+    }
+    let c = a * 2.0;
+    let d = A - pos;
+    let kk = 1.0 / dot(b, b);
+    let kx = kk * dot(a, b);
+    let ky = kk * (2.0 * dot(a, a) + dot(d, b)) / 3.0;
+    let kz = kk * dot(d, a);
+    let p = ky - kx * kx;
+    let q = kx * (2.0 * kx * kx - 3.0 * ky) + kz;
+    let h = q * q + 4.0 * p * p * p;
+    if h >= 0.0 {
+        let x = (vec2(sqrt(h), -sqrt(h)) - q) / 2.0;
+        let uv = sign(x) * pow(abs(x), vec2(1.0 / 3.0));
+        let t = clamp(uv.x + uv.y - kx, 0.0, 1.0);
+        return length(d + (c + b * t) * t);
+    }
+    let z = sqrt(-p);
+    let v = acos(q / (p * z * 2.0)) / 3.0;
+    let m = cos(v);
+    let n = sin(v) * 1.732050808;
+    let t = clamp(vec2(m + m, -n - m) * z - kx, vec2(0.0), vec2(1.0));
+    return min(length(d + (c + b * t.x) * t.x), length(d + (c + b * t.y) * t.y));
+}
+
+// The winding of a curve differs from the winding of its chord only in the region between them,
+// which is tested with the implicit form of the curve (Loop-Blinn)
+fn quadratic_region_winding(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>, c: vec2<f32>) -> i32 {
+    let det = cross2(b - a, c - a);
+    if det == 0.0 {
+        return 0;
+    }
+    let s = cross2(p - a, c - a) / det;
+    let t = cross2(b - a, p - a) / det;
+    let u = s / 2.0 + t;
+    if s < 0.0 || t < 0.0 || s + t > 1.0 || u * u >= t {
+        return 0;
+    }
+    return select(-1, 1, det > 0.0);
+}
+";
+
+/// Builds an [`Outline`] from path commands.
+pub struct OutlineBuilder {
+    outline: Outline,
+    curves: CurveMode,
+    /// Maximum deviation of approximated curves.
+    tolerance: f32,
+    start: Vec2,
+    current: Vec2,
+}
+
+impl OutlineBuilder {
+    pub fn new(curves: CurveMode, tolerance: f32) -> Self {
+        Self {
+            outline: Outline::default(),
+            curves,
+            tolerance: tolerance.max(1e-4),
+            start: Vec2::ZERO,
+            current: Vec2::ZERO,
+        }
+    }
+
+    pub fn move_to(&mut self, point: Vec2) {
+        self.close();
+        self.start = point;
+        self.current = point;
+    }
+
+    pub fn line_to(&mut self, point: Vec2) {
+        if point != self.current {
+            self.outline
+                .segments
+                .push(Segment::Line(self.current, point));
+        }
+        self.current = point;
+    }
+
+    pub fn quad_to(&mut self, control: Vec2, point: Vec2) {
+        let a = self.current;
+        // Nearly straight curves are degenerate for the distance function
+        let curvature = (a - 2.0 * control + point).length();
+        if curvature < self.tolerance {
+            self.line_to(point);
+            return;
+        }
+
+        match self.curves {
+            CurveMode::Bezier => {
+                self.outline
+                    .segments
+                    .push(Segment::Quadratic(a, control, point));
+                self.current = point;
+            }
+            CurveMode::Flatten => {
+                // The deviation from the chord is a quarter of the curvature
+                let count = (curvature / (4.0 * self.tolerance)).sqrt().ceil() as usize;
+                for i in 1..=count {
+                    let t = i as f32 / count as f32;
+                    let p = a.lerp(control, t).lerp(control.lerp(point, t), t);
+                    self.line_to(p);
+                }
+            }
+        }
+    }
+
+    pub fn cubic_to(&mut self, control1: Vec2, control2: Vec2, point: Vec2) {
+        let p = [self.current, control1, control2, point];
+        // Error of approximating the cubic with a single quadratic
+        let error = 3f32.sqrt() / 36.0 * (p[3] - 3.0 * p[2] + 3.0 * p[1] - p[0]).length();
+        let count = ((error / self.tolerance).cbrt().ceil() as usize).max(1);
+        for i in 0..count {
+            let t0 = i as f32 / count as f32;
+            let t1 = (i + 1) as f32 / count as f32;
+            let [q0, q1, q2, q3] = [
+                blossom(p, t0, t0, t0),
+                blossom(p, t0, t0, t1),
+                blossom(p, t0, t1, t1),
+                blossom(p, t1, t1, t1),
+            ];
+            self.quad_to((3.0 * (q1 + q2) - q0 - q3) / 4.0, q3);
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.line_to(self.start);
+    }
+
+    /// Closes the current path and starts a new one that is filled separately.
+    pub fn begin_path(&mut self, fill_rule: FillRule) {
+        self.close();
+        let start = self.outline.segments.len();
+        // A path without segments is replaced
+        if self
+            .outline
+            .paths
+            .last()
+            .is_some_and(|&(last, _)| last == start)
+        {
+            self.outline.paths.pop();
+        }
+        self.outline.paths.push((start, fill_rule));
+    }
+
+    pub fn build(mut self) -> Outline {
+        self.close();
+        self.outline
+    }
+}
+
+/// Evaluates the blossom of a cubic Bézier, which yields the control points of sub-curves.
+fn blossom(p: [Vec2; 4], t0: f32, t1: f32, t2: f32) -> Vec2 {
+    let a = [
+        p[0].lerp(p[1], t0),
+        p[1].lerp(p[2], t0),
+        p[2].lerp(p[3], t0),
+    ];
+    let b = [a[0].lerp(a[1], t1), a[1].lerp(a[2], t1)];
+    b[0].lerp(b[1], t2)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::Assets;
+
+    use super::*;
+    use crate::cpu_shader::{CpuShaders, GlobalInputs, ShapeInputs};
+
+    /// Distance from the point to the polyline through the points.
+    fn polyline_distance(points: &[Vec2], point: Vec2) -> f32 {
+        points
+            .windows(2)
+            .map(|line| {
+                let (pa, ba) = (point - line[0], line[1] - line[0]);
+                let h = (pa.dot(ba) / ba.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                (pa - ba * h).length()
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Samples the segments of the outline densely, in their order.
+    fn outline_points(outline: &Outline) -> Vec<Vec2> {
+        outline
+            .segments
+            .iter()
+            .flat_map(|segment| {
+                (0..=100).map(move |i| {
+                    let t = i as f32 / 100.0;
+                    match *segment {
+                        Segment::Line(a, b) => a.lerp(b, t),
+                        Segment::Quadratic(a, b, c) => a.lerp(b, t).lerp(b.lerp(c, t), t),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the largest distance from points on the curve to the outline.
+    fn max_deviation(outline: &Outline, curve: impl Fn(f32) -> Vec2) -> f32 {
+        let points = outline_points(outline);
+        (0..=1000)
+            .map(|i| polyline_distance(&points, curve(i as f32 / 1000.0)))
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn cubics_are_approximated_within_the_tolerance() {
+        let p = [
+            Vec2::ZERO,
+            Vec2::new(100.0, 150.0),
+            Vec2::new(-50.0, 150.0),
+            Vec2::new(100.0, 0.0),
+        ];
+        let cubic = |t: f32| blossom(p, t, t, t);
+        let mut segment_counts = Vec::new();
+        for tolerance in [1.0, 0.1] {
+            let mut builder = OutlineBuilder::new(CurveMode::Bezier, tolerance);
+            builder.move_to(p[0]);
+            builder.cubic_to(p[1], p[2], p[3]);
+            let outline = builder.build();
+
+            // The last segment closes the outline
+            let curves = &outline.segments[..outline.segments.len() - 1];
+            assert!(
+                curves
+                    .iter()
+                    .all(|segment| matches!(segment, Segment::Quadratic(..)))
+            );
+            let deviation = max_deviation(&outline, cubic);
+            assert!(
+                deviation <= tolerance,
+                "{deviation} with tolerance {tolerance}"
+            );
+            segment_counts.push(curves.len());
+        }
+        assert!(segment_counts[0] < segment_counts[1], "{segment_counts:?}");
+    }
+
+    #[test]
+    fn flattened_curves_stay_within_the_tolerance() {
+        let (a, b, c) = (Vec2::ZERO, Vec2::new(50.0, 200.0), Vec2::new(100.0, 0.0));
+        let quadratic = |t: f32| a.lerp(b, t).lerp(b.lerp(c, t), t);
+        for tolerance in [2.0, 0.25] {
+            let mut builder = OutlineBuilder::new(CurveMode::Flatten, tolerance);
+            builder.move_to(a);
+            builder.quad_to(b, c);
+            let outline = builder.build();
+
+            assert!(
+                outline
+                    .segments
+                    .iter()
+                    .all(|segment| matches!(segment, Segment::Line(..)))
+            );
+            let deviation = max_deviation(&outline, quadratic);
+            assert!(
+                deviation <= tolerance,
+                "{deviation} with tolerance {tolerance}"
+            );
+        }
+    }
+
+    #[test]
+    fn nearly_straight_curves_become_lines() {
+        let mut builder = OutlineBuilder::new(CurveMode::Bezier, 0.5);
+        builder.move_to(Vec2::ZERO);
+        builder.quad_to(Vec2::new(50.0, 0.1), Vec2::new(100.0, 0.0));
+        let outline = builder.build();
+        assert!(matches!(outline.segments[0], Segment::Line(..)));
+    }
+
+    /// The parts of the `smud` module of bevy_smud that the generated code uses.
+    const SMUD: &str = "
+        #define_import_path smud
+
+        struct SdfInput {
+            pos: vec2<f32>,
+            params: vec4<f32>,
+            color: vec4<f32>,
+        }
+    ";
+
+    fn add_square(builder: &mut OutlineBuilder, center: Vec2, radius: f32) {
+        builder.move_to(center - radius);
+        builder.line_to(center + Vec2::new(radius, -radius));
+        builder.line_to(center + radius);
+        builder.line_to(center + Vec2::new(-radius, radius));
+    }
+
+    /// Evaluates the generated SDF code of the outline at the positions.
+    fn distances(outline: &Outline, positions: &[Vec2]) -> Vec<f32> {
+        let code = outline.sdf_code("test", "Test outline");
+        let mut shaders = Assets::<Shader>::default();
+        shaders.add(Shader::from_wgsl(SMUD, "smud.wgsl"));
+        let shader = shaders.add(Shader::from_wgsl(code, "test.wgsl"));
+        let sdf = CpuShaders::default().get(&shaders, &shader, "sdf").unwrap();
+        let inputs = ShapeInputs {
+            params: Vec4::ZERO,
+            color: Vec4::ONE,
+            pixel_size: 1.0,
+            globals: GlobalInputs::default(),
+        };
+        positions
+            .iter()
+            .map(|pos| sdf.sdf(*pos, &inputs).unwrap())
+            .collect()
+    }
+
+    fn assert_distances(distances: &[f32], expected: &[f32]) {
+        for (distance, expected) in distances.iter().zip(expected) {
+            assert!((distance - expected).abs() < 1e-3, "{distances:?}");
+        }
+    }
+
+    #[test]
+    fn sdf_code_of_lines_and_curves() {
+        let mut builder = OutlineBuilder::new(CurveMode::Bezier, 0.1);
+        add_square(&mut builder, Vec2::ZERO, 10.0);
+        // A curve from (20, 0) to (40, 0) that bulges up to y = 10
+        builder.move_to(Vec2::new(40.0, 0.0));
+        builder.line_to(Vec2::new(20.0, 0.0));
+        builder.quad_to(Vec2::new(30.0, 20.0), Vec2::new(40.0, 0.0));
+        let outline = builder.build();
+
+        let distances = distances(
+            &outline,
+            &[
+                Vec2::ZERO,
+                Vec2::new(15.0, 0.0),
+                Vec2::new(30.0, 7.0),
+                Vec2::new(30.0, 15.0),
+                Vec2::new(30.0, -4.0),
+            ],
+        );
+        assert_distances(&distances, &[-10.0, 5.0, -3.0, 5.0, 4.0]);
+    }
+
+    #[test]
+    fn sdf_code_fills_paths_separately() {
+        let mut builder = OutlineBuilder::new(CurveMode::Bezier, 0.1);
+        builder.begin_path(FillRule::NonZero);
+        add_square(&mut builder, Vec2::ZERO, 10.0);
+        // Both squares wind the same way, so even-odd leaves a hole
+        builder.begin_path(FillRule::EvenOdd);
+        add_square(&mut builder, Vec2::new(5.0, 0.0), 10.0);
+        add_square(&mut builder, Vec2::new(5.0, 0.0), 4.0);
+        let outline = builder.build();
+
+        let distances = distances(
+            &outline,
+            &[
+                Vec2::ZERO,
+                Vec2::new(5.0, 0.0),
+                Vec2::new(12.0, 0.0),
+                Vec2::new(30.0, 0.0),
+            ],
+        );
+        // The hole of the second path doesn't cut into the first one
+        assert_distances(&distances, &[-10.0, -5.0, -3.0, 15.0]);
+    }
+}
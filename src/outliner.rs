@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::{picking::Pickable, prelude::*};
+use bevy_egui::egui;
+use bevy_smud::SmudShape;
+
+use crate::{
+    consts,
+    shape::{ShapeTransforms, local_transform, set_local_transform},
+    state::{GlobalState, SelectedTab, ShapeId, ShapeOrder, ShapeState},
+};
+
+/// Attaches shapes to the entities of their parents, so their transforms are relative to them.
+pub fn sync_hierarchy(
+    mut commands: Commands,
+    mut shape_query: Query<(Entity, &mut ShapeState, Option<&ChildOf>)>,
+) {
+    let entities: HashMap<_, _> = shape_query
+        .iter()
+        .map(|(entity, shape_state, _)| (shape_state.id, entity))
+        .collect();
+    for (entity, mut shape_state, child_of) in &mut shape_query {
+        let parent = shape_state.parent.and_then(|id| entities.get(&id).copied());
+        if parent.is_none() && shape_state.parent.is_some() {
+            // The parent doesn't exist anymore
+            shape_state.parent = None;
+        }
+        if parent != child_of.map(ChildOf::parent) {
+            set_child_of(&mut commands, entity, parent);
+        }
+    }
+}
+
+/// Hides invisible shapes and excludes them from picking, just like locked shapes.
+pub fn sync_visibility(
+    mut commands: Commands,
+    mut shape_query: Query<
+        (
+            Entity,
+            &ShapeState,
+            Option<&mut Visibility>,
+            Option<&mut Pickable>,
+        ),
+        Changed<ShapeState>,
+    >,
+) {
+    for (entity, shape_state, old_visibility, old_pickable) in &mut shape_query {
+        let visibility = if shape_state.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        // Shapes below are still picked, so clicking repeatedly can cycle through them
+        let pickable = if shape_state.visible && !shape_state.locked {
+            Pickable {
+                should_block_lower: false,
+                is_hoverable: true,
+            }
+        } else {
+            Pickable::IGNORE
+        };
+        set_or_insert(&mut commands, entity, old_visibility, visibility);
+        set_or_insert(&mut commands, entity, old_pickable, pickable);
+    }
+}
+
+/// Changes the component only if the value is different, so change detection isn't triggered for
+/// every change of the shape state.
+fn set_or_insert<C: Component + PartialEq>(
+    commands: &mut Commands,
+    entity: Entity,
+    component: Option<Mut<C>>,
+    value: C,
+) {
+    match component {
+        Some(mut component) => {
+            component.set_if_neq(value);
+        }
+        None => {
+            commands.entity(entity).insert(value);
+        }
+    }
+}
+
+fn set_child_of(commands: &mut Commands, entity: Entity, parent: Option<Entity>) {
+    match parent {
+        Some(parent) => commands.entity(entity).insert(ChildOf(parent)),
+        None => commands.entity(entity).remove::<ChildOf>(),
+    };
+}
+
+/// Attaches the shape to a new parent while keeping its placement in world space.
+fn set_parent(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: &mut Transform,
+    shape_state: &mut ShapeState,
+    parent: Option<(ShapeId, Entity)>,
+    transforms: &ShapeTransforms,
+) {
+    let world = transforms.world(shape_state.id);
+    let parent_world = parent.map_or(GlobalTransform::IDENTITY, |(id, _)| transforms.world(id));
+    set_local_transform(shape_state, world.reparented_to(&parent_world));
+    shape_state.parent = parent.map(|(id, _)| id);
+    *transform = local_transform(shape_state);
+    set_child_of(commands, entity, parent.map(|(_, entity)| entity));
+}
+
+/// Despawns the selected shapes, their remaining children are attached to the closest remaining
+/// ancestor.
+pub fn delete_selected(
+    commands: &mut Commands,
+    global_state: &GlobalState,
+    shape_query: &mut Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let transforms =
+        ShapeTransforms::new(shape_query.iter().map(|(_, _, _, shape_state)| shape_state));
+    let entities: HashMap<_, _> = shape_query
+        .iter()
+        .map(|(entity, _, _, shape_state)| (shape_state.id, entity))
+        .collect();
+
+    // Children have to be detached first, otherwise they are despawned with their parent
+    for (entity, mut transform, _, mut shape_state) in shape_query.iter_mut() {
+        if !global_state.is_selected(shape_state.id)
+            && shape_state
+                .parent
+                .is_some_and(|parent| global_state.is_selected(parent))
+        {
+            let parent = transforms
+                .ancestors(shape_state.id)
+                .find(|&id| !global_state.is_selected(id))
+                .and_then(|id| Some((id, *entities.get(&id)?)));
+            set_parent(
+                commands,
+                entity,
+                &mut transform,
+                &mut shape_state,
+                parent,
+                &transforms,
+            );
+        }
+    }
+    for (entity, _, _, shape_state) in shape_query.iter() {
+        if global_state.is_selected(shape_state.id) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Shows the shapes as a tree, shapes can be dragged onto others to make them their parent.
+pub fn outliner(
+    ui: &mut egui::Ui,
+    commands: &mut Commands,
+    global_state: &mut GlobalState,
+    shape_query: &mut Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let transforms =
+        ShapeTransforms::new(shape_query.iter().map(|(_, _, _, shape_state)| shape_state));
+    let entities: HashMap<_, _> = shape_query
+        .iter()
+        .map(|(entity, _, _, shape_state)| (shape_state.id, entity))
+        .collect();
+    let mut tree = Tree::default();
+    for (_, _, _, shape_state) in shape_query.iter() {
+        // Shapes with a missing parent are shown at the top level
+        let parent = shape_state
+            .parent
+            .filter(|parent| entities.contains_key(parent));
+        tree.children
+            .entry(parent)
+            .or_default()
+            .push(shape_state.id);
+        tree.rows.insert(
+            shape_state.id,
+            Row {
+                name: shape_state.label(),
+                visible: shape_state.visible,
+                locked: shape_state.locked,
+            },
+        );
+    }
+    let layers: HashMap<_, _> = transforms
+        .draw_order()
+        .into_iter()
+        .enumerate()
+        .map(|(layer, id)| (id, layer))
+        .collect();
+    for shapes in tree.children.values_mut() {
+        match global_state.shape_order {
+            ShapeOrder::Id => shapes.sort(),
+            ShapeOrder::Layer => shapes.sort_by_key(|id| std::cmp::Reverse(layers.get(id))),
+        }
+    }
+
+    // Make sure the shape we scroll to is visible
+    if let Some(id) = global_state.pending_scroll_to() {
+        for ancestor in transforms.ancestors(id).collect::<Vec<_>>() {
+            global_state.set_collapsed(ancestor, false);
+        }
+    }
+
+    egui::ScrollArea::vertical()
+        .id_salt("scroll_outliner")
+        .max_height(consts::OUTLINER_MAX_HEIGHT)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            let root = ui
+                .add(egui::Label::new("Shapes").sense(egui::Sense::hover()))
+                .on_hover_text("Drop a shape here to detach it from its parent");
+            if let Some(dragged) = root.dnd_release_payload::<ShapeId>() {
+                tree.dropped = Some((*dragged, None));
+            }
+            outliner_rows(ui, global_state, &mut tree, None, 1);
+        });
+
+    for (_, _, _, mut shape_state) in shape_query.iter_mut() {
+        if let Some(row) = tree.rows.get(&shape_state.id)
+            && (row.visible, row.locked) != (shape_state.visible, shape_state.locked)
+        {
+            shape_state.visible = row.visible;
+            shape_state.locked = row.locked;
+        }
+    }
+
+    if let Some((dragged, parent)) = tree.dropped
+        // A shape can't become its own ancestor
+        && parent.is_none_or(|parent| {
+            parent != dragged && !transforms.ancestors(parent).any(|id| id == dragged)
+        })
+        && let Some((entity, mut transform, _, mut shape_state)) = shape_query
+            .iter_mut()
+            .find(|(_, _, _, shape_state)| shape_state.id == dragged)
+        && shape_state.parent != parent
+    {
+        let parent = parent.and_then(|id| Some((id, *entities.get(&id)?)));
+        set_parent(
+            commands,
+            entity,
+            &mut transform,
+            &mut shape_state,
+            parent,
+            &transforms,
+        );
+    }
+}
+
+/// The shapes shown in the outliner.
+#[derive(Default)]
+struct Tree {
+    children: BTreeMap<Option<ShapeId>, Vec<ShapeId>>,
+    rows: HashMap<ShapeId, Row>,
+    /// The dragged shape and its new parent.
+    dropped: Option<(ShapeId, Option<ShapeId>)>,
+}
+
+struct Row {
+    name: String,
+    visible: bool,
+    locked: bool,
+}
+
+fn outliner_rows(
+    ui: &mut egui::Ui,
+    global_state: &mut GlobalState,
+    tree: &mut Tree,
+    parent: Option<ShapeId>,
+    depth: usize,
+) {
+    for id in tree.children.get(&parent).cloned().unwrap_or_default() {
+        let has_children = tree.children.contains_key(&Some(id));
+        let collapsed = global_state.is_collapsed(id);
+        let Some(row) = tree.rows.get_mut(&id) else {
+            continue;
+        };
+
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * consts::OUTLINER_INDENT);
+            if has_children {
+                if ui.small_button(if collapsed { "⏵" } else { "⏷" }).clicked() {
+                    global_state.set_collapsed(id, !collapsed);
+                }
+            } else {
+                ui.add_space(ui.spacing().interact_size.y);
+            }
+
+            // With several selected shapes, the shown one is underlined
+            let mut text = egui::RichText::new(&row.name);
+            if global_state.selection().len() > 1
+                && global_state.selected_tab() == SelectedTab::Shape(id)
+            {
+                text = text.underline();
+            }
+            if !row.visible {
+                text = text.weak();
+            }
+            let label = ui.dnd_drag_source(egui::Id::new(("outliner", id)), id, |ui| {
+                ui.selectable_label(global_state.is_selected(id), text)
+                    .on_hover_text("Shift or Ctrl click to select several shapes")
+            });
+            if label.inner.clicked() {
+                if ui.input(|i| i.modifiers.shift || i.modifiers.command) {
+                    global_state.toggle_selection(id);
+                } else {
+                    global_state.select_tab(SelectedTab::Shape(id));
+                }
+            }
+            if label.response.dnd_hover_payload::<ShapeId>().is_some() {
+                ui.painter().rect_stroke(
+                    label.response.rect,
+                    2.0,
+                    ui.visuals().selection.stroke,
+                    egui::StrokeKind::Outside,
+                );
+            }
+            if let Some(dragged) = label.response.dnd_release_payload::<ShapeId>() {
+                tree.dropped = Some((*dragged, Some(id)));
+            }
+            if global_state.check_scroll_to(id) {
+                label.response.scroll_to_me(None);
+            }
+
+            ui.toggle_value(&mut row.visible, "👁")
+                .on_hover_text("Show the shape");
+            ui.toggle_value(&mut row.locked, "🔒")
+                .on_hover_text("Lock the shape, so it can't be picked or edited in the viewport");
+        });
+
+        if has_children && !collapsed {
+            outliner_rows(ui, global_state, tree, Some(id), depth + 1);
+        }
+    }
+}
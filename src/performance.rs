@@ -0,0 +1,422 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use bevy::{
+    diagnostic::{DiagnosticPath, DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    platform::time::Instant,
+    prelude::*,
+    render::{
+        Render, RenderApp,
+        render_resource::{CachedPipeline, CachedPipelineState, PipelineCache, PipelineDescriptor},
+    },
+    shader::PipelineCacheError,
+    window::PrimaryWindow,
+};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use bevy_smud::SmudShape;
+
+use crate::{
+    ShapeCamera, consts,
+    cpu_shader::CpuShaders,
+    shape::shape_bounds,
+    state::{GlobalState, SelectedTab, ShapeId, ShapeState},
+    stress_test::{StressTest, stress_test_ui, update_stress_test},
+};
+
+/// Collects frame times, pipeline counts, shader compile times and estimated costs of shapes and
+/// shows them in a window, together with the [`StressTest`].
+pub struct PerformancePlugin;
+
+impl Plugin for PerformancePlugin {
+    fn build(&self, app: &mut App) {
+        let pipeline_stats = PipelineStats::default();
+        app.add_plugins(FrameTimeDiagnosticsPlugin::default())
+            .insert_resource(Performance::default())
+            .insert_resource(pipeline_stats.clone())
+            .insert_resource(StressTest::default())
+            .add_systems(Update, (update_performance, update_stress_test))
+            .add_systems(EguiPrimaryContextPass, performance_window);
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(pipeline_stats)
+                .add_systems(Render, update_pipeline_stats);
+        }
+    }
+}
+
+/// Render pipelines and their compilations, written by the render world.
+#[derive(Resource, Clone, Default)]
+struct PipelineStats(Arc<Mutex<PipelineStatsData>>);
+
+#[derive(Default)]
+struct PipelineStatsData {
+    ready: usize,
+    /// Pipelines that are queued or still being compiled.
+    pending: usize,
+    /// The most recent compilations first.
+    compilations: VecDeque<Compilation>,
+}
+
+impl PipelineStats {
+    fn lock(&self) -> std::sync::MutexGuard<'_, PipelineStatsData> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Counts the render pipelines and measures how long each one takes from being queued until it's
+/// ready, which includes the compilation by the graphics driver.
+fn update_pipeline_stats(
+    pipeline_cache: Res<PipelineCache>,
+    pipeline_stats: Res<PipelineStats>,
+    mut started: Local<HashMap<usize, Instant>>,
+) {
+    let mut stats = pipeline_stats.lock();
+    let (mut ready, mut pending) = (0, 0);
+    for (id, pipeline) in pipeline_cache.pipelines().enumerate() {
+        let error = match &pipeline.state {
+            CachedPipelineState::Queued
+            | CachedPipelineState::Creating(_)
+            // Bevy retries these errors until the shaders are loaded
+            | CachedPipelineState::Err(
+                PipelineCacheError::ShaderNotLoaded(_)
+                | PipelineCacheError::ShaderImportNotYetAvailable,
+            ) => {
+                pending += 1;
+                started.entry(id).or_insert_with(Instant::now);
+                continue;
+            }
+            CachedPipelineState::Ok(_) => {
+                ready += 1;
+                None
+            }
+            CachedPipelineState::Err(error) => Some(error.to_string()),
+        };
+        if let Some(start) = started.remove(&id) {
+            stats.compilations.push_front(Compilation {
+                label: pipeline_label(pipeline, id),
+                duration: Some(start.elapsed()),
+                error,
+            });
+        }
+    }
+    stats
+        .compilations
+        .truncate(consts::MAX_PERFORMANCE_COMPILATIONS);
+    stats.ready = ready;
+    stats.pending = pending;
+}
+
+fn pipeline_label(pipeline: &CachedPipeline, id: usize) -> String {
+    let label = match &pipeline.descriptor {
+        PipelineDescriptor::RenderPipelineDescriptor(descriptor) => descriptor.label.as_deref(),
+        PipelineDescriptor::ComputePipelineDescriptor(descriptor) => descriptor.label.as_deref(),
+    };
+    format!("{} #{id}", label.unwrap_or("unnamed pipeline"))
+}
+
+/// A shader or render pipeline that was compiled, e.g. after the code of a shape changed.
+struct Compilation {
+    label: String,
+    duration: Option<Duration>,
+    error: Option<String>,
+}
+
+/// Estimated cost of a visible shape, the SDF and the fill are evaluated for every covered pixel.
+struct ShapeCost {
+    id: ShapeId,
+    label: String,
+    /// Physical pixels covered by the bounds of the shape.
+    pixels: f32,
+    /// Expressions in the naga IR of `sdf` and `fill`.
+    instructions: usize,
+}
+
+impl ShapeCost {
+    fn cost(&self) -> f32 {
+        self.pixels * self.instructions as f32
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Performance {
+    pub window_open: bool,
+    /// Shaders of shapes whose compilation was already recorded.
+    compiled: HashSet<AssetId<Shader>>,
+    /// The most recent compilations first.
+    compilations: VecDeque<Compilation>,
+    costs: Vec<ShapeCost>,
+}
+
+fn update_performance(
+    mut performance: ResMut<Performance>,
+    mut cpu_shaders: ResMut<CpuShaders>,
+    shaders: Res<Assets<Shader>>,
+    global_state: Res<GlobalState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&GlobalTransform, &Projection), With<ShapeCamera>>,
+    shape_query: Query<(&ShapeState, &SmudShape, &GlobalTransform)>,
+) {
+    let performance = &mut *performance;
+    performance.costs.clear();
+    if !performance.window_open {
+        return;
+    }
+
+    performance.compiled.retain(|id| shaders.contains(*id));
+    for (shape_state, shape, _) in &shape_query {
+        for (name, handle) in [("sdf", &shape.sdf), ("fill", &shape.fill)] {
+            if !performance.compiled.insert(handle.id()) {
+                continue;
+            }
+            let error = cpu_shaders.get(&shaders, handle, name).err();
+            performance.compilations.push_front(Compilation {
+                label: format!("{} {name}", shape_state.label()),
+                duration: cpu_shaders.compile_time(handle),
+                error,
+            });
+        }
+    }
+    performance
+        .compilations
+        .truncate(consts::MAX_PERFORMANCE_COMPILATIONS);
+
+    let (camera_transform, projection) = *camera;
+    let Projection::Orthographic(projection) = projection else {
+        return;
+    };
+    let visible = Rect::from_center_size(
+        camera_transform.translation().xy(),
+        window.size() * projection.scale,
+    );
+    // Physical pixels per world unit
+    let resolution = window.scale_factor() * global_state.quality.render_scale / projection.scale;
+
+    for (shape_state, shape, world) in &shape_query {
+        if !shape_state.visible {
+            continue;
+        }
+        let bounds = shape_bounds(shape_state, world).intersect(visible);
+        if bounds.is_empty() {
+            continue;
+        }
+        let instructions = [("sdf", &shape.sdf), ("fill", &shape.fill)]
+            .into_iter()
+            .filter_map(|(name, handle)| cpu_shaders.get(&shaders, handle, name).ok())
+            .map(|function| function.instruction_count())
+            .sum();
+        performance.costs.push(ShapeCost {
+            id: shape_state.id,
+            label: shape_state.label(),
+            pixels: bounds.size().element_product() * resolution * resolution,
+            instructions,
+        });
+    }
+    performance
+        .costs
+        .sort_by(|a, b| b.cost().total_cmp(&a.cost()).then(a.id.cmp(&b.id)));
+}
+
+fn performance_window(
+    mut contexts: EguiContexts,
+    mut performance: ResMut<Performance>,
+    mut stress_test: ResMut<StressTest>,
+    global_state: Res<GlobalState>,
+    diagnostics: Res<DiagnosticsStore>,
+    pipeline_stats: Res<PipelineStats>,
+    shape_query: Query<&SmudShape, With<ShapeState>>,
+) -> Result {
+    let ctx = contexts.ctx_mut()?;
+    let mut open = performance.window_open;
+    egui::Window::new("Performance")
+        .open(&mut open)
+        .default_width(consts::PERFORMANCE_WINDOW_WIDTH)
+        .show(ctx, |ui| {
+            let pipeline_stats = pipeline_stats.lock();
+            let (ready, pending) = (pipeline_stats.ready, pipeline_stats.pending);
+            egui::Grid::new("grid_performance")
+                .num_columns(2)
+                .spacing([40.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("FPS:");
+                    diagnostic_plot(ui, &diagnostics, &FrameTimeDiagnosticsPlugin::FPS, "");
+                    ui.end_row();
+
+                    ui.label("Frame time:");
+                    diagnostic_plot(
+                        ui,
+                        &diagnostics,
+                        &FrameTimeDiagnosticsPlugin::FRAME_TIME,
+                        " ms",
+                    );
+                    ui.end_row();
+
+                    ui.label("Shapes:");
+                    ui.label(shape_query.iter().count().to_string());
+                    ui.end_row();
+
+                    // Copies of a shape share its shaders
+                    let shaders: HashSet<_> = shape_query
+                        .iter()
+                        .flat_map(|shape| [shape.sdf.id(), shape.fill.id()])
+                        .collect();
+                    ui.label("Shaders:");
+                    ui.label(shaders.len().to_string())
+                        .on_hover_text("Distinct sdf and fill shaders of the shapes");
+                    ui.end_row();
+
+                    ui.label("Render pipelines:");
+                    if pending > 0 {
+                        ui.label(format!("{ready} ({pending} compiling)"));
+                    } else {
+                        ui.label(ready.to_string());
+                    }
+                    ui.end_row();
+                });
+
+            egui::CollapsingHeader::new("Pipeline compilations")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.weak(
+                        "Time from queueing a render pipeline until it's ready, measured once \
+                         per frame",
+                    );
+                    compilations_ui(
+                        ui,
+                        "grid_pipeline_compilations",
+                        &pipeline_stats.compilations,
+                    );
+                });
+
+            egui::CollapsingHeader::new("CPU shader compilations").show(ui, |ui| {
+                ui.weak(
+                    "Time for composing and parsing the shaders of shapes with naga on the CPU, \
+                     e.g. for picking and the shape cost",
+                );
+                compilations_ui(ui, "grid_cpu_compilations", &performance.compilations);
+            });
+
+            egui::CollapsingHeader::new("Shape cost")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.weak("Covered pixels × instructions of sdf and fill, for visible shapes");
+                    let max_cost = performance.costs.first().map_or(0.0, ShapeCost::cost);
+                    egui::Grid::new("grid_shape_cost")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Shape");
+                            ui.strong("Pixels");
+                            ui.strong("Instructions");
+                            ui.strong("Cost");
+                            ui.end_row();
+                            for cost in &performance.costs {
+                                ui.label(&cost.label);
+                                ui.label(format!("{:.0}", cost.pixels));
+                                ui.label(cost.instructions.to_string());
+                                ui.add(
+                                    egui::ProgressBar::new(cost.cost() / max_cost.max(1.0))
+                                        .desired_width(100.0)
+                                        .text(format!("{:.1} M", cost.cost() / 1e6)),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            egui::CollapsingHeader::new("Stress test").show(ui, |ui| {
+                let selected = match global_state.selected_tab() {
+                    SelectedTab::Shape(id) => Some(id),
+                    SelectedTab::Global => None,
+                };
+                stress_test_ui(ui, &mut stress_test, selected);
+            });
+        });
+    performance.window_open = open;
+    Ok(())
+}
+
+fn compilations_ui(ui: &mut egui::Ui, id: &str, compilations: &VecDeque<Compilation>) {
+    if compilations.is_empty() {
+        ui.weak("Nothing was compiled yet");
+    }
+    egui::Grid::new(id)
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for compilation in compilations {
+                ui.label(&compilation.label);
+                match (&compilation.error, compilation.duration) {
+                    (Some(error), _) => {
+                        ui.colored_label(ui.visuals().error_fg_color, "failed")
+                            .on_hover_text(error);
+                    }
+                    (None, Some(duration)) => {
+                        ui.label(format!("{:.2} ms", duration.as_secs_f64() * 1000.0));
+                    }
+                    (None, None) => {
+                        ui.weak("-");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// Shows the current value of the diagnostic and a graph of its history.
+fn diagnostic_plot(
+    ui: &mut egui::Ui,
+    diagnostics: &DiagnosticsStore,
+    path: &DiagnosticPath,
+    suffix: &str,
+) {
+    let Some(diagnostic) = diagnostics.get(path) else {
+        ui.weak("unavailable");
+        return;
+    };
+    let values: Vec<f64> = diagnostic.values().copied().collect();
+    let max = values.iter().copied().fold(0.0, f64::max);
+
+    ui.horizontal(|ui| {
+        let value = diagnostic.smoothed().unwrap_or_default();
+        ui.add_sized(
+            [70.0, ui.spacing().interact_size.y],
+            egui::Label::new(format!("{value:.1}{suffix}")),
+        );
+
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), consts::PERFORMANCE_PLOT_HEIGHT),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        if values.len() < 2 || max <= 0.0 {
+            return;
+        }
+        let step = rect.width() / (values.len() - 1) as f32;
+        let points = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                egui::pos2(
+                    rect.left() + index as f32 * step,
+                    rect.bottom() - rect.height() * (value / max) as f32,
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            egui::Stroke::new(1.0, ui.visuals().strong_text_color()),
+        ));
+        painter.text(
+            rect.right_top(),
+            egui::Align2::RIGHT_TOP,
+            format!("{max:.1}"),
+            egui::FontId::monospace(9.0),
+            ui.visuals().weak_text_color(),
+        );
+    });
+}
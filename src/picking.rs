@@ -0,0 +1,121 @@
+use bevy::{
+    camera::RenderTarget,
+    picking::{
+        PickingSystems,
+        backend::{HitData, PointerHits},
+        pointer::{PointerId, PointerLocation},
+    },
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_smud::SmudShape;
+
+use crate::{
+    ShapeCamera,
+    cpu_shader::{ShapeEvaluator, ShapeInputs},
+    state::{GlobalState, PickingMode, ShapeState},
+};
+
+/// Picking backend for shapes that hits them either within their bounds or, with
+/// [`PickingMode::Sdf`], only within the threshold of the shape's area.
+pub struct ShapePickingPlugin;
+
+impl Plugin for ShapePickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, pick_shapes.in_set(PickingSystems::Backend));
+    }
+}
+
+fn pick_shapes(
+    global_state: Res<GlobalState>,
+    mut evaluator: ShapeEvaluator,
+    window: Single<(Entity, &Window), With<PrimaryWindow>>,
+    pointers: Query<(&PointerId, &PointerLocation)>,
+    camera: Single<(Entity, &Camera, &GlobalTransform, &Projection), With<ShapeCamera>>,
+    shape_query: Query<(Entity, &ShapeState, &SmudShape, &GlobalTransform)>,
+    mut output: MessageWriter<PointerHits>,
+) {
+    let (window_entity, window) = *window;
+    let (camera_entity, camera, camera_transform, projection) = *camera;
+    let Projection::Orthographic(projection) = projection else {
+        return;
+    };
+    // The camera might render into an image for the render scale, which is shown in the window
+    let Some(target) = RenderTarget::default().normalize(Some(window_entity)) else {
+        return;
+    };
+    let globals = evaluator.globals(window);
+    let camera_inverse = camera_transform.affine().inverse();
+
+    for (pointer, location) in &pointers {
+        let Some(location) = location.location() else {
+            continue;
+        };
+        if location.target != target {
+            continue;
+        }
+        let viewport_min = camera
+            .logical_viewport_rect()
+            .map_or(Vec2::ZERO, |rect| rect.min);
+        let Ok(cursor) =
+            camera.viewport_to_world_2d(camera_transform, location.position - viewport_min)
+        else {
+            continue;
+        };
+
+        let picks = shape_query
+            .iter()
+            .filter(|(_, shape_state, _, _)| shape_state.visible && !shape_state.locked)
+            .filter_map(|(entity, shape_state, shape, world)| {
+                let local = world
+                    .affine()
+                    .inverse()
+                    .transform_point3(cursor.extend(0.0))
+                    .xy();
+                if local.abs().max_element() > shape_state.bounds_length / 2.0 {
+                    return None;
+                }
+                let inputs =
+                    ShapeInputs::new(shape_state.params, shape, world, projection.scale, globals);
+                if global_state.picking_mode == PickingMode::Sdf
+                    && !is_within_threshold(
+                        &mut evaluator,
+                        shape,
+                        &inputs,
+                        local,
+                        global_state.picking_threshold,
+                    )
+                {
+                    return None;
+                }
+
+                // Shapes in front of others have a smaller depth
+                let depth =
+                    -projection.near - camera_inverse.transform_point3(world.translation()).z;
+                let position = cursor.extend(world.translation().z);
+                Some((
+                    entity,
+                    HitData::new(camera_entity, depth, Some(position), None),
+                ))
+            })
+            .collect();
+
+        output.write(PointerHits::new(*pointer, picks, camera.order as f32));
+    }
+}
+
+/// Returns whether the local position is inside of the shape or at most `threshold` logical
+/// pixels outside of it. If the SDF can't be evaluated, the whole bounds count as a hit.
+fn is_within_threshold(
+    evaluator: &mut ShapeEvaluator,
+    shape: &SmudShape,
+    inputs: &ShapeInputs,
+    local: Vec2,
+    threshold: f32,
+) -> bool {
+    evaluator
+        .get(&shape.sdf, "sdf")
+        .and_then(|sdf| sdf.sdf(local, inputs))
+        .ok()
+        .is_none_or(|distance| distance < threshold * inputs.pixel_size)
+}
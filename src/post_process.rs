@@ -0,0 +1,172 @@
+use bevy::{
+    core_pipeline::tonemapping::Tonemapping,
+    post_process::bloom::{Bloom, BloomCompositeMode, BloomPrefilter},
+    prelude::*,
+    render::view::Hdr,
+};
+use bevy_egui::egui;
+
+use crate::{ShapeCamera, consts, state::GlobalState};
+
+/// Operators that don't need the lookup textures of the `tonemapping_luts` feature.
+const TONEMAPPINGS: [(Tonemapping, &str); 5] = [
+    (Tonemapping::None, "None"),
+    (Tonemapping::Reinhard, "Reinhard"),
+    (Tonemapping::ReinhardLuminance, "Reinhard luminance"),
+    (Tonemapping::AcesFitted, "ACES fitted"),
+    (
+        Tonemapping::SomewhatBoringDisplayTransform,
+        "Somewhat boring display transform",
+    ),
+];
+
+/// Post-processing of the [`ShapeCamera`], bloom is only available with HDR.
+pub struct PostProcessSettings {
+    /// Renders into a texture with a higher range, so colors above 1 are kept until tonemapping.
+    pub hdr: bool,
+    pub tonemapping: Tonemapping,
+    pub bloom: bool,
+    pub bloom_intensity: f32,
+    /// Only colors brighter than the threshold contribute to the bloom.
+    pub bloom_threshold: f32,
+    pub bloom_composite_mode: BloomCompositeMode,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            hdr: false,
+            tonemapping: Tonemapping::None,
+            bloom: false,
+            bloom_intensity: consts::DEFAULT_BLOOM_INTENSITY,
+            bloom_threshold: 0.0,
+            bloom_composite_mode: BloomCompositeMode::EnergyConserving,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    /// Inserts the post-processing components into a new camera that renders the shapes, e.g.
+    /// for an export. Otherwise it would use the default tonemapping of [`Camera2d`].
+    pub fn insert_into(&self, camera: &mut EntityCommands) {
+        camera.insert(self.tonemapping);
+        if self.hdr {
+            camera.insert(Hdr);
+            if self.bloom {
+                camera.insert(self.new_bloom());
+            }
+        }
+    }
+
+    fn new_bloom(&self) -> Bloom {
+        Bloom {
+            intensity: self.bloom_intensity,
+            prefilter: BloomPrefilter {
+                threshold: self.bloom_threshold,
+                ..default()
+            },
+            composite_mode: self.bloom_composite_mode,
+            ..Bloom::NATURAL
+        }
+    }
+}
+
+/// Adds or removes the post-processing components of the camera according to the settings.
+pub fn apply_post_process(
+    mut commands: Commands,
+    global_state: Res<GlobalState>,
+    camera: Single<(Entity, Has<Hdr>, &mut Tonemapping, Option<&mut Bloom>), With<ShapeCamera>>,
+) {
+    let settings = &global_state.post_process;
+    let (entity, has_hdr, mut tonemapping, bloom) = camera.into_inner();
+    let mut camera = commands.entity(entity);
+
+    tonemapping.set_if_neq(settings.tonemapping);
+
+    if settings.hdr && !has_hdr {
+        camera.insert(Hdr);
+    }
+    if !settings.hdr && has_hdr {
+        camera.remove::<(Hdr, Bloom)>();
+        return;
+    }
+
+    match bloom {
+        Some(mut bloom) if settings.bloom => {
+            // Only written when changed, so the bloom isn't marked as changed every frame
+            if bloom.intensity != settings.bloom_intensity {
+                bloom.intensity = settings.bloom_intensity;
+            }
+            if bloom.prefilter.threshold != settings.bloom_threshold {
+                bloom.prefilter.threshold = settings.bloom_threshold;
+            }
+            if bloom.composite_mode != settings.bloom_composite_mode {
+                bloom.composite_mode = settings.bloom_composite_mode;
+            }
+        }
+        Some(_) => {
+            camera.remove::<Bloom>();
+        }
+        None if settings.bloom && settings.hdr => {
+            camera.insert(settings.new_bloom());
+        }
+        None => {}
+    }
+}
+
+pub fn post_process_ui(ui: &mut egui::Ui, settings: &mut PostProcessSettings) {
+    ui.label("Post-processing:");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut settings.hdr, "HDR")
+            .on_hover_text("Keep colors brighter than 1, e.g. of additive glows, for bloom");
+        ui.add_enabled_ui(settings.hdr, |ui| {
+            ui.checkbox(&mut settings.bloom, "Bloom")
+                .on_disabled_hover_text("Bloom requires HDR");
+        });
+    });
+    ui.end_row();
+
+    ui.label("Tonemapping:");
+    let selected = TONEMAPPINGS
+        .iter()
+        .find(|(tonemapping, _)| *tonemapping == settings.tonemapping)
+        .map_or("", |(_, name)| name);
+    egui::ComboBox::from_id_salt("tonemapping")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (tonemapping, name) in TONEMAPPINGS {
+                ui.selectable_value(&mut settings.tonemapping, tonemapping, name);
+            }
+        });
+    ui.end_row();
+
+    ui.label("Bloom:");
+    ui.add_enabled_ui(settings.hdr && settings.bloom, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("intensity");
+            ui.add(
+                egui::DragValue::new(&mut settings.bloom_intensity)
+                    .range(0.0..=1.0)
+                    .speed(0.005),
+            );
+            ui.label("threshold");
+            ui.add(
+                egui::DragValue::new(&mut settings.bloom_threshold)
+                    .range(0.0..=f32::MAX)
+                    .speed(0.01),
+            )
+            .on_hover_text("Only colors brighter than this contribute to the bloom");
+            ui.selectable_value(
+                &mut settings.bloom_composite_mode,
+                BloomCompositeMode::EnergyConserving,
+                "Energy conserving",
+            );
+            ui.selectable_value(
+                &mut settings.bloom_composite_mode,
+                BloomCompositeMode::Additive,
+                "Additive",
+            );
+        });
+    });
+    ui.end_row();
+}
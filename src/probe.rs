@@ -0,0 +1,277 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_egui::{EguiContexts, egui};
+use bevy_smud::SmudShape;
+
+use crate::{
+    ShapeCamera, consts,
+    cpu_shader::{ShapeEvaluator, ShapeInputs},
+    cursor::{Cursor, UserInput},
+    state::{ShapeId, ShapeState},
+};
+
+/// The shader outputs of a shape at one point, evaluated on the CPU.
+struct Sample {
+    shape: ShapeId,
+    label: String,
+    /// Position in the local space of the shape.
+    local: Vec2,
+    distance: Result<f32, String>,
+    /// Linear RGBA output of the fill.
+    color: Result<Vec4, String>,
+}
+
+/// A point in world space whose sample is kept up to date for comparison.
+struct Pin {
+    shape: ShapeId,
+    position: Vec2,
+    /// `None` if the shape was removed.
+    sample: Option<Sample>,
+}
+
+/// Reports the SDF distance and the fill color of the shape under the cursor, pressing P pins the
+/// probe at the cursor.
+#[derive(Resource, Default)]
+pub struct ProbeTool {
+    pub enabled: bool,
+    hover: Option<Sample>,
+    pins: Vec<Pin>,
+}
+
+pub fn update_probe(
+    mut probe_tool: ResMut<ProbeTool>,
+    mut evaluator: ShapeEvaluator,
+    cursor: Res<Cursor>,
+    input: UserInput,
+    window: Single<&Window, With<PrimaryWindow>>,
+    projection: Single<&Projection, With<ShapeCamera>>,
+    shape_query: Query<(&ShapeState, &SmudShape, &GlobalTransform)>,
+) {
+    if !probe_tool.enabled {
+        probe_tool.hover = None;
+        return;
+    }
+    let Projection::Orthographic(projection) = *projection else {
+        return;
+    };
+    let UserInput {
+        keys,
+        egui_wants_input,
+        ..
+    } = input;
+    let globals = evaluator.globals(&window);
+    // Pins sample their own shape, otherwise the topmost visible shape under the position
+    let mut sample = |position: Vec2, pinned: Option<ShapeId>| {
+        shape_query
+            .iter()
+            .filter(|(shape_state, _, _)| match pinned {
+                Some(id) => shape_state.id == id,
+                None => shape_state.visible,
+            })
+            .filter_map(|(shape_state, shape, world)| {
+                let local = world
+                    .affine()
+                    .inverse()
+                    .transform_point3(position.extend(0.0))
+                    .xy();
+                (pinned.is_some() || local.abs().max_element() <= shape_state.bounds_length / 2.0)
+                    .then_some((shape_state, shape, world, local))
+            })
+            .max_by(|(a, _, a_world, _), (b, _, b_world, _)| {
+                let (a_z, b_z) = (a_world.translation().z, b_world.translation().z);
+                a_z.total_cmp(&b_z).then(a.id.cmp(&b.id))
+            })
+            .map(|(shape_state, shape, world, local)| {
+                let inputs =
+                    ShapeInputs::new(shape_state.params, shape, world, projection.scale, globals);
+                evaluate(&mut evaluator, shape_state, shape, &inputs, local)
+            })
+    };
+
+    let hover = cursor
+        .world_position
+        .filter(|_| !egui_wants_input.is_pointer_over_area())
+        .and_then(|position| sample(position, None).map(|sample| (position, sample)));
+
+    if keys.just_pressed(KeyCode::KeyP)
+        && !egui_wants_input.wants_any_keyboard_input()
+        && let Some((position, sample)) = &hover
+    {
+        probe_tool.pins.push(Pin {
+            shape: sample.shape,
+            position: *position,
+            sample: None,
+        });
+    }
+    for pin in &mut probe_tool.pins {
+        pin.sample = sample(pin.position, Some(pin.shape));
+    }
+    probe_tool.hover = hover.map(|(_, sample)| sample);
+}
+
+/// Evaluates the SDF and the fill of the shape at the local position.
+fn evaluate(
+    evaluator: &mut ShapeEvaluator,
+    shape_state: &ShapeState,
+    shape: &SmudShape,
+    inputs: &ShapeInputs,
+    local: Vec2,
+) -> Sample {
+    let distance = evaluator
+        .get(&shape.sdf, "sdf")
+        .and_then(|sdf| sdf.sdf(local, inputs));
+    // The fill is the compiled one, so it includes the distance view
+    let color = distance.clone().and_then(|distance| {
+        evaluator
+            .get(&shape.fill, "fill")
+            .and_then(|fill| fill.fill(local, distance, inputs))
+    });
+    Sample {
+        shape: shape_state.id,
+        label: shape_state.label(),
+        local,
+        distance,
+        color,
+    }
+}
+
+/// Shows the sample under the cursor in a tooltip and marks the pinned points.
+pub fn probe_overlay(
+    mut contexts: EguiContexts,
+    probe_tool: Res<ProbeTool>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<ShapeCamera>>,
+) -> Result {
+    if !probe_tool.enabled {
+        return Ok(());
+    }
+    let ctx = contexts.ctx_mut()?;
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("probe"),
+    ));
+    let visuals = ctx.style().visuals.clone();
+
+    let (camera, camera_transform) = *camera;
+    for (index, pin) in probe_tool.pins.iter().enumerate() {
+        let Ok(position) = camera.world_to_viewport(camera_transform, pin.position.extend(0.0))
+        else {
+            continue;
+        };
+        let center = egui::pos2(position.x, position.y);
+        let stroke = egui::Stroke::new(1.5, visuals.strong_text_color());
+        painter.circle_stroke(center, consts::PROBE_MARKER_RADIUS, stroke);
+        painter.circle_filled(center, 1.5, stroke.color);
+        painter.text(
+            center + egui::vec2(consts::PROBE_MARKER_RADIUS + 2.0, 0.0),
+            egui::Align2::LEFT_BOTTOM,
+            pin_label(index),
+            egui::FontId::monospace(11.0),
+            stroke.color,
+        );
+    }
+
+    if let Some(sample) = &probe_tool.hover
+        && let Some(cursor) = window.cursor_position()
+    {
+        egui::Area::new(egui::Id::new("probe_tooltip"))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(egui::pos2(cursor.x, cursor.y) + consts::PROBE_TOOLTIP_OFFSET)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.strong(&sample.label);
+                    sample_grid(ui, "grid_probe_tooltip", sample);
+                    ui.weak("Press P to pin");
+                });
+            });
+    }
+
+    Ok(())
+}
+
+pub fn probe_ui(ui: &mut egui::Ui, probe_tool: &mut ProbeTool) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut probe_tool.enabled, "Enabled")
+            .on_hover_text(
+                "Show the distance and the fill color of the shape under the cursor, \
+                 evaluated on the CPU",
+            );
+        if ui
+            .add_enabled(!probe_tool.pins.is_empty(), egui::Button::new("Clear pins"))
+            .clicked()
+        {
+            probe_tool.pins.clear();
+        }
+    });
+    if probe_tool.enabled && probe_tool.pins.is_empty() {
+        ui.weak("Press P over a shape to pin the probe");
+    }
+
+    let mut removed = None;
+    for (index, pin) in probe_tool.pins.iter().enumerate() {
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.small_button("🗑").on_hover_text("Remove pin").clicked() {
+                removed = Some(index);
+            }
+            match &pin.sample {
+                Some(sample) => ui.strong(format!("{} {}", pin_label(index), sample.label)),
+                None => ui.weak(format!("{} shape was removed", pin_label(index))),
+            };
+        });
+        if let Some(sample) = &pin.sample {
+            sample_grid(ui, ("grid_probe_pin", index), sample);
+        }
+    }
+    if let Some(index) = removed {
+        probe_tool.pins.remove(index);
+    }
+}
+
+fn sample_grid(ui: &mut egui::Ui, id_salt: impl std::hash::Hash, sample: &Sample) {
+    egui::Grid::new(id_salt)
+        .num_columns(2)
+        .spacing([20.0, 2.0])
+        .show(ui, |ui| {
+            ui.label("Local:");
+            ui.monospace(format!(
+                "x: {:.2}, y: {:.2}",
+                sample.local.x, sample.local.y
+            ));
+            ui.end_row();
+
+            ui.label("Distance:");
+            match &sample.distance {
+                Ok(distance) => ui.monospace(format!("{distance:.3}")),
+                Err(error) => ui.colored_label(ui.visuals().error_fg_color, error),
+            };
+            ui.end_row();
+
+            ui.label("Fill:");
+            match &sample.color {
+                Ok(color) => {
+                    ui.horizontal(|ui| {
+                        let [r, g, b, a] = color.to_array();
+                        egui::color_picker::show_color(
+                            ui,
+                            egui::Rgba::from_rgba_unmultiplied(r, g, b, a),
+                            egui::vec2(16.0, 16.0),
+                        );
+                        ui.monospace(format!("{r:.3}, {g:.3}, {b:.3}, {a:.3}"));
+                    });
+                }
+                // The error of the SDF is already shown
+                Err(_) if sample.distance.is_err() => {
+                    ui.weak("-");
+                }
+                Err(error) => {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            }
+            ui.end_row();
+        });
+}
+
+fn pin_label(index: usize) -> String {
+    format!("#{}", index + 1)
+}
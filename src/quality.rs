@@ -0,0 +1,265 @@
+use bevy::{
+    anti_alias::{
+        fxaa::{Fxaa, Sensitivity},
+        smaa::{Smaa, SmaaPreset},
+    },
+    camera::{ImageRenderTarget, RenderTarget, visibility::RenderLayers},
+    core_pipeline::tonemapping::Tonemapping,
+    math::FloatOrd,
+    prelude::*,
+    render::render_resource::TextureFormat,
+    window::{PresentMode, PrimaryWindow},
+};
+use bevy_egui::{PrimaryEguiContext, egui};
+
+use crate::{ShapeCamera, consts, state::GlobalState};
+
+/// Sample counts that are supported by all backends, browsers only support 4 samples.
+#[cfg(not(target_arch = "wasm32"))]
+const MSAA: [(Msaa, &str); 4] = [
+    (Msaa::Off, "Off"),
+    (Msaa::Sample2, "2x"),
+    (Msaa::Sample4, "4x"),
+    (Msaa::Sample8, "8x"),
+];
+#[cfg(target_arch = "wasm32")]
+const MSAA: [(Msaa, &str); 2] = [(Msaa::Off, "Off"), (Msaa::Sample4, "4x")];
+
+const FXAA_SENSITIVITIES: [(Sensitivity, &str); 5] = [
+    (Sensitivity::Low, "Low"),
+    (Sensitivity::Medium, "Medium"),
+    (Sensitivity::High, "High"),
+    (Sensitivity::Ultra, "Ultra"),
+    (Sensitivity::Extreme, "Extreme"),
+];
+
+const SMAA_PRESETS: [(SmaaPreset, &str); 4] = [
+    (SmaaPreset::Low, "Low"),
+    (SmaaPreset::Medium, "Medium"),
+    (SmaaPreset::High, "High"),
+    (SmaaPreset::Ultra, "Ultra"),
+];
+
+/// Anti-aliasing and resolution of the [`ShapeCamera`].
+pub struct RenderQuality {
+    pub msaa: Msaa,
+    pub fxaa: bool,
+    /// How strong edges need to be for FXAA to smooth them.
+    pub fxaa_sensitivity: Sensitivity,
+    pub smaa: bool,
+    /// Higher presets search further along edges and detect diagonals and corners.
+    pub smaa_preset: SmaaPreset,
+    /// Resolution of the shapes relative to the window, values above 1 supersample them.
+    pub render_scale: f32,
+    pub vsync: bool,
+}
+
+impl Default for RenderQuality {
+    fn default() -> Self {
+        Self {
+            msaa: Msaa::Off,
+            fxaa: false,
+            fxaa_sensitivity: Sensitivity::High,
+            smaa: false,
+            smaa_preset: SmaaPreset::High,
+            render_scale: 1.0,
+            vsync: true,
+        }
+    }
+}
+
+/// Shows the image that the [`ShapeCamera`] renders into if the render scale isn't 1.
+#[derive(Component)]
+pub struct RenderScaleSprite;
+
+/// Spawns the camera that shows the scaled image of the shapes and the UI on top of them.
+pub fn spawn_display_camera(commands: &mut Commands) {
+    let render_layers = RenderLayers::layer(consts::DISPLAY_RENDER_LAYER);
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: 1,
+            // The shape camera renders into the window itself with a render scale of 1
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        PrimaryEguiContext,
+        Msaa::Off,
+        Tonemapping::None,
+        render_layers.clone(),
+    ));
+    commands.spawn((
+        Sprite::default(),
+        RenderScaleSprite,
+        Visibility::Hidden,
+        render_layers,
+    ));
+}
+
+/// Applies the anti-aliasing, the present mode and the render scale, which renders the shapes
+/// into an image whose logical size matches the window, so positions don't need to be converted.
+pub fn apply_render_quality(
+    mut commands: Commands,
+    global_state: Res<GlobalState>,
+    mut images: ResMut<Assets<Image>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    camera: Single<
+        (
+            Entity,
+            &mut Camera,
+            &mut Msaa,
+            Option<&mut Fxaa>,
+            Option<&mut Smaa>,
+        ),
+        With<ShapeCamera>,
+    >,
+    sprite: Single<(&mut Sprite, &mut Visibility), With<RenderScaleSprite>>,
+) {
+    let quality = &global_state.quality;
+    let (entity, mut camera, mut msaa, fxaa, smaa) = camera.into_inner();
+    let (mut sprite, mut visibility) = sprite.into_inner();
+
+    msaa.set_if_neq(quality.msaa);
+
+    match fxaa {
+        Some(mut fxaa) if quality.fxaa => {
+            if fxaa.edge_threshold != quality.fxaa_sensitivity {
+                fxaa.edge_threshold = quality.fxaa_sensitivity;
+                fxaa.edge_threshold_min = quality.fxaa_sensitivity;
+            }
+        }
+        Some(_) => {
+            commands.entity(entity).remove::<Fxaa>();
+        }
+        None if quality.fxaa => {
+            commands.entity(entity).insert(Fxaa {
+                enabled: true,
+                edge_threshold: quality.fxaa_sensitivity,
+                edge_threshold_min: quality.fxaa_sensitivity,
+            });
+        }
+        None => {}
+    }
+
+    match smaa {
+        Some(mut smaa) if quality.smaa => {
+            if smaa.preset != quality.smaa_preset {
+                smaa.preset = quality.smaa_preset;
+            }
+        }
+        Some(_) => {
+            commands.entity(entity).remove::<Smaa>();
+        }
+        None if quality.smaa => {
+            commands.entity(entity).insert(Smaa {
+                preset: quality.smaa_preset,
+            });
+        }
+        None => {}
+    }
+
+    let present_mode = if quality.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+
+    if quality.render_scale == 1.0 {
+        if !matches!(camera.target, RenderTarget::Window(_)) {
+            camera.target = RenderTarget::default();
+        }
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+
+    let size = (window.physical_size().as_vec2() * quality.render_scale)
+        .round()
+        .max(Vec2::ONE)
+        .as_uvec2();
+    let scale_factor =
+        FloatOrd(window.scale_factor() * size.x as f32 / window.physical_width().max(1) as f32);
+    let up_to_date = matches!(
+        &camera.target,
+        RenderTarget::Image(target) if target.scale_factor == scale_factor
+            && images.get(&target.handle).is_some_and(|image| image.size() == size)
+    );
+    if !up_to_date {
+        let image = images.add(Image::new_target_texture(
+            size.x,
+            size.y,
+            TextureFormat::bevy_default(),
+        ));
+        camera.target = RenderTarget::Image(ImageRenderTarget {
+            handle: image.clone(),
+            scale_factor,
+        });
+        sprite.image = image;
+    }
+    if sprite.custom_size != Some(window.size()) {
+        sprite.custom_size = Some(window.size());
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+}
+
+pub fn quality_ui(ui: &mut egui::Ui, quality: &mut RenderQuality) {
+    ui.label("Anti-aliasing:");
+    ui.horizontal(|ui| {
+        ui.label("MSAA");
+        for (msaa, name) in MSAA {
+            ui.selectable_value(&mut quality.msaa, msaa, name);
+        }
+        ui.separator();
+        ui.checkbox(&mut quality.fxaa, "FXAA");
+        ui.add_enabled_ui(quality.fxaa, |ui| {
+            let selected = FXAA_SENSITIVITIES
+                .iter()
+                .find(|(sensitivity, _)| *sensitivity == quality.fxaa_sensitivity)
+                .map_or("", |(_, name)| name);
+            egui::ComboBox::from_id_salt("fxaa_sensitivity")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (sensitivity, name) in FXAA_SENSITIVITIES {
+                        ui.selectable_value(&mut quality.fxaa_sensitivity, sensitivity, name);
+                    }
+                });
+        });
+        ui.separator();
+        ui.checkbox(&mut quality.smaa, "SMAA");
+        ui.add_enabled_ui(quality.smaa, |ui| {
+            let selected = SMAA_PRESETS
+                .iter()
+                .find(|(preset, _)| *preset == quality.smaa_preset)
+                .map_or("", |(_, name)| name);
+            egui::ComboBox::from_id_salt("smaa_preset")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (preset, name) in SMAA_PRESETS {
+                        ui.selectable_value(&mut quality.smaa_preset, preset, name);
+                    }
+                });
+        });
+    });
+    ui.end_row();
+
+    ui.label("Render scale:");
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut quality.render_scale)
+                .range(consts::MIN_RENDER_SCALE..=consts::MAX_RENDER_SCALE)
+                .speed(0.01)
+                .fixed_decimals(2),
+        )
+        .on_hover_text("Resolution of the shapes relative to the window");
+        for scale in [0.5, 1.0, 2.0] {
+            if ui.button(format!("{scale}x")).clicked() {
+                quality.render_scale = scale;
+            }
+        }
+        ui.separator();
+        ui.checkbox(&mut quality.vsync, "VSync");
+    });
+    ui.end_row();
+}
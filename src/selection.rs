@@ -0,0 +1,629 @@
+use std::f32::consts::TAU;
+
+use bevy::{picking::hover::PickingInteraction, prelude::*, window::PrimaryWindow};
+use bevy_egui::{
+    EguiContexts,
+    egui::{self, Widget},
+};
+use bevy_smud::{BlendMode, SmudShape};
+
+use crate::{
+    ShapeCamera, consts,
+    cursor::{UserInput, Viewport},
+    draw::DrawTool,
+    shape::{ShapeTransforms, local_transform, set_local_transform, shape_bounds, update_shape},
+    snap::{alt_pressed, snap, snap_offset},
+    state::{GlobalState, SelectedTab, ShapeId, ShapeState},
+    util::color_edit_button,
+};
+
+/// Selects several shapes and transforms them as a group.
+#[derive(Resource)]
+pub struct SelectionTool {
+    /// Viewport position where the box select started and the shape that was pressed there.
+    box_start: Option<(Vec2, Option<ShapeId>)>,
+    /// Viewport position of the last click, clicking there again selects the next shape below.
+    last_click: Option<Vec2>,
+    /// The selected shapes are moved while the select button is held after pressing a shape.
+    moving: Option<Move>,
+    /// World coordinates of the shape edges or centers that the moved shapes snapped to.
+    guides: (Option<f32>, Option<f32>),
+    /// Rotation applied to groups so far, changing it rotates the group by the difference.
+    rotation: f32,
+    /// Scale applied to groups so far, changing it scales the group by the ratio.
+    scale: f32,
+}
+
+impl Default for SelectionTool {
+    fn default() -> Self {
+        Self {
+            box_start: None,
+            last_click: None,
+            moving: None,
+            guides: (None, None),
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+struct Move {
+    /// Viewport position where the shape was pressed.
+    press: Vec2,
+    /// The shape that is selected if the button is released without moving.
+    click: Option<ShapeId>,
+    /// Set once the cursor moved far enough.
+    start: Option<MoveStart>,
+}
+
+struct MoveStart {
+    /// World position of the cursor when the move started.
+    cursor: Vec2,
+    /// World positions of the moved shapes, the shown one comes first.
+    origins: Vec<(ShapeId, Vec3)>,
+    /// Bounds of the moved shapes including their children.
+    bounds: Rect,
+    /// Centers and bounds edges of the other shapes along x and y.
+    targets: (Vec<f32>, Vec<f32>),
+}
+
+fn modifier_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ])
+}
+
+/// Selects shapes with the select button of the input scheme. Clicking the same spot again
+/// selects the next shape below the cursor. Holding shift or ctrl toggles the clicked shape, or
+/// adds all shapes whose origin lies within the dragged box. Dragging a shape moves the selected
+/// shapes, see [`move_input`].
+pub fn selection_input(
+    mut selection_tool: ResMut<SelectionTool>,
+    mut global_state: ResMut<GlobalState>,
+    draw_tool: Res<DrawTool>,
+    input: UserInput,
+    viewport: Viewport,
+    picking_query: Query<(&ShapeState, &PickingInteraction)>,
+    shape_query: Query<&ShapeState>,
+) {
+    let UserInput {
+        mouse,
+        keys,
+        egui_wants_input,
+    } = input;
+    let Viewport { window, camera } = viewport;
+    let cursor = window.cursor_position();
+    let button = global_state.input_scheme.select_button();
+    // The left button might be used for drawing or panning instead
+    let blocked =
+        button == MouseButton::Left && (draw_tool.shape.is_some() || keys.pressed(KeyCode::Space));
+
+    if mouse.just_pressed(button) && !blocked && !egui_wants_input.is_pointer_over_area() {
+        let transforms = ShapeTransforms::new(&shape_query);
+        let mut pressed: Vec<_> = picking_query
+            .iter()
+            .filter(|(shape_state, interaction)| {
+                **interaction == PickingInteraction::Pressed
+                    && shape_state.visible
+                    && !shape_state.locked
+            })
+            .map(|(shape_state, _)| {
+                let z = transforms.world(shape_state.id).translation().z;
+                (shape_state.id, z)
+            })
+            .collect();
+        // The topmost shape comes first
+        pressed.sort_by(|(a, a_z), (b, b_z)| b_z.total_cmp(a_z).then(b.cmp(a)));
+        let pressed: Vec<_> = pressed.into_iter().map(|(id, _)| id).collect();
+
+        // Clicking the same spot again cycles through the shapes below the shown one
+        let cycle = selection_tool
+            .last_click
+            .zip(cursor)
+            .is_some_and(|(last, cursor)| last.distance(cursor) < consts::BOX_SELECT_MIN_DISTANCE);
+        let shown = pressed
+            .iter()
+            .position(|&id| global_state.selected_tab() == SelectedTab::Shape(id));
+        let clicked = match shown {
+            Some(index) if cycle => pressed.get((index + 1) % pressed.len()),
+            _ => pressed.first(),
+        }
+        .copied();
+        selection_tool.last_click = cursor;
+
+        if modifier_pressed(&keys) {
+            selection_tool.box_start = cursor.map(|cursor| (cursor, pressed.first().copied()));
+        } else if let Some(id) = clicked {
+            // Pressing a selected shape keeps the selection, so the whole group can be moved
+            let click = if pressed.iter().any(|&id| global_state.is_selected(id)) {
+                Some(id)
+            } else {
+                global_state.select_tab(SelectedTab::Shape(id));
+                None
+            };
+            selection_tool.moving = cursor.map(|press| Move {
+                press,
+                click,
+                start: None,
+            });
+        }
+    }
+
+    if mouse.just_released(button)
+        && let Some(moving) = selection_tool.moving.take()
+        && moving.start.is_none()
+        && let Some(id) = moving.click
+    {
+        global_state.select_tab(SelectedTab::Shape(id));
+    }
+
+    if mouse.just_released(button)
+        && let Some((start, pressed)) = selection_tool.box_start.take()
+    {
+        let end = cursor.unwrap_or(start);
+        if start.distance(end) < consts::BOX_SELECT_MIN_DISTANCE {
+            if let Some(id) = pressed {
+                global_state.toggle_selection(id);
+            }
+            return;
+        }
+
+        let (camera, camera_transform) = *camera;
+        let (Ok(start), Ok(end)) = (
+            camera.viewport_to_world_2d(camera_transform, start),
+            camera.viewport_to_world_2d(camera_transform, end),
+        ) else {
+            return;
+        };
+        let area = Rect::from_corners(start, end);
+        let transforms = ShapeTransforms::new(&shape_query);
+        let mut shapes: Vec<_> = shape_query
+            .iter()
+            .filter(|shape_state| {
+                shape_state.visible
+                    && !shape_state.locked
+                    && area.contains(transforms.world(shape_state.id).translation().xy())
+            })
+            .map(|shape_state| shape_state.id)
+            .collect();
+        shapes.sort();
+        global_state.extend_selection(shapes);
+    }
+}
+
+/// Moves the selected shapes with the cursor while the select button is held. Snapping moves
+/// them to the grid or the centers and bounds edges of other shapes.
+pub fn move_input(
+    mut selection_tool: ResMut<SelectionTool>,
+    global_state: Res<GlobalState>,
+    input: UserInput,
+    viewport: Viewport,
+    mut shape_query: Query<(&mut Transform, &mut ShapeState)>,
+) {
+    let UserInput { mouse, keys, .. } = input;
+    let Viewport { window, camera } = viewport;
+    let selection_tool = &mut *selection_tool;
+    selection_tool.guides = (None, None);
+    if !mouse.pressed(global_state.input_scheme.select_button()) {
+        selection_tool.moving = None;
+    }
+    let (Some(moving), Some(cursor)) = (&mut selection_tool.moving, window.cursor_position())
+    else {
+        return;
+    };
+    if moving.start.is_none() && moving.press.distance(cursor) < consts::BOX_SELECT_MIN_DISTANCE {
+        return;
+    }
+
+    let (camera, camera_transform) = *camera;
+    let (Ok(press), Ok(world_cursor), Ok(next_pixel)) = (
+        camera.viewport_to_world_2d(camera_transform, moving.press),
+        camera.viewport_to_world_2d(camera_transform, cursor),
+        camera.viewport_to_world_2d(camera_transform, cursor + Vec2::X),
+    ) else {
+        return;
+    };
+    let transforms = ShapeTransforms::new(shape_query.iter().map(|(_, shape_state)| shape_state));
+    let moved = |id: ShapeId| {
+        global_state.is_selected(id)
+            || transforms
+                .ancestors(id)
+                .any(|id| global_state.is_selected(id))
+    };
+
+    let start = moving.start.get_or_insert_with(|| {
+        let mut origins = Vec::new();
+        let mut bounds = Rect::EMPTY;
+        let mut targets = (Vec::new(), Vec::new());
+        for (_, shape_state) in &shape_query {
+            let world = transforms.world(shape_state.id);
+            let shape_bounds = shape_bounds(shape_state, &world);
+            if moved(shape_state.id) {
+                bounds = bounds.union(shape_bounds);
+                // Children move along with their parents
+                if global_state.is_selected(shape_state.id)
+                    && !transforms
+                        .ancestors(shape_state.id)
+                        .any(|id| global_state.is_selected(id))
+                    && !shape_state.locked
+                {
+                    origins.push((shape_state.id, world.translation()));
+                }
+            } else if shape_state.visible {
+                let center = world.translation();
+                targets
+                    .0
+                    .extend([shape_bounds.min.x, center.x, shape_bounds.max.x]);
+                targets
+                    .1
+                    .extend([shape_bounds.min.y, center.y, shape_bounds.max.y]);
+            }
+        }
+        origins.sort_by_key(|(id, _)| global_state.selected_tab() != SelectedTab::Shape(*id));
+        MoveStart {
+            cursor: press,
+            origins,
+            bounds,
+            targets,
+        }
+    });
+    let Some(&(_, primary)) = start.origins.first() else {
+        return;
+    };
+
+    let mut delta = world_cursor - start.cursor;
+    let steps = global_state.snapping.steps(alt_pressed(&keys));
+    let max_distance = consts::SHAPE_SNAP_DISTANCE * world_cursor.distance(next_pixel);
+    let axes = [
+        (
+            0,
+            start.bounds.min.x,
+            start.bounds.max.x,
+            primary.x,
+            &start.targets.0,
+        ),
+        (
+            1,
+            start.bounds.min.y,
+            start.bounds.max.y,
+            primary.y,
+            &start.targets.1,
+        ),
+    ];
+    for (axis, min, max, origin, targets) in axes {
+        let features = [min, (min + max) / 2.0, max, origin].map(|feature| feature + delta[axis]);
+        let snapped = steps
+            .to_shapes
+            .then(|| snap_offset(&features, targets, max_distance))
+            .flatten();
+        // Snapping to shapes takes precedence over the grid
+        let guide = match snapped {
+            Some((offset, target)) => {
+                delta[axis] += offset;
+                Some(target)
+            }
+            None => {
+                delta[axis] = snap(origin + delta[axis], steps.position) - origin;
+                None
+            }
+        };
+        if axis == 0 {
+            selection_tool.guides.0 = guide;
+        } else {
+            selection_tool.guides.1 = guide;
+        }
+    }
+
+    for (mut transform, mut shape_state) in &mut shape_query {
+        let Some(&(_, origin)) = start.origins.iter().find(|(id, _)| *id == shape_state.id) else {
+            continue;
+        };
+        let world = origin + delta.extend(0.0);
+        let position = transforms
+            .parent_world(shape_state.id)
+            .affine()
+            .inverse()
+            .transform_point3(world);
+        if position != shape_state.position {
+            shape_state.position = position;
+            *transform = local_transform(&shape_state);
+        }
+    }
+}
+
+/// Shows the dragged box and, if several shapes are selected, their bounds in the viewport.
+pub fn selection_overlay(
+    mut contexts: EguiContexts,
+    selection_tool: Res<SelectionTool>,
+    global_state: Res<GlobalState>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<ShapeCamera>>,
+    shape_query: Query<&ShapeState>,
+) -> Result {
+    let ctx = contexts.ctx_mut()?;
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("selection"),
+    ));
+    let color = ctx.style().visuals.selection.stroke.color;
+
+    if let Some((start, _)) = selection_tool.box_start
+        && let Some(cursor) = window.cursor_position()
+    {
+        let rect = egui::Rect::from_two_pos(pos2(start), pos2(cursor));
+        painter.rect(
+            rect,
+            0.0,
+            color.gamma_multiply(0.1),
+            egui::Stroke::new(1.0, color),
+            egui::StrokeKind::Middle,
+        );
+    }
+
+    let (camera, camera_transform) = *camera;
+    let guide_stroke = egui::Stroke::new(1.0, color);
+    if let Some(x) = selection_tool.guides.0
+        && let Ok(position) = camera.world_to_viewport(camera_transform, Vec3::new(x, 0.0, 0.0))
+    {
+        painter.vline(position.x, 0.0..=window.height(), guide_stroke);
+    }
+    if let Some(y) = selection_tool.guides.1
+        && let Ok(position) = camera.world_to_viewport(camera_transform, Vec3::new(0.0, y, 0.0))
+    {
+        painter.hline(0.0..=window.width(), position.y, guide_stroke);
+    }
+
+    if global_state.selection().len() > 1 {
+        let stroke = egui::Stroke::new(1.0, color.gamma_multiply(0.5));
+        let transforms = ShapeTransforms::new(&shape_query);
+        for shape_state in &shape_query {
+            if !global_state.is_selected(shape_state.id) {
+                continue;
+            }
+            let bounds = shape_bounds(shape_state, &transforms.world(shape_state.id));
+            if let (Ok(min), Ok(max)) = (
+                camera.world_to_viewport(camera_transform, bounds.min.extend(0.0)),
+                camera.world_to_viewport(camera_transform, bounds.max.extend(0.0)),
+            ) {
+                let rect = egui::Rect::from_two_pos(pos2(min), pos2(max));
+                painter.rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Middle);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn pos2(position: Vec2) -> egui::Pos2 {
+    egui::pos2(position.x, position.y)
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    CenterX,
+    Right,
+    Bottom,
+    CenterY,
+    Top,
+}
+
+/// Shows the UI for changing all selected shapes at once.
+pub fn group_settings(
+    ui: &mut egui::Ui,
+    global_state: &mut GlobalState,
+    selection_tool: &mut SelectionTool,
+    shaders: &mut Assets<Shader>,
+    shape_query: &mut Query<(Entity, &mut Transform, &mut SmudShape, &mut ShapeState)>,
+) {
+    let transforms =
+        ShapeTransforms::new(shape_query.iter().map(|(_, _, _, shape_state)| shape_state));
+    let mut shapes: Vec<_> = shape_query
+        .iter_mut()
+        .filter(|(_, _, _, shape_state)| global_state.is_selected(shape_state.id))
+        .map(|(_, transform, shape, shape_state)| (transform, shape, shape_state))
+        .collect();
+    // The settings of the shown shape are used as the values for the group
+    let Some(shown) = shapes.iter().position(|(_, _, shape_state)| {
+        global_state.selected_tab() == SelectedTab::Shape(shape_state.id)
+    }) else {
+        return;
+    };
+
+    // Shapes are transformed in world space, children of selected shapes move with their parent
+    // and locked shapes stay in place
+    let mut worlds: Vec<_> = shapes
+        .iter()
+        .map(|(_, _, shape_state)| {
+            let id = shape_state.id;
+            (!transforms
+                .ancestors(id)
+                .any(|id| global_state.is_selected(id))
+                && !shape_state.locked)
+                .then(|| transforms.world(id).compute_transform())
+        })
+        .collect();
+    let center = worlds
+        .iter()
+        .flatten()
+        .fold(Rect::EMPTY, |rect, world| {
+            rect.union_point(world.translation.xy())
+        })
+        .center();
+    let mut new_center = center;
+    let mut rotation = selection_tool.rotation;
+    let mut scale = selection_tool.scale;
+    let mut color = shapes[shown].2.color;
+    let mut blend_mode = shapes[shown].2.blend_mode;
+    let mut align = None;
+    let mut distribute = None;
+
+    ui.heading(format!("Selection ({} shapes)", shapes.len()));
+    egui::Grid::new("grid_group")
+        .num_columns(2)
+        .spacing([40.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Center:");
+            ui.horizontal(|ui| {
+                ui.label("x");
+                egui::DragValue::new(&mut new_center.x).speed(5.0).ui(ui);
+                ui.label("y");
+                egui::DragValue::new(&mut new_center.y).speed(5.0).ui(ui);
+            });
+            ui.end_row();
+
+            ui.label("Rotate:");
+            ui.add(
+                egui::DragValue::new(&mut rotation)
+                    .min_decimals(2)
+                    .speed(TAU / 50.0),
+            )
+            .on_hover_text("Drag to rotate around the center");
+            ui.end_row();
+
+            ui.label("Scale:");
+            ui.add(
+                egui::DragValue::new(&mut scale)
+                    .min_decimals(1)
+                    .speed(1.0 / 100.0)
+                    .range(0.01..=f32::MAX),
+            )
+            .on_hover_text("Drag to scale from the center");
+            ui.end_row();
+
+            ui.label("Color:");
+            color_edit_button(ui, &mut color);
+            ui.end_row();
+
+            ui.label("Blend mode:");
+            ui.horizontal(|ui| {
+                for mode in [BlendMode::Alpha, BlendMode::Additive] {
+                    ui.selectable_value(&mut blend_mode, mode, format!("{mode:?}"));
+                }
+            });
+            ui.end_row();
+
+            ui.label("Align:");
+            ui.horizontal(|ui| {
+                for (label, value) in [
+                    ("Left", Align::Left),
+                    ("Center", Align::CenterX),
+                    ("Right", Align::Right),
+                    ("Bottom", Align::Bottom),
+                    ("Middle", Align::CenterY),
+                    ("Top", Align::Top),
+                ] {
+                    if ui.button(label).clicked() {
+                        align = Some(value);
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("Distribute:");
+            ui.add_enabled_ui(shapes.len() > 2, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Horizontally").clicked() {
+                        distribute = Some(Vec2::X);
+                    }
+                    if ui.button("Vertically").clicked() {
+                        distribute = Some(Vec2::Y);
+                    }
+                });
+            });
+            ui.end_row();
+        });
+
+    // Only the difference to the previous values is applied to the shapes
+    let rotation_delta = rotation - selection_tool.rotation;
+    let scale_factor = scale / selection_tool.scale;
+    selection_tool.rotation = rotation;
+    selection_tool.scale = scale;
+    let color_changed = color != shapes[shown].2.color;
+    let blend_mode_changed = blend_mode != shapes[shown].2.blend_mode;
+    let bounds: Vec<_> = shapes
+        .iter()
+        .map(|(_, _, shape_state)| shape_bounds(shape_state, &transforms.world(shape_state.id)))
+        .collect();
+    let group_bounds = bounds.iter().fold(Rect::EMPTY, |group_bounds, bounds| {
+        group_bounds.union(*bounds)
+    });
+    let mut changed = vec![false; shapes.len()];
+
+    for (i, world) in worlds.iter_mut().enumerate() {
+        let Some(world) = world else {
+            continue;
+        };
+        let offset = world.translation.xy() - center;
+        let position = new_center + Vec2::from_angle(rotation_delta).rotate(offset) * scale_factor;
+        if position != world.translation.xy() || rotation_delta != 0.0 || scale_factor != 1.0 {
+            world.translation = position.extend(world.translation.z);
+            world.rotate_z(rotation_delta);
+            world.scale *= scale_factor;
+            changed[i] = true;
+        }
+
+        if let Some(align) = align {
+            let bounds = bounds[i];
+            let offset = match align {
+                Align::Left => Vec2::X * (group_bounds.min.x - bounds.min.x),
+                Align::CenterX => Vec2::X * (group_bounds.center().x - bounds.center().x),
+                Align::Right => Vec2::X * (group_bounds.max.x - bounds.max.x),
+                Align::Bottom => Vec2::Y * (group_bounds.min.y - bounds.min.y),
+                Align::CenterY => Vec2::Y * (group_bounds.center().y - bounds.center().y),
+                Align::Top => Vec2::Y * (group_bounds.max.y - bounds.max.y),
+            };
+            world.translation += offset.extend(0.0);
+            changed[i] = true;
+        }
+    }
+
+    if let Some(axis) = distribute {
+        // Centers are spaced evenly between the outermost shapes
+        let mut order: Vec<_> = (0..worlds.len()).filter(|&i| worlds[i].is_some()).collect();
+        let coordinate =
+            |world: &Option<Transform>| world.map_or(0.0, |world| world.translation.xy().dot(axis));
+        order.sort_by(|&a, &b| coordinate(&worlds[a]).total_cmp(&coordinate(&worlds[b])));
+        if order.len() > 2 {
+            let first = coordinate(&worlds[order[0]]);
+            let last = coordinate(&worlds[order[order.len() - 1]]);
+            let step = (last - first) / (order.len() - 1) as f32;
+            for (i, &index) in order.iter().enumerate() {
+                let offset = first + step * i as f32 - coordinate(&worlds[index]);
+                if let Some(world) = &mut worlds[index] {
+                    world.translation += (axis * offset).extend(0.0);
+                }
+                changed[index] = true;
+            }
+        }
+    }
+
+    for (i, (transform, shape, shape_state)) in shapes.iter_mut().enumerate() {
+        if let Some(world) = worlds[i]
+            && changed[i]
+        {
+            let parent_world = transforms.parent_world(shape_state.id);
+            set_local_transform(
+                shape_state,
+                GlobalTransform::from(world).reparented_to(&parent_world),
+            );
+        }
+        if color_changed {
+            shape_state.color = color;
+            changed[i] = true;
+        }
+        if blend_mode_changed {
+            shape_state.blend_mode = blend_mode;
+            changed[i] = true;
+        }
+        if changed[i] {
+            update_shape(global_state, shaders, transform, shape, shape_state, false);
+        }
+    }
+}
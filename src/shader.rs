@@ -1,7 +1,20 @@
-use bevy::prelude::*;
+use bevy::{asset::uuid_handle, prelude::*};
 
 use crate::state::GlobalState;
 
+/// Shader with import path `smud_demo::globals` that can be imported by the shader code of shapes.
+const GLOBALS_SHADER_HANDLE: Handle<Shader> = uuid_handle!("3c5c2cb2-5a42-4a43-9d3c-a2f0d6b6f0e1");
+
+pub fn add_globals_shader(shaders: &mut Assets<Shader>) {
+    let shader = Shader::from_wgsl(
+        include_str!("../shaders/globals.wgsl"),
+        "shaders/globals.wgsl",
+    );
+    shaders
+        .insert(&GLOBALS_SHADER_HANDLE, shader)
+        .expect("Inserting with UUID never fails");
+}
+
 pub fn create_shader(
     shaders: &mut Assets<Shader>,
     global_state: &mut GlobalState,
@@ -1,12 +1,13 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
 use bevy_smud::SmudShape;
 
 use crate::{
     consts,
     shader::create_shader,
-    state::{GlobalState, ShaderKind, ShapeState},
+    state::{GlobalState, ShaderKind, ShapeId, ShapeState},
     templates::Templates,
-    util::convert_color,
 };
 
 pub fn add_shape(
@@ -15,11 +16,18 @@ pub fn add_shape(
     state: &mut GlobalState,
     shaders: &mut Assets<Shader>,
 ) {
-    let mut transform = Transform::default();
-    let mut shape = SmudShape::default();
+    let shape_state = new_shape_state(templates, state);
+    spawn_shape(commands, state, shaders, shape_state);
+}
 
-    let shape_state = ShapeState {
+/// Returns the state of a new shape with default settings, which can be adjusted before it's
+/// spawned with [`spawn_shape`].
+pub fn new_shape_state(templates: &Templates, state: &mut GlobalState) -> ShapeState {
+    ShapeState {
         id: state.create_shape(),
+        name: String::new(),
+        visible: true,
+        locked: false,
         position: consts::DEFAULT_SHAPE_POSITION,
         rotation: consts::DEFAULT_SHAPE_ROTATION,
         scale: consts::DEFAULT_SHAPE_SCALE,
@@ -36,7 +44,22 @@ pub fn add_shape(
             .unwrap_or_default(),
         params: consts::DEFAULT_SHAPE_PARAMS,
         blend_mode: consts::DEFAULT_SHAPE_BLEND_MODE,
-    };
+        expressions: default(),
+        drawing: None,
+        text: None,
+        parent: None,
+        show_distance: false,
+    }
+}
+
+pub fn spawn_shape(
+    commands: &mut Commands,
+    state: &mut GlobalState,
+    shaders: &mut Assets<Shader>,
+    shape_state: ShapeState,
+) {
+    let mut transform = Transform::default();
+    let mut shape = SmudShape::default();
 
     update_shape(
         state,
@@ -50,17 +73,26 @@ pub fn add_shape(
     commands.spawn((transform, shape, shape_state));
 }
 
+/// Spawns a copy of the shape, which is attached to `parent` instead of the original's parent.
 pub fn clone_shape(
     commands: &mut Commands,
     state: &mut GlobalState,
     transform: &Transform,
     shape: &SmudShape,
     shape_state: &ShapeState,
-) {
+    parent: Option<(ShapeId, Entity)>,
+) -> (ShapeId, Entity) {
     let mut shape_state = shape_state.clone();
-    shape_state.id = state.create_shape();
+    let id = state.create_shape();
+    shape_state.id = id;
+    shape_state.parent = parent.map(|(id, _)| id);
 
-    commands.spawn((*transform, shape.clone(), shape_state));
+    let mut entity = commands.spawn((*transform, shape.clone(), shape_state));
+    // The transform is relative to the parent from the first frame on
+    if let Some((_, parent)) = parent {
+        entity.insert(ChildOf(parent));
+    }
+    (id, entity.id())
 }
 
 pub fn update_shape(
@@ -71,17 +103,103 @@ pub fn update_shape(
     shape_state: &ShapeState,
     compile_shader: bool,
 ) {
-    *transform = Transform::from_translation(shape_state.position)
-        .with_rotation(Quat::from_rotation_z(shape_state.rotation))
-        .with_scale(Vec3::splat(shape_state.scale));
+    *transform = local_transform(shape_state);
 
-    shape.color = convert_color(shape_state.color);
+    shape.color = shape_state.color.into();
     shape.bounds = Rectangle::from_length(shape_state.bounds_length);
     shape.params = shape_state.params;
     shape.blend_mode = shape_state.blend_mode;
 
     if compile_shader {
         shape.sdf = create_shader(shaders, global_state, &shape_state.sdf_code);
-        shape.fill = create_shader(shaders, global_state, &shape_state.fill_code);
+        let fill_code = global_state
+            .distance_view
+            .fill_code(shape_state)
+            .into_owned();
+        shape.fill = create_shader(shaders, global_state, &fill_code);
     }
 }
+
+/// Returns the transform of the shape relative to its parent.
+pub fn local_transform(shape_state: &ShapeState) -> Transform {
+    Transform::from_translation(shape_state.position)
+        .with_rotation(Quat::from_rotation_z(shape_state.rotation))
+        .with_scale(Vec3::splat(shape_state.scale))
+}
+
+/// Sets position, rotation and scale from a transform relative to the parent of the shape.
+pub fn set_local_transform(shape_state: &mut ShapeState, transform: Transform) {
+    shape_state.position = transform.translation;
+    shape_state.rotation = transform.rotation.to_euler(EulerRot::ZYX).0;
+    shape_state.scale = transform.scale.x;
+}
+
+/// The hierarchy of shapes for converting between the local spaces of shapes and world space.
+pub struct ShapeTransforms(HashMap<ShapeId, (Option<ShapeId>, Transform)>);
+
+impl ShapeTransforms {
+    pub fn new<'a>(shapes: impl IntoIterator<Item = &'a ShapeState>) -> Self {
+        Self(
+            shapes
+                .into_iter()
+                .map(|shape_state| {
+                    (
+                        shape_state.id,
+                        (shape_state.parent, local_transform(shape_state)),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the ancestors of the shape, starting with its parent.
+    pub fn ancestors(&self, shape: ShapeId) -> impl Iterator<Item = ShapeId> + '_ {
+        let parent = |id| self.0.get(&id).and_then(|(parent, _)| *parent);
+        // Limited in case the hierarchy contains a cycle
+        std::iter::successors(parent(shape), move |&id| parent(id)).take(self.0.len())
+    }
+
+    /// Returns the transform of the shape in world space.
+    pub fn world(&self, shape: ShapeId) -> GlobalTransform {
+        let local = self
+            .0
+            .get(&shape)
+            .map_or(Transform::IDENTITY, |(_, local)| *local);
+        self.parent_world(shape).mul_transform(local)
+    }
+
+    /// Returns the transform of the parent of the shape in world space.
+    pub fn parent_world(&self, shape: ShapeId) -> GlobalTransform {
+        self.ancestors(shape)
+            .filter_map(|id| self.0.get(&id))
+            .fold(Transform::IDENTITY, |world, (_, local)| *local * world)
+            .into()
+    }
+
+    /// Returns the shapes in the order they are drawn, from back to front. Shapes at the same
+    /// depth are ordered by id.
+    pub fn draw_order(&self) -> Vec<ShapeId> {
+        let mut shapes: Vec<_> = self
+            .0
+            .keys()
+            .map(|&id| (id, self.world(id).translation().z))
+            .collect();
+        shapes.sort_by(|(a, a_z), (b, b_z)| a_z.total_cmp(b_z).then(a.cmp(b)));
+        shapes.into_iter().map(|(id, _)| id).collect()
+    }
+}
+
+/// Returns the axis-aligned bounds of the shape in world space.
+pub fn shape_bounds(shape_state: &ShapeState, world: &GlobalTransform) -> Rect {
+    let half_length = shape_state.bounds_length / 2.0;
+    let corners = [
+        Vec2::new(-half_length, -half_length),
+        Vec2::new(half_length, -half_length),
+        Vec2::new(half_length, half_length),
+        Vec2::new(-half_length, half_length),
+    ];
+    corners
+        .into_iter()
+        .map(|corner| world.transform_point(corner.extend(0.0)).xy())
+        .fold(Rect::EMPTY, |bounds, corner| bounds.union_point(corner))
+}
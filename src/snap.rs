@@ -0,0 +1,147 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::consts;
+
+/// Rounds values to steps while editing them, holding Alt temporarily disables it.
+pub struct Snapping {
+    pub enabled: bool,
+    /// Grid size for positions.
+    pub position_step: f32,
+    /// Angle increment for rotations in radians.
+    pub rotation_step: f32,
+    pub scale_step: f32,
+    /// Whether shapes moved in the viewport snap to the centers and bounds edges of other shapes.
+    pub to_shapes: bool,
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            position_step: consts::DEFAULT_POSITION_SNAP,
+            rotation_step: consts::DEFAULT_ROTATION_SNAP,
+            scale_step: consts::DEFAULT_SCALE_SNAP,
+            to_shapes: true,
+        }
+    }
+}
+
+/// The steps that apply at the moment, all `None` if snapping is disabled.
+#[derive(Clone, Copy, Default)]
+pub struct SnapSteps {
+    pub position: Option<f32>,
+    pub rotation: Option<f32>,
+    pub scale: Option<f32>,
+    pub to_shapes: bool,
+}
+
+impl Snapping {
+    pub fn steps(&self, alt_pressed: bool) -> SnapSteps {
+        if !self.enabled || alt_pressed {
+            return SnapSteps::default();
+        }
+        let step = |step: f32| (step > 0.0).then_some(step);
+        SnapSteps {
+            position: step(self.position_step),
+            rotation: step(self.rotation_step),
+            scale: step(self.scale_step),
+            to_shapes: self.to_shapes,
+        }
+    }
+}
+
+/// Rounds the value to the closest multiple of the step.
+pub fn snap(value: f32, step: Option<f32>) -> f32 {
+    match step {
+        Some(step) => (value / step).round() * step,
+        None => value,
+    }
+}
+
+/// Adds the drag value and rounds its result to the step. While dragging, the unrounded value is
+/// kept, so slow drags still add up to a step.
+pub fn snapped_drag_value(
+    ui: &mut egui::Ui,
+    value: &mut f32,
+    step: Option<f32>,
+    drag_value: impl FnOnce(&mut f32) -> egui::DragValue<'_>,
+) -> egui::Response {
+    if step.is_none() {
+        return ui.add(drag_value(value));
+    }
+
+    let id = ui.next_auto_id();
+    let mut unsnapped = ui
+        .data(|data| data.get_temp::<f32>(id))
+        .filter(|&unsnapped| snap(unsnapped, step) == *value)
+        .unwrap_or(*value);
+    let response = ui.add(drag_value(&mut unsnapped));
+    if response.changed() {
+        *value = snap(unsnapped, step);
+    }
+    ui.data_mut(|data| {
+        if response.dragged() {
+            data.insert_temp(id, unsnapped);
+        } else {
+            data.remove::<f32>(id);
+        }
+    });
+    response
+}
+
+/// Returns the offset that moves one of the `features` onto the closest of the `targets`, if it's
+/// at most `max_distance` away.
+pub fn snap_offset(features: &[f32], targets: &[f32], max_distance: f32) -> Option<(f32, f32)> {
+    features
+        .iter()
+        .flat_map(|&feature| {
+            targets
+                .iter()
+                .map(move |&target| (target - feature, target))
+        })
+        .filter(|(offset, _)| offset.abs() <= max_distance)
+        .min_by(|(a, _), (b, _)| a.abs().total_cmp(&b.abs()))
+}
+
+pub fn snapping_ui(ui: &mut egui::Ui, snapping: &mut Snapping) {
+    ui.label("Snapping:");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut snapping.enabled, "Enabled")
+            .on_hover_text("Hold Alt to temporarily disable snapping");
+        ui.add_enabled_ui(snapping.enabled, |ui| {
+            ui.checkbox(&mut snapping.to_shapes, "To shapes")
+                .on_hover_text("Snap moved shapes to the centers and bounds edges of other shapes");
+        });
+    });
+    ui.end_row();
+
+    ui.label("Snap steps:");
+    ui.add_enabled_ui(snapping.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("position");
+            ui.add(
+                egui::DragValue::new(&mut snapping.position_step)
+                    .range(0.0..=f32::MAX)
+                    .speed(1.0),
+            );
+            ui.label("angle");
+            ui.drag_angle(&mut snapping.rotation_step);
+            snapping.rotation_step = snapping.rotation_step.clamp(0.0, TAU);
+            ui.label("scale");
+            ui.add(
+                egui::DragValue::new(&mut snapping.scale_step)
+                    .range(0.0..=f32::MAX)
+                    .speed(0.01),
+            );
+        });
+    });
+    ui.end_row();
+}
+
+/// Returns whether Alt is held, which disables snapping.
+pub fn alt_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+}
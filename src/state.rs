@@ -1,31 +1,69 @@
-use std::fmt::Display;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
 
 use bevy::prelude::*;
 use bevy_egui::egui;
 use bevy_smud::BlendMode;
 
-use crate::consts;
+use crate::{
+    background::BackgroundSettings, consts, draw::Drawing, expr::Expression, grid::GridSettings,
+    post_process::PostProcessSettings, quality::RenderQuality, snap::Snapping, text::Text,
+};
 
 type ShaderId = u32;
-type ShapeId = u32;
+pub type ShapeId = u32;
 
 #[derive(Resource)]
 pub struct GlobalState {
     pub background_color: egui::Color32,
+    pub background: BackgroundSettings,
     next_shader_id: ShapeId,
     next_shape_id: ShapeId,
-    pub selected_tab: SelectedTab,
+    selected_tab: SelectedTab,
+    /// The shapes that group operations apply to, includes the shape of the selected tab.
+    selection: BTreeSet<ShapeId>,
     scroll_to: Option<ShapeId>,
+    /// Shapes whose children are hidden in the outliner.
+    collapsed: BTreeSet<ShapeId>,
+    /// Width of the side panel in logical pixels, the shapes are visible in the remaining space.
+    pub side_panel_width: f32,
+    /// How shapes with the same parent are ordered in the outliner.
+    pub shape_order: ShapeOrder,
+    /// Mouse buttons for selecting shapes and panning the viewport.
+    pub input_scheme: InputScheme,
+    pub picking_mode: PickingMode,
+    /// Maximum distance in logical pixels outside of the shape for [`PickingMode::Sdf`].
+    pub picking_threshold: f32,
+    pub grid: GridSettings,
+    pub snapping: Snapping,
+    pub distance_view: DistanceView,
+    pub post_process: PostProcessSettings,
+    pub quality: RenderQuality,
 }
 
 impl Default for GlobalState {
     fn default() -> Self {
         Self {
             background_color: consts::DEFAULT_BACKGROUND_COLOR,
+            background: BackgroundSettings::default(),
             next_shader_id: 0,
             next_shape_id: 0,
             selected_tab: SelectedTab::Global,
+            selection: BTreeSet::new(),
             scroll_to: None,
+            collapsed: BTreeSet::new(),
+            side_panel_width: consts::SIDE_PANEL_WIDTH,
+            shape_order: ShapeOrder::Id,
+            input_scheme: InputScheme::LeftSelects,
+            picking_mode: PickingMode::Bounds,
+            picking_threshold: consts::DEFAULT_PICKING_THRESHOLD,
+            grid: GridSettings::default(),
+            snapping: Snapping::default(),
+            distance_view: DistanceView::default(),
+            post_process: PostProcessSettings::default(),
+            quality: RenderQuality::default(),
         }
     }
 }
@@ -44,13 +82,65 @@ impl GlobalState {
         id
     }
 
+    pub fn selected_tab(&self) -> SelectedTab {
+        self.selected_tab
+    }
+
+    /// Selects the tab, the selection only contains its shape afterwards.
     pub fn select_tab(&mut self, tab: SelectedTab) {
         self.selected_tab = tab;
+        self.selection.clear();
         if let SelectedTab::Shape(id) = tab {
+            self.selection.insert(id);
             self.scroll_to = Some(id);
         }
     }
 
+    pub fn selection(&self) -> &BTreeSet<ShapeId> {
+        &self.selection
+    }
+
+    pub fn is_selected(&self, shape: ShapeId) -> bool {
+        self.selection.contains(&shape)
+    }
+
+    /// Adds the shapes to the selection, the first one is shown if no shape was selected.
+    pub fn extend_selection(&mut self, shapes: impl IntoIterator<Item = ShapeId>) {
+        for shape in shapes {
+            if self.selection.is_empty() {
+                self.selected_tab = SelectedTab::Shape(shape);
+                self.scroll_to = Some(shape);
+            }
+            self.selection.insert(shape);
+        }
+    }
+
+    /// Adds the shape to the selection and shows it, or removes it if it was already selected.
+    pub fn toggle_selection(&mut self, shape: ShapeId) {
+        if self.selection.insert(shape) {
+            self.selected_tab = SelectedTab::Shape(shape);
+            self.scroll_to = Some(shape);
+        } else {
+            self.deselect(shape);
+        }
+    }
+
+    /// Removes the shape from the selection, another selected shape is shown instead.
+    pub fn deselect(&mut self, shape: ShapeId) {
+        self.selection.remove(&shape);
+        if self.selected_tab == SelectedTab::Shape(shape) {
+            self.selected_tab = self
+                .selection
+                .last()
+                .map_or(SelectedTab::Global, |&id| SelectedTab::Shape(id));
+        }
+    }
+
+    /// Returns the shape that will be scrolled to.
+    pub fn pending_scroll_to(&self) -> Option<ShapeId> {
+        self.scroll_to
+    }
+
     pub fn check_scroll_to(&mut self, shape: ShapeId) -> bool {
         if self.scroll_to == Some(shape) {
             self.scroll_to = None;
@@ -59,6 +149,85 @@ impl GlobalState {
             false
         }
     }
+
+    pub fn is_collapsed(&self, shape: ShapeId) -> bool {
+        self.collapsed.contains(&shape)
+    }
+
+    pub fn set_collapsed(&mut self, shape: ShapeId, collapsed: bool) {
+        if collapsed {
+            self.collapsed.insert(shape);
+        } else {
+            self.collapsed.remove(&shape);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeOrder {
+    /// Oldest shapes first.
+    Id,
+    /// Front shapes first.
+    Layer,
+}
+
+/// How the viewport decides which shapes are under the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PickingMode {
+    /// Shapes are hit anywhere within their bounds.
+    Bounds,
+    /// Shapes are hit where their SDF, evaluated on the CPU, is below the picking threshold,
+    /// i.e. inside of the shape or close to it.
+    Sdf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputScheme {
+    /// The left button selects, the middle button or space with the left button pans.
+    LeftSelects,
+    /// The right button selects, the left button pans.
+    RightSelects,
+}
+
+impl InputScheme {
+    pub const ALL: [InputScheme; 2] = [InputScheme::LeftSelects, InputScheme::RightSelects];
+
+    /// Name used for storing the scheme as a preference.
+    pub fn name(self) -> &'static str {
+        match self {
+            InputScheme::LeftSelects => "left_selects",
+            InputScheme::RightSelects => "right_selects",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scheme| scheme.name() == name)
+    }
+
+    pub fn select_button(self) -> MouseButton {
+        match self {
+            InputScheme::LeftSelects => MouseButton::Left,
+            InputScheme::RightSelects => MouseButton::Right,
+        }
+    }
+
+    /// Returns the buttons that pan the viewport, depending on whether space is held.
+    pub fn pan_buttons(self, space_pressed: bool) -> Vec<MouseButton> {
+        match self {
+            InputScheme::LeftSelects if space_pressed => vec![MouseButton::Left],
+            InputScheme::LeftSelects => vec![MouseButton::Middle],
+            InputScheme::RightSelects => vec![MouseButton::Left],
+        }
+    }
+}
+
+impl Display for InputScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputScheme::LeftSelects => write!(f, "Left click selects"),
+            InputScheme::RightSelects => write!(f, "Right click selects"),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -70,16 +239,133 @@ pub enum SelectedTab {
 #[derive(Clone, Component)]
 pub struct ShapeState {
     pub id: u32,
+    /// Shown instead of the id if it's not empty.
+    pub name: String,
+    pub visible: bool,
+    /// Locked shapes can't be picked or edited in the viewport.
+    pub locked: bool,
     pub position: Vec3,
     pub rotation: f32,
     pub scale: f32,
-    pub color: egui::Color32,
+    /// Unmultiplied, so channels driven by expressions don't affect each other.
+    pub color: Srgba,
     pub selected_shader: ShaderKind,
     pub sdf_code: String,
     pub fill_code: String,
     pub bounds_length: f32,
     pub params: Vec4,
     pub blend_mode: BlendMode,
+    pub expressions: BTreeMap<ShapeField, Expression>,
+    /// The outline drawn in the viewport, the SDF code is generated from it.
+    pub drawing: Option<Drawing>,
+    /// The text whose glyphs the SDF code is generated from.
+    pub text: Option<Text>,
+    /// Position, rotation and scale are relative to the parent.
+    pub parent: Option<ShapeId>,
+    /// Shows the distance view instead of the fill, see [`DistanceView`].
+    pub show_distance: bool,
+}
+
+impl ShapeState {
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("shape_{}", self.id)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// A numeric field of [`ShapeState`] that can be driven by an [`Expression`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShapeField {
+    PositionX,
+    PositionY,
+    PositionZ,
+    Rotation,
+    Scale,
+    ParamsX,
+    ParamsY,
+    ParamsZ,
+    ParamsW,
+    ColorR,
+    ColorG,
+    ColorB,
+    ColorA,
+}
+
+impl ShapeField {
+    pub const ALL: [ShapeField; 13] = [
+        ShapeField::PositionX,
+        ShapeField::PositionY,
+        ShapeField::PositionZ,
+        ShapeField::Rotation,
+        ShapeField::Scale,
+        ShapeField::ParamsX,
+        ShapeField::ParamsY,
+        ShapeField::ParamsZ,
+        ShapeField::ParamsW,
+        ShapeField::ColorR,
+        ShapeField::ColorG,
+        ShapeField::ColorB,
+        ShapeField::ColorA,
+    ];
+
+    /// Name used for referencing the field of another shape in an expression.
+    pub fn name(self) -> &'static str {
+        match self {
+            ShapeField::PositionX => "x",
+            ShapeField::PositionY => "y",
+            ShapeField::PositionZ => "z",
+            ShapeField::Rotation => "rotation",
+            ShapeField::Scale => "scale",
+            ShapeField::ParamsX => "params_x",
+            ShapeField::ParamsY => "params_y",
+            ShapeField::ParamsZ => "params_z",
+            ShapeField::ParamsW => "params_w",
+            ShapeField::ColorR => "color_r",
+            ShapeField::ColorG => "color_g",
+            ShapeField::ColorB => "color_b",
+            ShapeField::ColorA => "color_a",
+        }
+    }
+
+    /// Color channels are normalized to `0.0..=1.0`.
+    pub fn get(self, shape_state: &ShapeState) -> f32 {
+        match self {
+            ShapeField::PositionX => shape_state.position.x,
+            ShapeField::PositionY => shape_state.position.y,
+            ShapeField::PositionZ => shape_state.position.z,
+            ShapeField::Rotation => shape_state.rotation,
+            ShapeField::Scale => shape_state.scale,
+            ShapeField::ParamsX => shape_state.params.x,
+            ShapeField::ParamsY => shape_state.params.y,
+            ShapeField::ParamsZ => shape_state.params.z,
+            ShapeField::ParamsW => shape_state.params.w,
+            ShapeField::ColorR => shape_state.color.red,
+            ShapeField::ColorG => shape_state.color.green,
+            ShapeField::ColorB => shape_state.color.blue,
+            ShapeField::ColorA => shape_state.color.alpha,
+        }
+    }
+
+    pub fn set(self, shape_state: &mut ShapeState, value: f32) {
+        match self {
+            ShapeField::PositionX => shape_state.position.x = value,
+            ShapeField::PositionY => shape_state.position.y = value,
+            ShapeField::PositionZ => shape_state.position.z = value,
+            ShapeField::Rotation => shape_state.rotation = value,
+            ShapeField::Scale => shape_state.scale = value,
+            ShapeField::ParamsX => shape_state.params.x = value,
+            ShapeField::ParamsY => shape_state.params.y = value,
+            ShapeField::ParamsZ => shape_state.params.z = value,
+            ShapeField::ParamsW => shape_state.params.w = value,
+            ShapeField::ColorR => shape_state.color.red = value.clamp(0.0, 1.0),
+            ShapeField::ColorG => shape_state.color.green = value.clamp(0.0, 1.0),
+            ShapeField::ColorB => shape_state.color.blue = value.clamp(0.0, 1.0),
+            ShapeField::ColorA => shape_state.color.alpha = value.clamp(0.0, 1.0),
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -0,0 +1,339 @@
+use std::{collections::VecDeque, f32::consts::TAU};
+
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_smud::SmudShape;
+
+use crate::{
+    clock::Clock,
+    consts,
+    state::{ShapeId, ShapeState},
+};
+
+/// How the instances are arranged around the shape they copy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StressPattern {
+    Grid,
+    Scatter,
+    /// A sunflower spiral that keeps the density constant.
+    Spiral,
+}
+
+/// A change of the instances requested by the UI, applied by [`update_stress_test`].
+enum StressRequest {
+    Spawn(ShapeId),
+    Clear,
+}
+
+/// Spawns many copies of a shape for measuring how expensive it is to render. The copies aren't
+/// shapes with their own state, so they don't show up in the outliner and can't be selected.
+#[derive(Resource)]
+pub struct StressTest {
+    pub count: u32,
+    pub pattern: StressPattern,
+    /// Distance between neighboring instances in world space.
+    pub spacing: f32,
+    pub random_color: bool,
+    pub random_rotation: bool,
+    /// Maximum random offset added to each param, 0 keeps the params of the shape.
+    pub params_range: f32,
+    /// Rotates each instance with its own speed.
+    pub animate: bool,
+    request: Option<StressRequest>,
+    /// The copied shape and the number of instances.
+    source: Option<(ShapeId, usize)>,
+    /// Frames that are left out of the statistics after spawning, while pipelines are compiled.
+    warmup: u32,
+    /// Frame times in milliseconds, the most recent last.
+    frame_times: VecDeque<f32>,
+    /// Average frame time before the instances were spawned.
+    baseline: Option<f32>,
+}
+
+impl Default for StressTest {
+    fn default() -> Self {
+        Self {
+            count: consts::DEFAULT_STRESS_TEST_COUNT,
+            pattern: StressPattern::Grid,
+            spacing: consts::DEFAULT_STRESS_TEST_SPACING,
+            random_color: false,
+            random_rotation: false,
+            params_range: 0.0,
+            animate: false,
+            request: None,
+            source: None,
+            warmup: 0,
+            frame_times: VecDeque::new(),
+            baseline: None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StressInstance {
+    rotation: f32,
+    /// Speed of the animated rotation in radians per second.
+    speed: f32,
+}
+
+pub fn update_stress_test(
+    mut commands: Commands,
+    mut stress_test: ResMut<StressTest>,
+    time: Res<Time>,
+    clock: Res<Clock>,
+    mut instance_query: Query<
+        (Entity, &StressInstance, &mut Transform, &mut SmudShape),
+        Without<ShapeState>,
+    >,
+    shape_query: Query<(&ShapeState, &SmudShape, &GlobalTransform)>,
+) {
+    if stress_test.warmup > 0 {
+        stress_test.warmup -= 1;
+    } else {
+        stress_test
+            .frame_times
+            .push_back(time.delta_secs() * 1000.0);
+        if stress_test.frame_times.len() > consts::MAX_STRESS_TEST_SAMPLES {
+            stress_test.frame_times.pop_front();
+        }
+    }
+
+    if let Some(request) = stress_test.request.take() {
+        for (entity, _, _, _) in &instance_query {
+            commands.entity(entity).despawn();
+        }
+        // Frame times with instances of a previous spawn aren't a baseline
+        let baseline = match stress_test.source.take() {
+            Some(_) => stress_test.baseline.take(),
+            None => average(&stress_test.frame_times),
+        };
+        if let StressRequest::Spawn(id) = request
+            && let Some((shape_state, shape, world)) = shape_query
+                .iter()
+                .find(|(shape_state, _, _)| shape_state.id == id)
+        {
+            let instances = spawn_instances(&stress_test, shape_state, shape, world);
+            let count = instances.len();
+            commands.spawn_batch(instances);
+            stress_test.source = Some((id, count));
+            stress_test.baseline = baseline;
+        }
+        stress_test.frame_times.clear();
+        stress_test.warmup = consts::STRESS_TEST_WARMUP_FRAMES;
+        return;
+    }
+
+    // Instances follow the shaders of the copied shape when it's recompiled
+    let Some((source, _)) = stress_test.source else {
+        return;
+    };
+    let Some((_, source, _)) = shape_query
+        .iter()
+        .find(|(shape_state, _, _)| shape_state.id == source)
+    else {
+        return;
+    };
+    for (_, instance, mut transform, mut shape) in &mut instance_query {
+        if shape.sdf != source.sdf || shape.fill != source.fill {
+            shape.sdf = source.sdf.clone();
+            shape.fill = source.fill.clone();
+        }
+        if stress_test.animate {
+            transform.rotation =
+                Quat::from_rotation_z(instance.rotation + clock.elapsed * instance.speed);
+        }
+    }
+}
+
+fn spawn_instances(
+    stress_test: &StressTest,
+    shape_state: &ShapeState,
+    shape: &SmudShape,
+    world: &GlobalTransform,
+) -> Vec<(Transform, SmudShape, StressInstance)> {
+    let (scale, rotation, center) = world.to_scale_rotation_translation();
+    let rotation = rotation.to_euler(EulerRot::ZYX).0;
+    let count = stress_test.count as usize;
+    let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = count.div_ceil(columns);
+
+    (0..count)
+        .map(|index| {
+            // Every instance gets its own sequence of random numbers
+            let random = |channel: u32| random(index as u32 * 16 + channel);
+            let offset = match stress_test.pattern {
+                StressPattern::Grid => {
+                    let cell = Vec2::new((index % columns) as f32, (index / columns) as f32);
+                    cell - Vec2::new(columns as f32 - 1.0, rows as f32 - 1.0) / 2.0
+                }
+                StressPattern::Scatter => (Vec2::new(random(0), random(1)) - 0.5) * columns as f32,
+                StressPattern::Spiral => {
+                    // Consecutive instances are apart by the golden angle
+                    let angle = index as f32 * TAU * (1.0 - 1.0 / consts::GOLDEN_RATIO);
+                    Vec2::from_angle(angle) * (index as f32).sqrt()
+                }
+            } * stress_test.spacing;
+
+            let mut shape = shape.clone();
+            if stress_test.random_color {
+                let alpha = shape.color.alpha();
+                shape.color = Color::hsla(random(2) * 360.0, 0.7, 0.6, alpha);
+            }
+            if stress_test.params_range > 0.0 {
+                let offset = Vec4::new(random(3), random(4), random(5), random(6)) * 2.0 - 1.0;
+                shape.params = shape_state.params + offset * stress_test.params_range;
+            }
+            let rotation = if stress_test.random_rotation {
+                random(7) * TAU
+            } else {
+                rotation
+            };
+            let speed = (random(8) * 2.0 - 1.0) * consts::STRESS_TEST_MAX_SPEED;
+
+            (
+                Transform::from_translation(center + offset.extend(0.0))
+                    .with_rotation(Quat::from_rotation_z(rotation))
+                    .with_scale(scale),
+                shape,
+                StressInstance { rotation, speed },
+            )
+        })
+        .collect()
+}
+
+/// Returns a pseudo-random number between 0 and 1 that only depends on the seed.
+fn random(seed: u32) -> f32 {
+    // PCG hash, see "Hash Functions for GPU Rendering" by Jarzynski and Olano
+    let state = seed.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    let hash = (word >> 22) ^ word;
+    hash as f32 / u32::MAX as f32
+}
+
+fn average(values: &VecDeque<f32>) -> Option<f32> {
+    (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+}
+
+/// Shows the settings, the buttons for spawning and removing instances and the frame times.
+pub fn stress_test_ui(ui: &mut egui::Ui, stress_test: &mut StressTest, selected: Option<ShapeId>) {
+    egui::Grid::new("grid_stress_test")
+        .num_columns(2)
+        .spacing([40.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Instances:");
+            ui.add(
+                egui::DragValue::new(&mut stress_test.count)
+                    .range(1..=consts::MAX_STRESS_TEST_COUNT)
+                    .speed(10.0),
+            );
+            ui.end_row();
+
+            ui.label("Pattern:");
+            ui.horizontal(|ui| {
+                for pattern in [
+                    StressPattern::Grid,
+                    StressPattern::Scatter,
+                    StressPattern::Spiral,
+                ] {
+                    ui.selectable_value(&mut stress_test.pattern, pattern, format!("{pattern:?}"));
+                }
+                ui.label("spacing");
+                ui.add(
+                    egui::DragValue::new(&mut stress_test.spacing)
+                        .range(0.0..=f32::MAX)
+                        .speed(1.0),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Randomize:");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut stress_test.random_color, "Color");
+                ui.checkbox(&mut stress_test.random_rotation, "Rotation");
+                ui.label("params ±");
+                ui.add(
+                    egui::DragValue::new(&mut stress_test.params_range)
+                        .range(0.0..=f32::MAX)
+                        .speed(0.1),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Animation:");
+            ui.checkbox(&mut stress_test.animate, "Rotate instances");
+            ui.end_row();
+        });
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(selected.is_some(), egui::Button::new("Spawn"))
+            .on_hover_text("Replace the instances with copies of the selected shape")
+            .on_disabled_hover_text("Select a shape to copy")
+            .clicked()
+            && let Some(id) = selected
+        {
+            stress_test.request = Some(StressRequest::Spawn(id));
+        }
+        if ui
+            .add_enabled(
+                stress_test.source.is_some(),
+                egui::Button::new("Remove all"),
+            )
+            .clicked()
+        {
+            stress_test.request = Some(StressRequest::Clear);
+        }
+    });
+
+    let Some((source, count)) = stress_test.source else {
+        return;
+    };
+    ui.label(format!("{count} instances of shape {source}"));
+    let mut frame_times: Vec<f32> = stress_test.frame_times.iter().copied().collect();
+    let Some(mean) = average(&stress_test.frame_times) else {
+        ui.weak("Collecting frame times…");
+        return;
+    };
+    frame_times.sort_by(f32::total_cmp);
+    let percentile = |p: f32| frame_times[((frame_times.len() - 1) as f32 * p).round() as usize];
+
+    egui::Grid::new("grid_stress_test_stats")
+        .num_columns(2)
+        .spacing([40.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Frames:");
+            ui.label(frame_times.len().to_string());
+            ui.end_row();
+
+            ui.label("Frame time:");
+            ui.label(format!(
+                "mean {mean:.2} ms, min {:.2} ms, max {:.2} ms",
+                percentile(0.0),
+                percentile(1.0),
+            ));
+            ui.end_row();
+
+            ui.label("Percentiles:");
+            ui.label(format!(
+                "p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms",
+                percentile(0.5),
+                percentile(0.95),
+                percentile(0.99),
+            ));
+            ui.end_row();
+
+            if let Some(baseline) = stress_test.baseline {
+                ui.label("Without instances:");
+                ui.label(format!(
+                    "mean {baseline:.2} ms, {:+.3} ms per 1000 instances",
+                    (mean - baseline) / count.max(1) as f32 * 1000.0
+                ));
+                ui.end_row();
+            }
+        });
+    if ui.button("Reset statistics").clicked() {
+        stress_test.frame_times.clear();
+    }
+}
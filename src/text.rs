@@ -0,0 +1,547 @@
+use std::{collections::HashMap, fmt::Write, sync::Arc};
+
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Widget};
+use ttf_parser::{
+    GlyphId, Tag,
+    gpos::{PairAdjustment, PositioningSubtable},
+    kern,
+};
+
+use crate::{
+    consts,
+    file::{PendingFile, open_file_ui},
+    outline::{CurveMode, LINE_FUNCTIONS, Outline, OutlineBuilder, QUADRATIC_FUNCTIONS, Segment},
+    state::{ShapeId, ShapeState},
+};
+
+/// Lettering whose glyph outlines are turned into SDF code.
+#[derive(Clone)]
+pub struct Text {
+    pub string: String,
+    pub font: Font,
+    /// Height of an em in the local space of the shape.
+    pub size: f32,
+    pub curves: CurveMode,
+}
+
+#[derive(Clone)]
+pub struct Font {
+    pub name: String,
+    pub data: Arc<[u8]>,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            name: "Ubuntu Light".to_owned(),
+            data: Arc::from(epaint_default_fonts::UBUNTU_LIGHT),
+        }
+    }
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Self {
+            string: consts::DEFAULT_TEXT.to_owned(),
+            font: Font::default(),
+            size: consts::DEFAULT_TEXT_SIZE,
+            curves: CurveMode::Bezier,
+        }
+    }
+}
+
+/// Loads font files for the text of shapes.
+#[derive(Resource)]
+pub struct TextEditor {
+    font_path: String,
+    font_file: Option<(ShapeId, PendingFile)>,
+    error: Option<String>,
+}
+
+impl Default for TextEditor {
+    fn default() -> Self {
+        Self {
+            font_path: consts::DEFAULT_FONT_PATH.to_owned(),
+            font_file: None,
+            error: None,
+        }
+    }
+}
+
+/// The glyphs of a text, each distinct glyph is only stored once.
+struct TextLayout {
+    /// Outlines of the glyphs relative to their origin.
+    glyphs: Vec<Outline>,
+    /// Origin and glyph index of every visible character.
+    characters: Vec<(Vec2, usize)>,
+}
+
+/// Kerning between pairs of glyphs, read from the `kern` feature of the GPOS table or, if the font
+/// has none, from the legacy `kern` table.
+struct Kerning<'a> {
+    /// The pair adjustment subtables of each lookup of the `kern` feature.
+    lookups: Vec<Vec<PairAdjustment<'a>>>,
+    subtables: Vec<kern::Subtable<'a>>,
+}
+
+impl<'a> Kerning<'a> {
+    fn new(face: &ttf_parser::Face<'a>) -> Self {
+        let lookups: Vec<Vec<_>> = face
+            .tables()
+            .gpos
+            .map(|gpos| {
+                let mut indices: Vec<_> = gpos
+                    .features
+                    .into_iter()
+                    .filter(|feature| feature.tag == Tag::from_bytes(b"kern"))
+                    .flat_map(|feature| feature.lookup_indices)
+                    .collect();
+                // Each script can have its own `kern` feature with the same lookups
+                indices.sort_unstable();
+                indices.dedup();
+                indices
+                    .into_iter()
+                    .filter_map(|index| gpos.lookups.get(index))
+                    .map(|lookup| {
+                        lookup
+                            .subtables
+                            .into_iter()
+                            .filter_map(|subtable| match subtable {
+                                PositioningSubtable::Pair(pair) => Some(pair),
+                                _ => None,
+                            })
+                            .collect()
+                    })
+                    .filter(|pairs: &Vec<_>| !pairs.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Fonts with GPOS kerning often keep a `kern` table for older software
+        let subtables = match (lookups.is_empty(), face.tables().kern) {
+            (true, Some(kern)) => kern
+                .subtables
+                .into_iter()
+                .filter(|subtable| subtable.horizontal && !subtable.variable)
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self { lookups, subtables }
+    }
+
+    /// Returns the adjustment of the advance of `left` when followed by `right` in font units.
+    fn get(&self, left: GlyphId, right: GlyphId) -> i16 {
+        // Within a lookup only the first subtable that covers the pair applies
+        let gpos: i16 = self
+            .lookups
+            .iter()
+            .filter_map(|pairs| {
+                pairs
+                    .iter()
+                    .find_map(|pair| pair_kerning(pair, left, right))
+            })
+            .fold(0, i16::saturating_add);
+        let kern = self
+            .subtables
+            .iter()
+            .find_map(|subtable| subtable.glyphs_kerning(left, right))
+            .unwrap_or(0);
+        gpos.saturating_add(kern)
+    }
+}
+
+/// Returns the advance adjustment of the first glyph, or `None` if the subtable doesn't cover the
+/// pair.
+fn pair_kerning(pair: &PairAdjustment, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let index = pair.coverage().get(left)?;
+    let (first, _) = match pair {
+        PairAdjustment::Format1 { sets, .. } => sets.get(index)?.get(right)?,
+        PairAdjustment::Format2 {
+            classes, matrix, ..
+        } => matrix.get((classes.0.get(left), classes.1.get(right)))?,
+    };
+    Some(first.x_advance)
+}
+
+impl Text {
+    fn layout(&self) -> Result<TextLayout, String> {
+        let face = ttf_parser::Face::parse(&self.font.data, 0)
+            .map_err(|error| format!("{}: {error}", self.font.name))?;
+        let scale = self.size / f32::from(face.units_per_em());
+        let line_height = (f32::from(face.ascender()) - f32::from(face.descender())
+            + f32::from(face.line_gap()))
+            * scale;
+        let kerning = Kerning::new(&face);
+
+        let mut layout = TextLayout {
+            glyphs: Vec::new(),
+            characters: Vec::new(),
+        };
+        // Glyphs without an outline like spaces map to `None`
+        let mut glyph_indices = HashMap::<GlyphId, Option<usize>>::new();
+        for (line, string) in self.string.lines().enumerate() {
+            let mut origin = Vec2::new(0.0, -(line as f32) * line_height);
+            let mut previous = None;
+            for c in string.chars() {
+                // Missing characters are shown with the replacement glyph
+                let id = face.glyph_index(c).unwrap_or(GlyphId(0));
+                if let Some(previous) = previous {
+                    origin.x += f32::from(kerning.get(previous, id)) * scale;
+                }
+
+                let index = *glyph_indices.entry(id).or_insert_with(|| {
+                    let mut builder = GlyphBuilder(OutlineBuilder::new(
+                        self.curves,
+                        consts::TEXT_TOLERANCE / scale,
+                    ));
+                    face.outline_glyph(id, &mut builder)?;
+                    let mut outline = builder.0.build();
+                    if outline.is_empty() {
+                        return None;
+                    }
+                    outline.transform(|point| point * scale);
+                    layout.glyphs.push(outline);
+                    Some(layout.glyphs.len() - 1)
+                });
+                if let Some(index) = index {
+                    layout.characters.push((origin, index));
+                }
+
+                origin.x += f32::from(face.glyph_hor_advance(id).unwrap_or(0)) * scale;
+                previous = Some(id);
+            }
+        }
+
+        // Center the text at the origin
+        let center = layout.bounds().center();
+        if center.is_finite() {
+            for (origin, _) in &mut layout.characters {
+                *origin -= center;
+            }
+        }
+        Ok(layout)
+    }
+}
+
+impl TextLayout {
+    fn bounds(&self) -> Rect {
+        self.characters
+            .iter()
+            .map(|(origin, index)| {
+                let bounds = self.glyphs[*index].bounds();
+                Rect::from_corners(bounds.min + *origin, bounds.max + *origin)
+            })
+            .fold(Rect::EMPTY, |bounds, rect| bounds.union(rect))
+    }
+
+    /// Returns the WGSL code of an SDF that evaluates the segments of each glyph at the origin of
+    /// every character.
+    fn sdf_code(&self, comment: &str) -> String {
+        let mut code = format!(
+            "#define_import_path {}\n\n#import smud\n\n",
+            consts::TEXT_SDF_IMPORT_PATH
+        );
+        for line in comment.lines() {
+            let _ = writeln!(code, "// {line}");
+        }
+        code.push('\n');
+        if self.characters.is_empty() {
+            code.push_str("fn sdf(input: smud::SdfInput) -> f32 {\n    return 1e20;\n}\n");
+            return code;
+        }
+
+        let mut lines = Vec::new();
+        let mut quadratics = Vec::new();
+        let mut ranges = Vec::new();
+        for glyph in &self.glyphs {
+            let (line_start, quadratic_start) = (lines.len(), quadratics.len());
+            for segment in &glyph.segments {
+                match *segment {
+                    Segment::Line(a, b) => lines.push((a, b)),
+                    Segment::Quadratic(a, b, c) => quadratics.push((a, b, c)),
+                }
+            }
+            ranges.push([line_start, lines.len(), quadratic_start, quadratics.len()]);
+        }
+
+        if !lines.is_empty() {
+            let _ = writeln!(code, "const LINES = array<vec4<f32>, {}>(", lines.len());
+            for (a, b) in &lines {
+                let _ = writeln!(
+                    code,
+                    "    vec4({:?}, {:?}, {:?}, {:?}),",
+                    a.x, a.y, b.x, b.y
+                );
+            }
+            code.push_str(");\n\n");
+        }
+        if !quadratics.is_empty() {
+            let _ = writeln!(
+                code,
+                "const QUADRATICS = array<vec2<f32>, {}>(",
+                3 * quadratics.len()
+            );
+            for (a, b, c) in &quadratics {
+                let _ = writeln!(
+                    code,
+                    "    vec2({:?}, {:?}), vec2({:?}, {:?}), vec2({:?}, {:?}),",
+                    a.x, a.y, b.x, b.y, c.x, c.y
+                );
+            }
+            code.push_str(");\n\n");
+        }
+
+        code.push_str("// Ranges of the lines and curves of each glyph\n");
+        let _ = writeln!(code, "const GLYPHS = array<vec4<u32>, {}>(", ranges.len());
+        for [a, b, c, d] in &ranges {
+            let _ = writeln!(code, "    vec4({a}u, {b}u, {c}u, {d}u),");
+        }
+        code.push_str(");\n\n");
+        let _ = writeln!(
+            code,
+            "const GLYPH_BOUNDS = array<vec4<f32>, {}>(",
+            self.glyphs.len()
+        );
+        for glyph in &self.glyphs {
+            let bounds = glyph.bounds();
+            let _ = writeln!(
+                code,
+                "    vec4({:?}, {:?}, {:?}, {:?}),",
+                bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y
+            );
+        }
+        code.push_str(");\n\n");
+
+        code.push_str("// Origin and glyph of every character, including kerning\n");
+        let n = self.characters.len();
+        let _ = writeln!(code, "const ORIGINS = array<vec2<f32>, {n}>(");
+        for (origin, _) in &self.characters {
+            let _ = writeln!(code, "    vec2({:?}, {:?}),", origin.x, origin.y);
+        }
+        code.push_str(");\n\n");
+        let _ = writeln!(code, "const CHARACTERS = array<u32, {n}>(");
+        for (_, index) in &self.characters {
+            let _ = writeln!(code, "    {index}u,");
+        }
+        code.push_str(");\n\n");
+
+        let _ = write!(
+            code,
+            "fn sdf(input: smud::SdfInput) -> f32 {{\n    \
+                 var distance = 1e20;\n    \
+                 var winding = 0;\n    \
+                 for (var i = 0; i < {n}; i++) {{\n        \
+                     let glyph = CHARACTERS[i];\n        \
+                     let p = input.pos - ORIGINS[i];\n        \
+                     // Outside of its bounds a glyph doesn't change the winding number, so it's\n        \
+                     // skipped if it's farther away than the closest segment so far\n        \
+                     let bounds = GLYPH_BOUNDS[glyph];\n        \
+                     let outside = max(max(bounds.xy - p, p - bounds.zw), vec2(0.0));\n        \
+                     if length(outside) >= distance {{\n            \
+                         continue;\n        \
+                     }}\n        \
+                     let range = GLYPHS[glyph];\n"
+        );
+        if !lines.is_empty() {
+            code.push_str(
+                "        for (var j = range.x; j < range.y; j++) {\n            \
+                             let a = LINES[j].xy;\n            \
+                             let b = LINES[j].zw;\n            \
+                             distance = min(distance, line_distance(p, a, b));\n            \
+                             winding += line_winding(p, a, b);\n        \
+                         }\n",
+            );
+        }
+        if !quadratics.is_empty() {
+            code.push_str(
+                "        for (var j = range.z; j < range.w; j++) {\n            \
+                             let a = QUADRATICS[3u * j];\n            \
+                             let b = QUADRATICS[3u * j + 1u];\n            \
+                             let c = QUADRATICS[3u * j + 2u];\n            \
+                             distance = min(distance, quadratic_distance(p, a, b, c));\n            \
+                             winding += line_winding(p, a, c) + quadratic_region_winding(p, a, b, c);\n        \
+                         }\n",
+            );
+        }
+        code.push_str("    }\n    return select(distance, -distance, winding != 0);\n}\n");
+
+        code.push_str(LINE_FUNCTIONS);
+        if !quadratics.is_empty() {
+            code.push_str(QUADRATIC_FUNCTIONS);
+        }
+        code
+    }
+}
+
+/// Adds glyph outlines in font units to an [`OutlineBuilder`].
+struct GlyphBuilder(OutlineBuilder);
+
+impl ttf_parser::OutlineBuilder for GlyphBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(Vec2::new(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(Vec2::new(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.quad_to(Vec2::new(x1, y1), Vec2::new(x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0
+            .cubic_to(Vec2::new(x1, y1), Vec2::new(x2, y2), Vec2::new(x, y));
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Regenerates the SDF code of a text shape and fits its bounds to the text.
+fn update_text_code(shape_state: &mut ShapeState) -> Result<(), String> {
+    let Some(text) = &shape_state.text else {
+        return Ok(());
+    };
+    let layout = text.layout()?;
+    shape_state.sdf_code = layout.sdf_code(&format!(
+        "Text in {}, editing the text replaces this code",
+        text.font.name
+    ));
+
+    let bounds = layout.bounds();
+    if !bounds.is_empty() {
+        let extent = bounds.min.abs().max(bounds.max.abs()).max_element();
+        shape_state.bounds_length = 2.0 * extent * consts::IMPORTED_SHAPE_BOUNDS_FACTOR;
+    }
+    Ok(())
+}
+
+/// Shows the UI for editing the text of the shape, returns whether the SDF code was changed.
+pub fn text_ui(
+    ui: &mut egui::Ui,
+    shape_state: &mut ShapeState,
+    text_editor: &mut TextEditor,
+) -> bool {
+    let Some(text) = &mut shape_state.text else {
+        let clicked = ui
+            .button("🗛 Text")
+            .on_hover_text("Generate the SDF from the glyphs of a text")
+            .clicked();
+        if clicked {
+            shape_state.text = Some(Text::default());
+            shape_state.drawing = None;
+            text_editor.error = update_text_code(shape_state).err();
+        }
+        return clicked;
+    };
+
+    let mut changed = false;
+    if let Some((id, file)) = &text_editor.font_file
+        && *id == shape_state.id
+        && let Some(file) = file.take()
+    {
+        text_editor.font_file = None;
+        match file {
+            Ok(file) => {
+                text.font = Font {
+                    name: file.name,
+                    data: file.bytes.into(),
+                };
+                changed = true;
+            }
+            Err(error) => text_editor.error = Some(error),
+        }
+    }
+
+    let mut remove = false;
+    ui.vertical(|ui| {
+        changed |= ui
+            .add(egui::TextEdit::multiline(&mut text.string).desired_rows(1))
+            .changed();
+        ui.horizontal(|ui| {
+            ui.label(format!("Font: {}", text.font.name));
+            if ui.button("⟲").clicked() {
+                text.font = Font::default();
+                changed = true;
+            }
+        });
+        if let Some(file) = open_file_ui(ui, &mut text_editor.font_path, ".ttf,.otf") {
+            text_editor.font_file = Some((shape_state.id, file));
+        }
+        ui.horizontal(|ui| {
+            ui.label("Size");
+            changed |= egui::DragValue::new(&mut text.size)
+                .range(1.0..=f32::MAX)
+                .ui(ui)
+                .changed();
+            changed |= ui
+                .selectable_value(&mut text.curves, CurveMode::Bezier, "Bézier")
+                .changed();
+            changed |= ui
+                .selectable_value(&mut text.curves, CurveMode::Flatten, "Flatten")
+                .changed();
+        });
+        remove = ui.button("Remove text").clicked();
+        if let Some(error) = &text_editor.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    });
+
+    if remove {
+        // The generated SDF code is kept
+        shape_state.text = None;
+        text_editor.error = None;
+    } else if changed {
+        text_editor.error = update_text_code(shape_state).err();
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(face: &ttf_parser::Face, c: char) -> GlyphId {
+        face.glyph_index(c).unwrap()
+    }
+
+    #[test]
+    fn gpos_kerning_of_the_default_font() {
+        let face = ttf_parser::Face::parse(epaint_default_fonts::UBUNTU_LIGHT, 0).unwrap();
+        let kerning = Kerning::new(&face);
+        // Ubuntu has no `kern` table, only GPOS kerning
+        assert!(!kerning.lookups.is_empty());
+        assert!(kerning.subtables.is_empty());
+
+        assert_eq!(kerning.get(glyph(&face, 'A'), glyph(&face, 'V')), -50);
+        assert_eq!(kerning.get(glyph(&face, 'H'), glyph(&face, 'H')), 0);
+    }
+
+    #[test]
+    fn layout_applies_kerning_and_shares_glyphs() {
+        let text = |string: &str| Text {
+            string: string.to_owned(),
+            size: 1000.0,
+            ..default()
+        };
+        let face = ttf_parser::Face::parse(epaint_default_fonts::UBUNTU_LIGHT, 0).unwrap();
+        let scale = 1000.0 / f32::from(face.units_per_em());
+        let advance = f32::from(face.glyph_hor_advance(glyph(&face, 'A')).unwrap()) * scale;
+
+        let layout = text("AV").layout().unwrap();
+        assert_eq!(layout.glyphs.len(), 2);
+        let distance = layout.characters[1].0.x - layout.characters[0].0.x;
+        assert!(
+            (distance - (advance - 50.0 * scale)).abs() < 1e-3,
+            "{distance}"
+        );
+
+        // Spaces have no outline and repeated characters share their glyph
+        let layout = text("A A").layout().unwrap();
+        assert_eq!(layout.glyphs.len(), 1);
+        assert_eq!(layout.characters.len(), 2);
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+/// An edge of the sampling grid, either horizontal or vertical starting at a grid point.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Edge {
+    Horizontal(u32, u32),
+    Vertical(u32, u32),
+}
+
+/// Traces the contours where `value` crosses zero using marching squares.
+///
+/// The area is sampled with a grid of `resolution` cells, values outside of it are assumed to be
+/// positive, so all contours are closed. The returned polygons run counterclockwise around
+/// negative values.
+pub fn trace_contours(
+    area: Rect,
+    resolution: UVec2,
+    mut value: impl FnMut(Vec2) -> f32,
+) -> Vec<Vec<Vec2>> {
+    let cell_size = area.size() / resolution.as_vec2();
+    // One point more than cells and a border of padding on each side
+    let points = resolution + 3;
+    let position = |i: u32, j: u32| area.min + (UVec2::new(i, j).as_vec2() - 1.0) * cell_size;
+
+    let mut values = Vec::with_capacity((points.x * points.y) as usize);
+    for j in 0..points.y {
+        for i in 0..points.x {
+            let padding = i == 0 || j == 0 || i == points.x - 1 || j == points.y - 1;
+            let v = if padding {
+                f32::MAX
+            } else {
+                value(position(i, j))
+            };
+            values.push(if v.is_nan() { f32::MAX } else { v });
+        }
+    }
+    let value = |i: u32, j: u32| values[(j * points.x + i) as usize];
+
+    let crossing = |edge: Edge| {
+        let (a, b) = match edge {
+            Edge::Horizontal(i, j) => ((i, j), (i + 1, j)),
+            Edge::Vertical(i, j) => ((i, j), (i, j + 1)),
+        };
+        let (va, vb) = (value(a.0, a.1), value(b.0, b.1));
+        let t = (va / (va - vb)).clamp(0.0, 1.0);
+        position(a.0, a.1).lerp(position(b.0, b.1), t)
+    };
+
+    // Segments are directed so the negative values are on their left
+    let mut starts = Vec::new();
+    let mut next = HashMap::new();
+    for j in 0..points.y - 1 {
+        for i in 0..points.x - 1 {
+            let corners = [
+                value(i, j),
+                value(i + 1, j),
+                value(i + 1, j + 1),
+                value(i, j + 1),
+            ];
+            let case = corners
+                .iter()
+                .enumerate()
+                .fold(0, |case, (bit, v)| case | ((*v < 0.0) as u8) << bit);
+            let center_inside = corners.iter().sum::<f32>() < 0.0;
+
+            let bottom = Edge::Horizontal(i, j);
+            let top = Edge::Horizontal(i, j + 1);
+            let left = Edge::Vertical(i, j);
+            let right = Edge::Vertical(i + 1, j);
+            let segments: &[(Edge, Edge)] = match case {
+                1 => &[(bottom, left)],
+                2 => &[(right, bottom)],
+                3 => &[(right, left)],
+                4 => &[(top, right)],
+                5 if center_inside => &[(bottom, right), (top, left)],
+                5 => &[(bottom, left), (top, right)],
+                6 => &[(top, bottom)],
+                7 => &[(top, left)],
+                8 => &[(left, top)],
+                9 => &[(bottom, top)],
+                10 if center_inside => &[(left, bottom), (right, top)],
+                10 => &[(right, bottom), (left, top)],
+                11 => &[(right, top)],
+                12 => &[(left, right)],
+                13 => &[(bottom, right)],
+                14 => &[(left, bottom)],
+                _ => &[],
+            };
+            for (from, to) in segments {
+                starts.push(*from);
+                next.insert(*from, *to);
+            }
+        }
+    }
+
+    let mut contours = Vec::new();
+    for start in starts {
+        let mut contour = Vec::new();
+        let mut edge = start;
+        while let Some(to) = next.remove(&edge) {
+            contour.push(crossing(edge));
+            edge = to;
+        }
+        if contour.len() >= 3 {
+            contours.push(contour);
+        }
+    }
+    contours
+}
+
+/// Removes points of a closed polygon that deviate less than `tolerance` from the simplified
+/// polygon (Ramer–Douglas–Peucker).
+pub fn simplify(polygon: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    if polygon.len() < 4 {
+        return polygon.to_vec();
+    }
+
+    // Split the closed polygon into two open polylines at the point farthest from the first one
+    let farthest = (1..polygon.len())
+        .max_by(|a, b| {
+            let da = polygon[*a].distance_squared(polygon[0]);
+            let db = polygon[*b].distance_squared(polygon[0]);
+            da.total_cmp(&db)
+        })
+        .unwrap_or(0);
+
+    let mut keep = vec![false; polygon.len()];
+    keep[0] = true;
+    keep[farthest] = true;
+    let mut closed = polygon.to_vec();
+    closed.push(polygon[0]);
+    simplify_polyline(&closed, 0, farthest, tolerance, &mut keep);
+    simplify_polyline(&closed, farthest, polygon.len(), tolerance, &mut keep);
+
+    polygon
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+fn simplify_polyline(
+    points: &[Vec2],
+    first: usize,
+    last: usize,
+    tolerance: f32,
+    keep: &mut [bool],
+) {
+    if last <= first + 1 {
+        return;
+    }
+
+    let (a, b) = (points[first], points[last]);
+    let distance = |p: Vec2| {
+        let ab = b - a;
+        let t = ((p - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+        if t.is_nan() {
+            p.distance(a)
+        } else {
+            p.distance(a + ab * t)
+        }
+    };
+    let (index, max_distance) = (first + 1..last)
+        .map(|i| (i, distance(points[i])))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((first, 0.0));
+
+    if max_distance > tolerance {
+        keep[index % keep.len()] = true;
+        simplify_polyline(points, first, index, tolerance, keep);
+        simplify_polyline(points, index, last, tolerance, keep);
+    }
+}
+
+/// A cubic Bézier segment starting at the end of the previous segment.
+pub struct CubicSegment {
+    pub control1: Vec2,
+    pub control2: Vec2,
+    pub end: Vec2,
+}
+
+/// Fits a closed smooth curve through the points of a polygon using Catmull–Rom splines.
+///
+/// At points where the polygon turns sharper than `corner_angle`, the curve keeps a corner.
+pub fn fit_beziers(polygon: &[Vec2], corner_angle: f32) -> Vec<CubicSegment> {
+    let n = polygon.len();
+    let point = |i: usize| polygon[i % n];
+    let tangent = |i: usize| {
+        let (previous, current, next) = (point(i + n - 1), point(i), point(i + 1));
+        let turn = (current - previous).angle_to(next - current).abs();
+        if turn > corner_angle {
+            Vec2::ZERO
+        } else {
+            (next - previous) / 6.0
+        }
+    };
+
+    (0..n)
+        .map(|i| CubicSegment {
+            control1: point(i) + tangent(i),
+            control2: point(i + 1) - tangent(i + 1),
+            end: point(i + 1),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts;
+
+    use super::*;
+
+    /// Twice the signed area, positive for counterclockwise polygons.
+    fn signed_area(polygon: &[Vec2]) -> f32 {
+        (0..polygon.len())
+            .map(|i| polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]))
+            .sum()
+    }
+
+    fn area() -> Rect {
+        Rect::from_center_size(Vec2::ZERO, Vec2::splat(20.0))
+    }
+
+    #[test]
+    fn circle_contour() {
+        let contours = trace_contours(area(), UVec2::splat(40), |pos| pos.length() - 6.0);
+        assert_eq!(contours.len(), 1);
+        for point in &contours[0] {
+            assert!((point.length() - 6.0).abs() < 0.05);
+        }
+        assert!(signed_area(&contours[0]) > 0.0);
+    }
+
+    #[test]
+    fn ring_contours() {
+        let contours = trace_contours(area(), UVec2::splat(40), |pos| {
+            (pos.length() - 5.0).abs() - 2.0
+        });
+        assert_eq!(contours.len(), 2);
+        let (outer, inner) = if contours[0][0].length() > contours[1][0].length() {
+            (&contours[0], &contours[1])
+        } else {
+            (&contours[1], &contours[0])
+        };
+        // The hole runs clockwise, so the negative values stay on the left
+        assert!(signed_area(outer) > 0.0);
+        assert!(signed_area(inner) < 0.0);
+    }
+
+    #[test]
+    fn contours_are_closed_at_the_area_border() {
+        let contours = trace_contours(area(), UVec2::splat(10), |_| -1.0);
+        assert_eq!(contours.len(), 1);
+        let bounds = contours[0]
+            .iter()
+            .fold(Rect::EMPTY, |bounds, point| bounds.union_point(*point));
+        assert!(bounds.min.cmple(area().min).all() && bounds.max.cmpge(area().max).all());
+    }
+
+    #[test]
+    fn simplify_keeps_corners() {
+        let square: Vec<_> = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.01),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(0.0, 2.0),
+            Vec2::new(0.0, 1.0),
+        ]
+        .into();
+        assert_eq!(
+            simplify(&square, 0.1),
+            [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(2.0, 0.01),
+                Vec2::new(2.0, 2.0),
+                Vec2::new(0.0, 2.0),
+            ]
+        );
+        assert_eq!(simplify(&square, 0.001).len(), 5);
+    }
+
+    #[test]
+    fn fit_beziers_keeps_sharp_corners() {
+        let square = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        let segments = fit_beziers(&square, consts::FRAC_PI_3);
+        assert_eq!(segments.len(), 4);
+        for (i, segment) in segments.iter().enumerate() {
+            let (start, end) = (square[i], square[(i + 1) % 4]);
+            assert_eq!(segment.end, end);
+            assert_eq!(segment.control1, start);
+            assert_eq!(segment.control2, end);
+        }
+    }
+
+    #[test]
+    fn fit_beziers_follows_smooth_polygons() {
+        let polygon: Vec<_> = (0..32)
+            .map(|i| Vec2::from_angle(i as f32 / 32.0 * consts::TAU) * 10.0)
+            .collect();
+        let segments = fit_beziers(&polygon, consts::FRAC_PI_3);
+        for (i, segment) in segments.iter().enumerate() {
+            // The midpoint of the curve bulges outwards onto the circle
+            let start = polygon[i];
+            let middle =
+                (start + 3.0 * segment.control1 + 3.0 * segment.control2 + segment.end) / 8.0;
+            assert!((middle.length() - 10.0).abs() < 0.01);
+        }
+    }
+}
@@ -1,7 +1,18 @@
-use bevy::prelude::*;
+use bevy::{color::ColorToPacked, prelude::*};
 use bevy_egui::egui;
 
 pub fn convert_color(color: egui::Color32) -> Color {
     let [r, g, b, a] = color.to_array();
     Color::srgba_u8(r, g, b, a)
 }
+
+/// Shows a color picker for an unmultiplied color, unlike [`egui::Color32`] it keeps the color
+/// channels if alpha is 0.
+pub fn color_edit_button(ui: &mut egui::Ui, color: &mut Srgba) -> egui::Response {
+    let mut srgba = color.to_u8_array();
+    let response = ui.color_edit_button_srgba_unmultiplied(&mut srgba);
+    if response.changed() {
+        *color = Srgba::from_u8_array(srgba);
+    }
+    response
+}
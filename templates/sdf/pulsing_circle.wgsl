@@ -0,0 +1,11 @@
+#define_import_path smud_demo::sdf::pulsing_circle
+
+#import smud
+#import smud_demo::globals
+
+fn sdf(input: smud::SdfInput) -> f32 {
+    let p = input.pos;
+    let t = globals::time();
+    let r = input.params.x + 100. + 20. * sin(t * 3.);
+    return smud::sd_circle(p, r);
+}